@@ -4,10 +4,14 @@ use crate::{
     tf2::{
         lobby::{Lobby, shared_lobby::SharedLobby},
         logfile::LogLine,
-        rcon::G15DumpPlayerOutput,
+        rcon::{G15DumpPlayerOutput, RconState},
+        replay::recorder::SessionRecorder,
         steamapi::SteamApiMsg,
     },
-    tf2bd::{Tf2bdMsg, models::PlayerAttribute},
+    tf2bd::{
+        Tf2bdMsg,
+        models::{PlayerAttribute, Rule},
+    },
 };
 use bus::Bus;
 
@@ -15,6 +19,9 @@ pub struct AppBus {
     pub logfile_bus: Bus<LogLine>,
     pub rcon_bus: Bus<String>,
     pub g15_report_bus: Bus<G15DumpPlayerOutput>,
+
+    /// Changes in the RCON connection to TF2
+    pub rcon_state_bus: Bus<RconState>,
     pub steamapi_bus: Bus<SteamApiMsg>,
     pub tf2bd_bus: Bus<Tf2bdMsg>,
 
@@ -25,6 +32,12 @@ pub struct AppBus {
     /// Shared lobby state accessible from all threads.
     /// Use shared_lobby.get() to get a copy of the current lobby state.
     pub shared_lobby: SharedLobby,
+
+    /// Records the raw data from TF2 when recording is enabled in the settings
+    pub session_recorder: Option<SessionRecorder>,
+
+    /// Set when a recorded session is shown instead of TF2
+    pub replaying: bool,
 }
 
 impl Default for AppBus {
@@ -42,10 +55,13 @@ impl AppBus {
             logfile_bus: Bus::new(10000),
             rcon_bus: Bus::new(100),
             g15_report_bus: Bus::new(100),
+            rcon_state_bus: Bus::new(100),
             steamapi_bus: Bus::new(10000),
             tf2bd_bus: Bus::new(10000),
             app_event_bus: Bus::new(1000),
             shared_lobby: SharedLobby::new(initial_lobby),
+            session_recorder: None,
+            replaying: false,
         }
     }
 
@@ -65,9 +81,28 @@ pub enum AppEventMsg {
     /// Sets or removes a flag(Cheater, Exploiter, etc) for a SteamID
     SetPlayerFlag {
         steamid: SteamID,
-        name: String,
+
+        /// The name to store as last seen, None if it is unknown
+        name: Option<String>,
         flag: PlayerAttribute,
         enable: bool,
+
+        /// Why the flag is set, stored as proof together with the player's stats and chat
+        reason: Option<String>,
     },
     UpdatedSettings(AppSettings),
+
+    /// A TF2BD list file has been replaced with a newer version and should be reloaded
+    PlayerListUpdated {
+        filename: String,
+    },
+
+    /// Appends a rule to the rules section of a TF2BD list file
+    AddRule {
+        filename: String,
+        rule: Rule,
+    },
+
+    /// The window was closed, the lobby thread saves the session and stops
+    Exit,
 }
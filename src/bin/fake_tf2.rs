@@ -0,0 +1,80 @@
+//
+// A fake TF2 to run the app without the game, see src/tf2/fake_tf2.rs
+//
+//   cargo run --features fake-tf2 --bin fake_tf2 -- <script.json> [--port <port>]
+//             [--password <password>] [--log-file <file>]
+//
+// Serves RCON and writes the console log like TF2 would, and prints the commands it receives.
+// Runs until stopped with Ctrl+C.
+//
+
+// Shared with the app's tests, which run the app against it
+#[path = "../tf2/fake_tf2.rs"]
+mod fake_tf2;
+
+use fake_tf2::{FakeTf2, FakeTf2Script};
+use std::{error::Error, thread::sleep, time::Duration};
+
+const USAGE: &str = "Usage:
+  fake_tf2 <script.json> [--port <port>] [--password <password>] [--log-file <file>]
+
+Runs a fake TF2 from a script like src/tf2/fake_tf2_fixture.json until stopped with Ctrl+C.";
+
+fn main() {
+    simple_logger::SimpleLogger::new().init().unwrap();
+
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if let Err(e) = run(&args) {
+        eprintln!("{}\n\n{}", e, USAGE);
+        std::process::exit(1);
+    }
+}
+
+fn run(args: &[String]) -> Result<(), Box<dyn Error>> {
+    let Some(filename) = args.first().filter(|x| !x.starts_with("--")) else {
+        return Err("Missing filename".into());
+    };
+
+    let mut port = 40434;
+    let mut password = "rconpwd".to_string();
+    let mut log_file = std::env::temp_dir().join("tf2monitor-fake-tf2-console.log");
+
+    let mut rest = args[1..].iter();
+    while let Some(name) = rest.next() {
+        let value = rest
+            .next()
+            .ok_or_else(|| format!("Missing value for {}", name))?;
+        match name.as_str() {
+            "--port" => {
+                port = value
+                    .parse()
+                    .map_err(|_| format!("Invalid port '{}'", value))?;
+            }
+            "--password" => password.clone_from(value),
+            "--log-file" => log_file = value.into(),
+            _ => return Err(format!("Unknown option {}", name).into()),
+        }
+    }
+
+    let script = FakeTf2Script::load(filename)?;
+    let fake_tf2 = FakeTf2::start(script, port, &password, &log_file)?;
+    println!(
+        "Fake TF2 is serving RCON on port {} and writing the console log to {}",
+        fake_tf2.port(),
+        log_file.display()
+    );
+
+    let mut printed = 0;
+    loop {
+        sleep(Duration::from_millis(500));
+
+        let commands = fake_tf2.commands();
+        for cmd in commands[printed..]
+            .iter()
+            .filter(|cmd| *cmd != "g15_dumpplayer")
+        {
+            println!("RCON: {}", cmd);
+        }
+        printed = commands.len();
+    }
+}
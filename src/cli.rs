@@ -0,0 +1,214 @@
+//
+// Command line commands that run without the GUI
+//
+//   tf2monitor --export-flags <file> [--attributes cheater,bot] [--source <text>] [--since-days <days>]
+//              [--include-suggestions true|false] [--title <title>] [--description <text>]
+//              [--author <name>]... [--update-url <url>] [--format tf2bd|mac]
+//   tf2monitor --import-flags <file> [--source <name>] [--policy keep|overwrite|merge] [--format tf2bd|mac]
+//   tf2monitor --replay <session.jsonl> [--speed <factor>]
+//
+
+use crate::{
+    db::db::DbPool,
+    models::app_settings::AppSettings,
+    tf2::replay::{ArchiveEntry, load_archive},
+    tf2bd::{
+        flag_exchange::{self, ConflictPolicy, ExportFilter},
+        formats::ListFormat,
+        models::{FileInfo, PlayerAttribute},
+    },
+    utils::BoxResult,
+};
+use chrono::Utc;
+
+const USAGE: &str = "Usage:
+  tf2monitor --export-flags <file> [--attributes cheater,bot] [--source <text>] [--since-days <days>]
+             [--include-suggestions true|false] [--title <title>] [--description <text>]
+             [--author <name>]... [--update-url <url>] [--format tf2bd|mac]
+  tf2monitor --import-flags <file> [--source <name>] [--policy keep|overwrite|merge] [--format tf2bd|mac]
+  tf2monitor --replay <session.jsonl> [--speed <factor>]
+
+Flags from suggestion lists and rules are only exported with --include-suggestions true.
+The format of an imported file is detected from its content when --format is not given.
+--replay starts the app with a session recorded to the sessions folder instead of TF2.";
+
+/// Runs the command given on the command line, if any.
+/// Returns the exit code when a command was run and the app should not start.
+pub fn run(settings: &AppSettings, db: &DbPool) -> Option<i32> {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.is_empty() {
+        return None;
+    }
+
+    let result = match args[0].as_str() {
+        "--export-flags" => export_flags(db, &args[1..]),
+        "--import-flags" => import_flags(settings, db, &args[1..]),
+        // Starts the app, see replay_options
+        "--replay" => return None,
+        "--help" | "-h" => {
+            println!("{}", USAGE);
+            return Some(0);
+        }
+        _ => return None,
+    };
+
+    match result {
+        Ok(message) => {
+            println!("{}", message);
+            Some(0)
+        }
+        Err(e) => {
+            eprintln!("{}\n\n{}", e, USAGE);
+            Some(1)
+        }
+    }
+}
+
+/// A recorded session to show instead of the running TF2
+pub struct ReplayOptions {
+    pub archive: Vec<ArchiveEntry>,
+
+    /// 1.0 replays at the speed it was recorded
+    pub speed: f64,
+}
+
+/// The replay options when the app was started with --replay
+pub fn replay_options() -> BoxResult<Option<ReplayOptions>> {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.first().map(String::as_str) != Some("--replay") {
+        return Ok(None);
+    }
+
+    let (filename, options) = parse_options(&args[1..])?;
+    let mut speed = 1.0;
+    for (name, value) in options {
+        match name.as_str() {
+            "--speed" => {
+                speed = value
+                    .parse()
+                    .ok()
+                    .filter(|speed: &f64| *speed > 0.0)
+                    .ok_or_else(|| format!("Invalid speed '{}'", value))?;
+            }
+            _ => return Err(format!("Unknown option {}", name).into()),
+        }
+    }
+
+    let archive = load_archive(&filename)?;
+    log::info!("Replaying {} at {}x speed", filename, speed);
+
+    Ok(Some(ReplayOptions { archive, speed }))
+}
+
+/// The filename and the options after it as (name, value) pairs
+fn parse_options(args: &[String]) -> BoxResult<(String, Vec<(String, String)>)> {
+    let Some(filename) = args.first().filter(|x| !x.starts_with("--")) else {
+        return Err("Missing filename".into());
+    };
+
+    let mut options = Vec::new();
+    let mut rest = args[1..].iter();
+    while let Some(name) = rest.next() {
+        if !name.starts_with("--") {
+            return Err(format!("Unexpected argument '{}'", name).into());
+        }
+        let value = rest
+            .next()
+            .ok_or_else(|| format!("Missing value for {}", name))?;
+        options.push((name.clone(), value.clone()));
+    }
+
+    Ok((filename.clone(), options))
+}
+
+fn export_flags(db: &DbPool, args: &[String]) -> BoxResult<String> {
+    let (filename, options) = parse_options(args)?;
+
+    let mut filter = ExportFilter::default();
+    let mut file_info = FileInfo::default();
+    let mut format = ListFormat::Tf2bd;
+
+    for (name, value) in options {
+        match name.as_str() {
+            "--attributes" => {
+                for attribute in value.split(',').map(str::trim).filter(|x| !x.is_empty()) {
+                    filter.attributes.insert(parse_attribute(attribute)?);
+                }
+            }
+            "--source" => filter.source = value,
+            "--since-days" => {
+                let days: i64 = value
+                    .parse()
+                    .map_err(|_| format!("Invalid number of days '{}'", value))?;
+                filter.seen_since = Some(Utc::now().timestamp() - days * 24 * 60 * 60);
+            }
+            "--include-suggestions" => {
+                filter.include_suggestions = value
+                    .parse()
+                    .map_err(|_| format!("Invalid value '{}', expected true or false", value))?;
+            }
+            "--title" => file_info.title = value,
+            "--description" => file_info.description = value,
+            "--author" => file_info.authors.push(value),
+            "--update-url" => file_info.update_url = value,
+            "--format" => format = parse_format(&value)?,
+            _ => return Err(format!("Unknown option {}", name).into()),
+        }
+    }
+
+    let mut conn = db.get()?;
+    let players = flag_exchange::export_to_file(&mut conn, &filter, file_info, &filename, format)?;
+
+    Ok(format!("Exported {} players to {}", players, filename))
+}
+
+fn import_flags(settings: &AppSettings, db: &DbPool, args: &[String]) -> BoxResult<String> {
+    let (filename, options) = parse_options(args)?;
+
+    let mut source = None;
+    let mut policy = ConflictPolicy::default();
+    let mut format = None;
+
+    for (name, value) in options {
+        match name.as_str() {
+            "--source" => source = Some(value),
+            "--policy" => {
+                policy = ConflictPolicy::parse(&value)
+                    .ok_or_else(|| format!("Unknown conflict policy '{}'", value))?;
+            }
+            "--format" => format = Some(parse_format(&value)?),
+            _ => return Err(format!("Unknown option {}", name).into()),
+        }
+    }
+
+    let source = match source {
+        Some(source) => source,
+        None => flag_exchange::default_import_source(&filename),
+    };
+
+    let mut conn = db.get()?;
+    let summary = flag_exchange::import_from_file(
+        &mut conn,
+        &filename,
+        &source,
+        policy,
+        format,
+        &settings.attribute_mapping,
+    )?;
+
+    Ok(format!(
+        "Imported {} as '{}': {}",
+        filename, source, summary
+    ))
+}
+
+/// Accepts both the TF2BD name (racist) and the TF2Monitor name (Toxic) of an attribute
+fn parse_attribute(name: &str) -> BoxResult<PlayerAttribute> {
+    flag_exchange::flag_attribute(name)
+        .or_else(|| PlayerAttribute::from_tf2bd_name(&name.to_lowercase()))
+        .ok_or_else(|| format!("Unknown attribute '{}'", name).into())
+}
+
+fn parse_format(name: &str) -> BoxResult<ListFormat> {
+    ListFormat::parse(name).ok_or_else(|| format!("Unknown list format '{}'", name).into())
+}
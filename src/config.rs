@@ -6,9 +6,28 @@ use std::time::Duration;
 pub const RCON_DELAY: Duration = Duration::from_millis(100);
 pub const RCON_LOOP_DELAY: Duration = Duration::from_millis(2000);
 
+/// How long to wait before connecting again when TF2 can't be reached, doubled after each failure
+pub const RCON_RECONNECT_MIN_DELAY: Duration = Duration::from_secs(2);
+pub const RCON_RECONNECT_MAX_DELAY: Duration = Duration::from_secs(60);
+
 /// Lobby thread delay
 pub const LOBBY_LOOP_DELAY: Duration = Duration::from_millis(20);
 
+/// How often the lobby thread stores the current match session and roster in the database
+pub const MATCH_SESSION_SAVE_PERIOD: Duration = Duration::from_secs(30);
+
+/// Longest time between two g15_dumpplayer outputs that counts as time spent in a lobby with a player
+pub const ENCOUNTER_MAX_GAP: Duration = Duration::from_secs(10);
+
+/// How long the kicker waits before calling another kick vote on a player whose vote failed
+pub const FAILED_KICK_VOTE_RETRY_DELAY: Duration = Duration::from_secs(5 * 60);
+
+/// How long TF2 makes us wait between calling two votes (sv_vote_creation_timer)
+pub const VOTE_CREATION_COOLDOWN: Duration = Duration::from_secs(120);
+
+/// How long the kicker waits after a party member called a vote, so we don't waste ours on the same target
+pub const PARTY_VOTE_BACKOFF: Duration = Duration::from_secs(30);
+
 /// Steam API thread delays
 pub const STEAMAPI_LOOP_DELAY: Duration = Duration::from_millis(100);
 pub const STEAMAPI_RETRY_DELAY: Duration = Duration::from_millis(5000);
@@ -20,9 +39,19 @@ pub const REPUTATION_LOOP_DELAY: Duration = Duration::from_millis(100);
 pub const LOGFILE_LOOP_DELAY: Duration = Duration::from_millis(1000);
 pub const LOGFILE_FILE_NOT_EXIST_DELAY: Duration = Duration::from_millis(10 * 1000);
 
+/// Longest unfinished line kept between reads, console lines are far shorter
+pub const LOGFILE_MAX_PARTIAL_LINE: usize = 64 * 1024;
+
+/// Replay thread delay
+pub const REPLAY_LOOP_DELAY: Duration = Duration::from_millis(50);
+
 /// TF2BD thread delay
 pub const TF2BD_LOOP_DELAY: Duration = Duration::from_millis(50);
 
+/// TF2BD list updater delays
+pub const TF2BD_LIST_UPDATE_LOOP_DELAY: Duration = Duration::from_millis(1000);
+pub const TF2BD_LIST_UPDATE_PERIOD: Duration = Duration::from_secs(60 * 60);
+
 /// GUI delays
 pub const GUI_SLEEP_DELAY: Duration = Duration::from_millis(40);
 pub const GUI_REPAINT_DELAY: Duration = Duration::from_millis(50);
@@ -36,9 +65,18 @@ pub const NUM_PROFILE_COMMENTS_TO_FETCH: usize = 2;
 /// Reputation thread batch size - how many players to process per loop iteration
 pub const NUM_REPUTATIONS_TO_FETCH: usize = 3;
 
+/// How many old versions of a TF2BD playerlist are kept as .bak files when saving
+pub const TF2BD_PLAYERLIST_BACKUPS: usize = 5;
+
+/// How many of a player's latest chat lines are stored as proof when they are flagged
+pub const FLAG_PROOF_CHAT_LINES: usize = 5;
+
 /// HTTP cache configuration
 pub const HTTP_CACHE_BASE_DIR: &str = ".http-cache";
 
+/// How long a request may take before it is given up, so a stalled server doesn't hang a thread
+pub const HTTP_REQUEST_TIMEOUT: Duration = Duration::from_secs(20);
+
 /// HTTP cache TTL (Time To Live) in days - how long cached data should be used before fetching new data
 pub const HTTP_CACHE_TTL_TF2_PLAYTIME_DAYS: i32 = 30;
 pub const HTTP_CACHE_TTL_FRIENDLIST_DAYS: i32 = 30;
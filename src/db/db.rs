@@ -49,7 +49,7 @@ impl CustomizeConnection<SqliteConnection, diesel::r2d2::Error> for SqliteConnec
 /// Connects to the SQLite database and sets up the schema if needed.
 ///
 /// This function:
-/// - Connects to the SQLite database file `appdata.sqlite`
+/// - Connects to the SQLite database file `appdata.sqlite`, see [`connect_file`]
 /// - Creates the database file if it doesn't exist
 /// - Creates all necessary tables based on the entity definitions
 /// - Creates indexes as specified in the data model
@@ -58,21 +58,27 @@ impl CustomizeConnection<SqliteConnection, diesel::r2d2::Error> for SqliteConnec
 ///
 /// Returns a connection pool if successful, or an error if connection/setup fails.
 pub fn connect() -> Result<DbPool, Box<dyn std::error::Error>> {
-    let db_path = Path::new(DATABASE_FILE);
+    connect_file(DATABASE_FILE)
+}
+
+/// Connects to the given SQLite database file and sets up the schema if needed,
+/// e.g. a temporary database for tests.
+pub fn connect_file(database_file: &str) -> Result<DbPool, Box<dyn std::error::Error>> {
+    let db_path = Path::new(database_file);
 
     // Check if database file exists, log accordingly
     if db_path.exists() {
-        log::info!("Database file '{}' exists, connecting...", DATABASE_FILE);
+        log::info!("Database file '{}' exists, connecting...", database_file);
     } else {
         log::info!(
             "Database file '{}' does not exist, will be created...",
-            DATABASE_FILE
+            database_file
         );
     }
 
     // Connect to SQLite database
     // SQLite will create the file if it doesn't exist
-    let database_url = DATABASE_FILE;
+    let database_url = database_file;
     let manager = ConnectionManager::<SqliteConnection>::new(database_url);
 
     // Build connection pool with optimized settings for concurrent access
@@ -85,7 +91,7 @@ pub fn connect() -> Result<DbPool, Box<dyn std::error::Error>> {
 
     log::info!(
         "Connected to database '{}' with WAL mode enabled",
-        DATABASE_FILE
+        database_file
     );
 
     // Set up schema (create tables if they don't exist)
@@ -95,6 +101,24 @@ pub fn connect() -> Result<DbPool, Box<dyn std::error::Error>> {
     Ok(pool)
 }
 
+/// Connects to a new empty database in memory, for tests and replays.
+/// The pool has one connection that is never closed, since each connection to
+/// an in-memory database gets a database of its own.
+pub fn connect_memory() -> Result<DbPool, Box<dyn std::error::Error>> {
+    let manager = ConnectionManager::<SqliteConnection>::new(":memory:");
+    let pool = r2d2::Pool::builder()
+        .max_size(1)
+        .idle_timeout(None)
+        .max_lifetime(None)
+        .connection_customizer(Box::new(SqliteConnectionCustomizer))
+        .build(manager)?;
+
+    let mut conn = pool.get()?;
+    setup_schema(&mut conn)?;
+
+    Ok(pool)
+}
+
 /// Sets up the database schema by creating all necessary tables and indexes.
 ///
 /// This function uses raw SQL to create tables based on entity definitions.
@@ -129,6 +153,11 @@ fn setup_schema(conn: &mut SqliteConnection) -> Result<(), Box<dyn std::error::E
         .execute(conn)
         .ok(); // Ignore error if column already exists
 
+    // Add avatar_hash column if it doesn't exist (for existing databases)
+    diesel::sql_query("ALTER TABLE account ADD COLUMN avatar_hash TEXT")
+        .execute(conn)
+        .ok(); // Ignore error if column already exists
+
     // Create friendship table
     diesel::sql_query(
         "CREATE TABLE IF NOT EXISTS friendship (
@@ -274,11 +303,106 @@ fn setup_schema(conn: &mut SqliteConnection) -> Result<(), Box<dyn std::error::E
             first_seen INTEGER NOT NULL,
             last_seen INTEGER NOT NULL,
             notified INTEGER NOT NULL,
+            suggestion INTEGER NOT NULL DEFAULT 0,
+            from_marking INTEGER NOT NULL DEFAULT 0,
             PRIMARY KEY (steam_id, flag_type, source)
         )",
     )
     .execute(conn)?;
 
+    // Add suggestion column if it doesn't exist (for existing databases)
+    diesel::sql_query("ALTER TABLE player_flags ADD COLUMN suggestion INTEGER NOT NULL DEFAULT 0")
+        .execute(conn)
+        .ok(); // Ignore error if column already exists
+
+    // Add from_marking column if it doesn't exist (for existing databases)
+    diesel::sql_query(
+        "ALTER TABLE player_flags ADD COLUMN from_marking INTEGER NOT NULL DEFAULT 0",
+    )
+    .execute(conn)
+    .ok(); // Ignore error if column already exists
+
+    // Create player_flag_proofs table
+    // Rows belong to the player_flags row with the same steam_id, flag_type and source
+    diesel::sql_query(
+        "CREATE TABLE IF NOT EXISTS player_flag_proofs (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            steam_id INTEGER NOT NULL,
+            flag_type TEXT NOT NULL,
+            source TEXT NOT NULL,
+            created INTEGER NOT NULL,
+            reason TEXT,
+            lobby_id TEXT,
+            map TEXT,
+            server TEXT,
+            chat TEXT NOT NULL,
+            kills INTEGER NOT NULL,
+            deaths INTEGER NOT NULL,
+            crit_kills INTEGER NOT NULL,
+            kills_with TEXT NOT NULL
+        )",
+    )
+    .execute(conn)?;
+
+    // Create encounters table, how often we have met each player
+    diesel::sql_query(
+        "CREATE TABLE IF NOT EXISTS encounters (
+            steam_id INTEGER PRIMARY KEY NOT NULL,
+            first_seen INTEGER NOT NULL,
+            last_seen INTEGER NOT NULL,
+            matches INTEGER NOT NULL,
+            seconds INTEGER NOT NULL,
+            same_team INTEGER NOT NULL,
+            enemy_team INTEGER NOT NULL,
+            names TEXT NOT NULL
+        )",
+    )
+    .execute(conn)?;
+
+    // Create match_sessions table, one row per lobby played
+    diesel::sql_query(
+        "CREATE TABLE IF NOT EXISTS match_sessions (
+            lobby_id TEXT PRIMARY KEY NOT NULL,
+            started INTEGER NOT NULL,
+            ended INTEGER,
+            map TEXT,
+            server TEXT,
+            address TEXT
+        )",
+    )
+    .execute(conn)?;
+
+    // Create match_session_players table, the roster of each match session
+    diesel::sql_query(
+        "CREATE TABLE IF NOT EXISTS match_session_players (
+            lobby_id TEXT NOT NULL,
+            steam_id INTEGER NOT NULL,
+            name TEXT NOT NULL,
+            team TEXT NOT NULL,
+            kills INTEGER NOT NULL,
+            deaths INTEGER NOT NULL,
+            flags TEXT NOT NULL,
+            first_seen INTEGER NOT NULL,
+            last_seen INTEGER NOT NULL,
+            PRIMARY KEY (lobby_id, steam_id)
+        )",
+    )
+    .execute(conn)?;
+
+    // Create name_history table, the names each account has used
+    diesel::sql_query(
+        "CREATE TABLE IF NOT EXISTS name_history (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            steam_id INTEGER NOT NULL,
+            name TEXT NOT NULL,
+            source TEXT NOT NULL,
+            seen INTEGER NOT NULL,
+            lobby_id TEXT,
+            mid_match INTEGER NOT NULL
+        )",
+    )
+    .execute(conn)?;
+
     // Create steam_bans table
     // Note: steam_id is NOT a foreign key - we want to store bans for players
     // that may not yet exist in the account table
@@ -298,6 +422,8 @@ fn setup_schema(conn: &mut SqliteConnection) -> Result<(), Box<dyn std::error::E
     // Create indexes as specified in DATAMODEL.md
     // Note: Primary keys are automatically indexed, so we only need to create additional indexes
 
+    diesel::sql_query("CREATE INDEX IF NOT EXISTS idx_account_avatar_hash ON account(avatar_hash)")
+        .execute(conn)?;
     diesel::sql_query("CREATE INDEX IF NOT EXISTS idx_friendship_steam_id ON friendship(steam_id)")
         .execute(conn)?;
     diesel::sql_query(
@@ -332,9 +458,31 @@ fn setup_schema(conn: &mut SqliteConnection) -> Result<(), Box<dyn std::error::E
         "CREATE INDEX IF NOT EXISTS idx_player_flags_notified ON player_flags(notified)",
     )
     .execute(conn)?;
+    diesel::sql_query(
+        "CREATE INDEX IF NOT EXISTS idx_match_sessions_started ON match_sessions(started)",
+    )
+    .execute(conn)?;
+    diesel::sql_query(
+        "CREATE INDEX IF NOT EXISTS idx_match_session_players_steam_id ON match_session_players(steam_id)",
+    )
+    .execute(conn)?;
+    diesel::sql_query(
+        "CREATE INDEX IF NOT EXISTS idx_name_history_steam_id ON name_history(steam_id)",
+    )
+    .execute(conn)?;
+    diesel::sql_query(
+        "CREATE INDEX IF NOT EXISTS idx_player_flag_proofs_flag ON player_flag_proofs(steam_id, flag_type, source)",
+    )
+    .execute(conn)?;
     diesel::sql_query("CREATE INDEX IF NOT EXISTS idx_steam_bans_steam_id ON steam_bans(steam_id)")
         .execute(conn)?;
 
+    // Accounts stored before avatar_hash existed get it from their avatar URL
+    let updated = super::queries::backfill_avatar_hashes(conn)?;
+    if updated > 0 {
+        log::info!("Added avatar hashes to {} accounts", updated);
+    }
+
     log::info!("Database schema setup completed");
     Ok(())
 }
@@ -42,6 +42,9 @@ pub struct Account {
 
     /// UnixTime when steam bans (VAC/Game bans) was last fetched (nullable)
     pub steam_bans_last_fetched: Option<i64>,
+
+    /// Hash of the avatar image, the same for all sizes (nullable)
+    pub avatar_hash: Option<String>,
 }
 
 #[derive(Clone, Debug, Insertable, AsChangeset)]
@@ -59,4 +62,5 @@ pub struct NewAccount {
     pub playtimes_fetched: Option<i64>,
     pub reputation_fetched: Option<i64>,
     pub steam_bans_last_fetched: Option<i64>,
+    pub avatar_hash: Option<String>,
 }
@@ -0,0 +1,45 @@
+use diesel::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::db::schema::encounters;
+
+/// How often and how long we have been in a lobby with a player
+#[derive(Clone, Debug, PartialEq, Eq, Queryable, Selectable, Serialize, Deserialize)]
+#[diesel(table_name = encounters)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct Encounter {
+    /// SteamID64 (Primary Key, Foreign Key to Account)
+    pub steam_id: i64,
+
+    /// UnixTime when the player was first seen in a lobby with us
+    pub first_seen: i64,
+
+    /// UnixTime when the player was last seen in a lobby with us
+    pub last_seen: i64,
+
+    /// Number of lobbies the player was in
+    pub matches: i32,
+
+    /// Total time in lobbies with us
+    pub seconds: i64,
+
+    /// Number of lobbies the player was on our team or on the enemy team
+    pub same_team: i32,
+    pub enemy_team: i32,
+
+    /// Names seen in the lobbies, one per line, oldest first
+    pub names: String,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Insertable, AsChangeset)]
+#[diesel(table_name = encounters)]
+pub struct NewEncounter {
+    pub steam_id: i64,
+    pub first_seen: i64,
+    pub last_seen: i64,
+    pub matches: i32,
+    pub seconds: i64,
+    pub same_team: i32,
+    pub enemy_team: i32,
+    pub names: String,
+}
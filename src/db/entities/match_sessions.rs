@@ -0,0 +1,81 @@
+use diesel::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::db::schema::{match_session_players, match_sessions};
+
+/// One lobby that was played, from Lobby created until Lobby destroyed
+#[derive(Clone, Debug, PartialEq, Eq, Queryable, Selectable, Serialize, Deserialize)]
+#[diesel(table_name = match_sessions)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct MatchSession {
+    /// Lobby::lobby_id (Primary Key)
+    pub lobby_id: String,
+
+    /// UnixTime when the lobby was created
+    pub started: i64,
+
+    /// UnixTime when the lobby ended, None while it is played or when the app was closed
+    pub ended: Option<i64>,
+
+    pub map: Option<String>,
+
+    /// Hostname of the server
+    pub server: Option<String>,
+
+    /// IP and port of the server
+    pub address: Option<String>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Insertable, AsChangeset)]
+#[diesel(table_name = match_sessions)]
+pub struct NewMatchSession {
+    pub lobby_id: String,
+    pub started: i64,
+    pub map: Option<String>,
+    pub server: Option<String>,
+    pub address: Option<String>,
+}
+
+/// A player in a match session, with their stats when they were last seen in it
+#[derive(Clone, Debug, PartialEq, Eq, Queryable, Selectable, Serialize, Deserialize)]
+#[diesel(table_name = match_session_players)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct MatchSessionPlayer {
+    /// Composite Primary Key, Foreign Key to MatchSession
+    pub lobby_id: String,
+
+    /// SteamID64 (Composite Primary Key)
+    pub steam_id: i64,
+
+    /// Name used in the match
+    pub name: String,
+
+    /// "Red", "Blue", "Spec" or "Unknown"
+    pub team: String,
+
+    pub kills: i32,
+    pub deaths: i32,
+
+    /// Comma separated flags the player had in the match, e.g. "Cheater,Bot"
+    pub flags: String,
+
+    /// UnixTime when the player was first seen in the match
+    pub first_seen: i64,
+
+    /// UnixTime when the player was last seen in the match
+    pub last_seen: i64,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Insertable)]
+#[diesel(table_name = match_session_players)]
+pub struct NewMatchSessionPlayer {
+    pub lobby_id: String,
+    pub steam_id: i64,
+    pub name: String,
+    pub team: String,
+    pub kills: i32,
+    pub deaths: i32,
+    pub flags: String,
+    pub first_seen: i64,
+    pub last_seen: i64,
+}
@@ -2,7 +2,11 @@ pub mod account;
 pub mod ban_sources;
 pub mod bans;
 pub mod comments;
+pub mod encounters;
 pub mod friendship;
+pub mod match_sessions;
+pub mod name_history;
+pub mod player_flag_proofs;
 pub mod player_flags;
 pub mod playtime;
 pub mod steam_bans;
@@ -12,7 +16,13 @@ pub use account::{Account, NewAccount};
 pub use ban_sources::{BanSource, NewBanSource};
 pub use bans::{Ban, NewBan};
 pub use comments::{Comment, NewComment};
+pub use encounters::{Encounter, NewEncounter};
 pub use friendship::{Friendship, NewFriendship};
+pub use match_sessions::{
+    MatchSession, MatchSessionPlayer, NewMatchSession, NewMatchSessionPlayer,
+};
+pub use name_history::{NAME_SOURCE_GAME, NAME_SOURCE_STEAM, NameHistory, NewNameHistory};
+pub use player_flag_proofs::{NewPlayerFlagProof, PlayerFlagProof};
 pub use player_flags::{NewPlayerFlag, PlayerFlag};
 pub use playtime::{Game, NewPlaytime, Playtime};
 pub use steam_bans::{NewSteamBan, SteamBan};
@@ -0,0 +1,47 @@
+use diesel::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::db::schema::name_history;
+
+/// Source of a name seen in a lobby, from the g15_dumpplayer output
+pub const NAME_SOURCE_GAME: &str = "game";
+
+/// Source of a name from the Steam player summary
+pub const NAME_SOURCE_STEAM: &str = "steam";
+
+/// A name used by an account, a row is added whenever the name differs from the last one
+#[derive(Clone, Debug, PartialEq, Eq, Queryable, Selectable, Serialize, Deserialize)]
+#[diesel(table_name = name_history)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct NameHistory {
+    /// Auto-increment Primary Key
+    pub id: i64,
+
+    /// SteamID64 (Foreign Key to Account)
+    pub steam_id: i64,
+
+    pub name: String,
+
+    /// Where the name was seen, NAME_SOURCE_GAME or NAME_SOURCE_STEAM
+    pub source: String,
+
+    /// UnixTime when the name was first seen
+    pub seen: i64,
+
+    /// The lobby the name was seen in, for names from the game
+    pub lobby_id: Option<String>,
+
+    /// Whether the player changed to this name during a match
+    pub mid_match: bool,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Insertable)]
+#[diesel(table_name = name_history)]
+pub struct NewNameHistory {
+    pub steam_id: i64,
+    pub name: String,
+    pub source: String,
+    pub seen: i64,
+    pub lobby_id: Option<String>,
+    pub mid_match: bool,
+}
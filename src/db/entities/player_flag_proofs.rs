@@ -0,0 +1,65 @@
+use diesel::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::db::schema::player_flag_proofs;
+
+/// Evidence stored with a player flag: why it was set and what was going on at the time
+#[derive(Clone, Debug, PartialEq, Eq, Queryable, Selectable, Serialize, Deserialize)]
+#[diesel(table_name = player_flag_proofs)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct PlayerFlagProof {
+    /// Auto-increment Primary Key
+    pub id: i64,
+
+    /// SteamID64 of flagged account (steam_id, flag_type, source link to PlayerFlag)
+    pub steam_id: i64,
+
+    /// Type of flag the proof belongs to (e.g., "Cheater", "Bot")
+    pub flag_type: String,
+
+    /// Source of the flag the proof belongs to (e.g., "manual")
+    pub source: String,
+
+    /// UnixTime when the proof was recorded
+    pub created: i64,
+
+    /// Free text reason given by the user, or a proof line from an imported list
+    pub reason: Option<String>,
+
+    /// TF2 lobby the player was in when flagged
+    pub lobby_id: Option<String>,
+
+    /// Map being played when flagged
+    pub map: Option<String>,
+
+    /// Server name or address when flagged
+    pub server: Option<String>,
+
+    /// Recent chat lines of the player, one per line
+    pub chat: String,
+
+    pub kills: i32,
+    pub deaths: i32,
+    pub crit_kills: i32,
+
+    /// Weapons the player got kills with, e.g. "sniperrifle x5, smg x1"
+    pub kills_with: String,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Insertable)]
+#[diesel(table_name = player_flag_proofs)]
+pub struct NewPlayerFlagProof {
+    pub steam_id: i64,
+    pub flag_type: String,
+    pub source: String,
+    pub created: i64,
+    pub reason: Option<String>,
+    pub lobby_id: Option<String>,
+    pub map: Option<String>,
+    pub server: Option<String>,
+    pub chat: String,
+    pub kills: i32,
+    pub deaths: i32,
+    pub crit_kills: i32,
+    pub kills_with: String,
+}
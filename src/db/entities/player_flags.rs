@@ -24,6 +24,13 @@ pub struct PlayerFlag {
 
     /// Whether we've notified the user about this flag
     pub notified: bool,
+
+    /// From a suggestion-only list or rule, not acted upon and not exported by default
+    pub suggestion: bool,
+
+    /// Written from the player's marking in the loaded lists and rules,
+    /// removed when the marking no longer has it
+    pub from_marking: bool,
 }
 
 #[derive(Clone, Debug, Insertable, AsChangeset)]
@@ -35,4 +42,6 @@ pub struct NewPlayerFlag {
     pub first_seen: i64,
     pub last_seen: i64,
     pub notified: bool,
+    pub suggestion: bool,
+    pub from_marking: bool,
 }
@@ -1,12 +1,16 @@
 use diesel::prelude::*;
 
 use super::entities::{
-    Account, Ban, BanSource, Comment, Friendship, Game, NewAccount, NewBan, NewBanSource,
-    NewComment, NewFriendship, NewPlayerFlag, NewPlaytime, NewSteamBan, PlayerFlag, SteamBan,
+    Account, Ban, BanSource, Comment, Encounter, Friendship, Game, MatchSession,
+    MatchSessionPlayer, NameHistory, NewAccount, NewBan, NewBanSource, NewComment, NewEncounter,
+    NewFriendship, NewMatchSession, NewMatchSessionPlayer, NewNameHistory, NewPlayerFlag,
+    NewPlayerFlagProof, NewPlaytime, NewSteamBan, PlayerFlag, PlayerFlagProof, SteamBan,
 };
 use super::schema::{
-    account, ban_sources, bans, comments, friendship, player_flags, playtime, steam_bans,
+    account, ban_sources, bans, comments, encounters, friendship, match_session_players,
+    match_sessions, name_history, player_flag_proofs, player_flags, playtime, steam_bans,
 };
+use crate::tf2::steamapi::avatar_hash_from_url;
 
 /// Get all friendships for a given steam_id.
 ///
@@ -90,6 +94,44 @@ pub fn get_all_accounts(
         .load::<Account>(conn)
 }
 
+/// Get every account that has used the avatar, ordered by name
+pub fn get_accounts_by_avatar_hash(
+    conn: &mut SqliteConnection,
+    avatar_hash: &str,
+) -> Result<Vec<Account>, diesel::result::Error> {
+    use account::dsl;
+
+    account::table
+        .filter(dsl::avatar_hash.eq(avatar_hash.to_ascii_lowercase()))
+        .order((dsl::name.asc(), dsl::steam_id.asc()))
+        .load::<Account>(conn)
+}
+
+/// Fill in avatar_hash for accounts stored before the column existed,
+/// using the hash in the avatar URL. Returns the number of updated accounts.
+pub fn backfill_avatar_hashes(conn: &mut SqliteConnection) -> Result<usize, diesel::result::Error> {
+    use account::dsl;
+
+    let accounts: Vec<(i64, String)> = account::table
+        .filter(dsl::avatar_hash.is_null())
+        .filter(dsl::avatar_full_url.ne(""))
+        .select((dsl::steam_id, dsl::avatar_full_url))
+        .load(conn)?;
+
+    let mut updated = 0;
+    conn.transaction(|conn| {
+        for (steam_id, avatar_full_url) in &accounts {
+            if let Some(avatar_hash) = avatar_hash_from_url(avatar_full_url) {
+                diesel::update(account::table.filter(dsl::steam_id.eq(steam_id)))
+                    .set(dsl::avatar_hash.eq(avatar_hash))
+                    .execute(conn)?;
+                updated += 1;
+            }
+        }
+        Ok(updated)
+    })
+}
+
 /// Insert or update an account record.
 /// When updating existing records, preserves timestamp fields (friends_fetched, comments_fetched,
 /// playtimes_fetched, reputation_fetched) and only updates account info fields.
@@ -108,6 +150,7 @@ pub fn upsert_account(
             dsl::created_date.eq(&new_account.created_date),
             dsl::avatar_thumb_url.eq(&new_account.avatar_thumb_url),
             dsl::avatar_full_url.eq(&new_account.avatar_full_url),
+            dsl::avatar_hash.eq(&new_account.avatar_hash),
             dsl::public_profile.eq(&new_account.public_profile),
             dsl::last_updated.eq(&new_account.last_updated),
             // Note: We deliberately do NOT update the *_fetched timestamp fields here
@@ -242,6 +285,7 @@ pub fn update_account_friends_fetched(
         playtimes_fetched: None,
         reputation_fetched: None,
         steam_bans_last_fetched: None,
+        avatar_hash: None,
     };
 
     // Use INSERT OR REPLACE (UPSERT) to either create or update the record
@@ -279,6 +323,7 @@ pub fn update_account_comments_fetched(
         playtimes_fetched: None,
         reputation_fetched: None,
         steam_bans_last_fetched: None,
+        avatar_hash: None,
     };
 
     // Use INSERT OR REPLACE (UPSERT) to either create or update the record
@@ -316,6 +361,7 @@ pub fn update_account_playtimes_fetched(
         playtimes_fetched: Some(playtimes_fetched),
         reputation_fetched: None,
         steam_bans_last_fetched: None,
+        avatar_hash: None,
     };
 
     // Use INSERT OR REPLACE (UPSERT) to either create or update the record
@@ -353,6 +399,7 @@ pub fn update_account_reputation_fetched(
         playtimes_fetched: None,
         reputation_fetched: Some(reputation_fetched),
         steam_bans_last_fetched: None,
+        avatar_hash: None,
     };
 
     // Use INSERT OR REPLACE (UPSERT) to either create or update the record
@@ -505,7 +552,8 @@ pub fn set_ban_source_active(
 // ============================================================================
 
 /// Insert or update a player flag.
-/// If the flag already exists, updates last_seen timestamp.
+/// If the flag already exists, updates last_seen timestamp, whether it is a suggestion
+/// and whether it comes from a marking.
 pub fn upsert_player_flag(
     conn: &mut SqliteConnection,
     new_flag: NewPlayerFlag,
@@ -516,7 +564,27 @@ pub fn upsert_player_flag(
         .values(&new_flag)
         .on_conflict((dsl::steam_id, dsl::flag_type, dsl::source))
         .do_update()
-        .set(dsl::last_seen.eq(&new_flag.last_seen))
+        .set((
+            dsl::last_seen.eq(&new_flag.last_seen),
+            dsl::suggestion.eq(&new_flag.suggestion),
+            dsl::from_marking.eq(&new_flag.from_marking),
+        ))
+        .execute(conn)?;
+    Ok(())
+}
+
+/// Insert a player flag, or replace every field of the flag if it already exists.
+pub fn replace_player_flag(
+    conn: &mut SqliteConnection,
+    new_flag: NewPlayerFlag,
+) -> Result<(), diesel::result::Error> {
+    use player_flags::dsl;
+
+    diesel::insert_into(player_flags::table)
+        .values(&new_flag)
+        .on_conflict((dsl::steam_id, dsl::flag_type, dsl::source))
+        .do_update()
+        .set(&new_flag)
         .execute(conn)?;
     Ok(())
 }
@@ -565,6 +633,19 @@ pub fn get_all_player_flags(
         .load::<PlayerFlag>(conn)
 }
 
+/// Get all player flags from a source, e.g. an imported list.
+pub fn get_player_flags_by_source(
+    conn: &mut SqliteConnection,
+    source: &str,
+) -> Result<Vec<PlayerFlag>, diesel::result::Error> {
+    use player_flags::dsl;
+
+    player_flags::table
+        .filter(dsl::source.eq(source))
+        .order((dsl::steam_id.asc(), dsl::flag_type.asc()))
+        .load::<PlayerFlag>(conn)
+}
+
 /// Get unnotified player flags for a steam_id.
 pub fn get_unnotified_player_flags(
     conn: &mut SqliteConnection,
@@ -578,7 +659,7 @@ pub fn get_unnotified_player_flags(
         .load::<PlayerFlag>(conn)
 }
 
-/// Remove a player flag.
+/// Remove a player flag and the proofs stored with it.
 pub fn remove_player_flag(
     conn: &mut SqliteConnection,
     steam_id: i64,
@@ -596,9 +677,67 @@ pub fn remove_player_flag(
         ),
     )
     .execute(conn)?;
+
+    diesel::delete(
+        player_flag_proofs::table.filter(
+            player_flag_proofs::steam_id
+                .eq(steam_id)
+                .and(player_flag_proofs::flag_type.eq(flag_type))
+                .and(player_flag_proofs::source.eq(source)),
+        ),
+    )
+    .execute(conn)?;
+    Ok(())
+}
+
+/// Store a proof for a player flag.
+pub fn insert_player_flag_proof(
+    conn: &mut SqliteConnection,
+    new_proof: &NewPlayerFlagProof,
+) -> Result<(), diesel::result::Error> {
+    diesel::insert_into(player_flag_proofs::table)
+        .values(new_proof)
+        .execute(conn)?;
     Ok(())
 }
 
+/// Get the proofs of all flags of a steam_id, oldest first.
+pub fn get_player_flag_proofs(
+    conn: &mut SqliteConnection,
+    steam_id: i64,
+) -> Result<Vec<PlayerFlagProof>, diesel::result::Error> {
+    use player_flag_proofs::dsl;
+
+    player_flag_proofs::table
+        .filter(dsl::steam_id.eq(steam_id))
+        .order((dsl::created.asc(), dsl::id.asc()))
+        .load::<PlayerFlagProof>(conn)
+}
+
+/// Get the proofs of all flags, oldest first.
+pub fn get_all_player_flag_proofs(
+    conn: &mut SqliteConnection,
+) -> Result<Vec<PlayerFlagProof>, diesel::result::Error> {
+    use player_flag_proofs::dsl;
+
+    player_flag_proofs::table
+        .order((dsl::steam_id.asc(), dsl::created.asc(), dsl::id.asc()))
+        .load::<PlayerFlagProof>(conn)
+}
+
+/// Get the proofs of the flags from a source, e.g. an imported list.
+pub fn get_player_flag_proofs_by_source(
+    conn: &mut SqliteConnection,
+    source: &str,
+) -> Result<Vec<PlayerFlagProof>, diesel::result::Error> {
+    use player_flag_proofs::dsl;
+
+    player_flag_proofs::table
+        .filter(dsl::source.eq(source))
+        .order((dsl::steam_id.asc(), dsl::id.asc()))
+        .load::<PlayerFlagProof>(conn)
+}
+
 //
 // Steam Bans queries
 //
@@ -757,3 +896,216 @@ pub fn get_active_bans_count(
         .select(count_star())
         .first::<i64>(conn)
 }
+
+//
+// Match session queries
+//
+
+/// Insert a match session, or update its map and server when it already exists.
+pub fn upsert_match_session(
+    conn: &mut SqliteConnection,
+    new_session: &NewMatchSession,
+) -> Result<(), diesel::result::Error> {
+    use match_sessions::dsl;
+
+    diesel::insert_into(match_sessions::table)
+        .values(new_session)
+        .on_conflict(dsl::lobby_id)
+        .do_update()
+        .set((
+            dsl::map.eq(&new_session.map),
+            dsl::server.eq(&new_session.server),
+            dsl::address.eq(&new_session.address),
+        ))
+        .execute(conn)?;
+    Ok(())
+}
+
+/// Set the end time of a match session, unless it has ended already.
+pub fn end_match_session(
+    conn: &mut SqliteConnection,
+    lobby_id: &str,
+    ended: i64,
+) -> Result<(), diesel::result::Error> {
+    use match_sessions::dsl;
+
+    diesel::update(
+        match_sessions::table
+            .filter(dsl::lobby_id.eq(lobby_id))
+            .filter(dsl::ended.is_null()),
+    )
+    .set(dsl::ended.eq(Some(ended)))
+    .execute(conn)?;
+    Ok(())
+}
+
+/// Insert a player of a match session, or update their stats.
+/// first_seen is kept from when the player was first stored.
+pub fn upsert_match_session_player(
+    conn: &mut SqliteConnection,
+    new_player: &NewMatchSessionPlayer,
+) -> Result<(), diesel::result::Error> {
+    use match_session_players::dsl;
+
+    diesel::insert_into(match_session_players::table)
+        .values(new_player)
+        .on_conflict((dsl::lobby_id, dsl::steam_id))
+        .do_update()
+        .set((
+            dsl::name.eq(&new_player.name),
+            dsl::team.eq(&new_player.team),
+            dsl::kills.eq(new_player.kills),
+            dsl::deaths.eq(new_player.deaths),
+            dsl::flags.eq(&new_player.flags),
+            dsl::last_seen.eq(new_player.last_seen),
+        ))
+        .execute(conn)?;
+    Ok(())
+}
+
+/// Get the match sessions a player was in, latest first.
+pub fn get_player_match_sessions(
+    conn: &mut SqliteConnection,
+    steam_id: i64,
+) -> Result<Vec<(MatchSessionPlayer, MatchSession)>, diesel::result::Error> {
+    match_session_players::table
+        .inner_join(match_sessions::table)
+        .filter(match_session_players::steam_id.eq(steam_id))
+        .order(match_sessions::started.desc())
+        .select((MatchSessionPlayer::as_select(), MatchSession::as_select()))
+        .load::<(MatchSessionPlayer, MatchSession)>(conn)
+}
+
+//
+// Encounter queries
+//
+
+/// Insert an encounter, or replace every field of it if it already exists.
+pub fn replace_encounter(
+    conn: &mut SqliteConnection,
+    new_encounter: &NewEncounter,
+) -> Result<(), diesel::result::Error> {
+    use encounters::dsl;
+
+    diesel::insert_into(encounters::table)
+        .values(new_encounter)
+        .on_conflict(dsl::steam_id)
+        .do_update()
+        .set(new_encounter)
+        .execute(conn)?;
+    Ok(())
+}
+
+/// Get the encounter with a player, None if we have never met.
+pub fn get_encounter(
+    conn: &mut SqliteConnection,
+    steam_id: i64,
+) -> Result<Option<Encounter>, diesel::result::Error> {
+    encounters::table
+        .filter(encounters::steam_id.eq(steam_id))
+        .first::<Encounter>(conn)
+        .optional()
+}
+
+/// Get the encounters with all players.
+pub fn get_all_encounters(
+    conn: &mut SqliteConnection,
+) -> Result<Vec<Encounter>, diesel::result::Error> {
+    encounters::table.load::<Encounter>(conn)
+}
+
+//
+// Name history queries
+//
+
+/// Add a name to the history of an account, unless it is the last name seen from the same source.
+/// The Steam name and the name in game can differ, comparing them would add both every time.
+/// Returns true when the name was added.
+pub fn record_name(
+    conn: &mut SqliteConnection,
+    new_name: &NewNameHistory,
+) -> Result<bool, diesel::result::Error> {
+    use name_history::dsl;
+
+    let last_name = name_history::table
+        .filter(dsl::steam_id.eq(new_name.steam_id))
+        .filter(dsl::source.eq(&new_name.source))
+        .order(dsl::id.desc())
+        .select(dsl::name)
+        .first::<String>(conn)
+        .optional()?;
+
+    if last_name.as_ref() == Some(&new_name.name) {
+        return Ok(false);
+    }
+
+    diesel::insert_into(name_history::table)
+        .values(new_name)
+        .execute(conn)?;
+    Ok(true)
+}
+
+/// Get the names an account has used, oldest first.
+pub fn get_name_history(
+    conn: &mut SqliteConnection,
+    steam_id: i64,
+) -> Result<Vec<NameHistory>, diesel::result::Error> {
+    use name_history::dsl;
+
+    name_history::table
+        .filter(dsl::steam_id.eq(steam_id))
+        .order(dsl::id.asc())
+        .load::<NameHistory>(conn)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{
+        db::connect_memory,
+        entities::{NAME_SOURCE_GAME, NAME_SOURCE_STEAM},
+    };
+
+    fn new_name(name: &str, source: &str, seen: i64) -> NewNameHistory {
+        NewNameHistory {
+            steam_id: 1,
+            name: name.to_string(),
+            source: source.to_string(),
+            seen,
+            lobby_id: None,
+            mid_match: false,
+        }
+    }
+
+    #[test]
+    fn test_record_name() {
+        let db = connect_memory().unwrap();
+        let mut conn = db.get().unwrap();
+
+        assert!(record_name(&mut conn, &new_name("Steam name", NAME_SOURCE_STEAM, 1)).unwrap());
+        assert!(record_name(&mut conn, &new_name("Game name", NAME_SOURCE_GAME, 2)).unwrap());
+
+        // A different name from the other source doesn't add the name again
+        assert!(!record_name(&mut conn, &new_name("Steam name", NAME_SOURCE_STEAM, 3)).unwrap());
+        assert!(!record_name(&mut conn, &new_name("Game name", NAME_SOURCE_GAME, 4)).unwrap());
+
+        assert!(record_name(&mut conn, &new_name("New name", NAME_SOURCE_GAME, 5)).unwrap());
+        assert!(record_name(&mut conn, &new_name("Game name", NAME_SOURCE_GAME, 6)).unwrap());
+
+        let names: Vec<(String, i64)> = get_name_history(&mut conn, 1)
+            .unwrap()
+            .into_iter()
+            .map(|name| (name.name, name.seen))
+            .collect();
+        assert_eq!(
+            names,
+            vec![
+                ("Steam name".to_string(), 1),
+                ("Game name".to_string(), 2),
+                ("New name".to_string(), 5),
+                ("Game name".to_string(), 6),
+            ]
+        );
+        assert!(get_name_history(&mut conn, 2).unwrap().is_empty());
+    }
+}
@@ -14,6 +14,7 @@ diesel::table! {
         playtimes_fetched -> Nullable<BigInt>,
         reputation_fetched -> Nullable<BigInt>,
         steam_bans_last_fetched -> Nullable<BigInt>,
+        avatar_hash -> Nullable<Text>,
     }
 }
 
@@ -62,6 +63,75 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    encounters (steam_id) {
+        steam_id -> BigInt,
+        first_seen -> BigInt,
+        last_seen -> BigInt,
+        matches -> Integer,
+        seconds -> BigInt,
+        same_team -> Integer,
+        enemy_team -> Integer,
+        names -> Text,
+    }
+}
+
+diesel::table! {
+    match_session_players (lobby_id, steam_id) {
+        lobby_id -> Text,
+        steam_id -> BigInt,
+        name -> Text,
+        team -> Text,
+        kills -> Integer,
+        deaths -> Integer,
+        flags -> Text,
+        first_seen -> BigInt,
+        last_seen -> BigInt,
+    }
+}
+
+diesel::table! {
+    match_sessions (lobby_id) {
+        lobby_id -> Text,
+        started -> BigInt,
+        ended -> Nullable<BigInt>,
+        map -> Nullable<Text>,
+        server -> Nullable<Text>,
+        address -> Nullable<Text>,
+    }
+}
+
+diesel::table! {
+    name_history (id) {
+        id -> BigInt,
+        steam_id -> BigInt,
+        name -> Text,
+        source -> Text,
+        seen -> BigInt,
+        lobby_id -> Nullable<Text>,
+        mid_match -> Bool,
+    }
+}
+
+diesel::table! {
+    player_flag_proofs (id) {
+        id -> BigInt,
+        steam_id -> BigInt,
+        flag_type -> Text,
+        source -> Text,
+        created -> BigInt,
+        reason -> Nullable<Text>,
+        lobby_id -> Nullable<Text>,
+        map -> Nullable<Text>,
+        server -> Nullable<Text>,
+        chat -> Text,
+        kills -> Integer,
+        deaths -> Integer,
+        crit_kills -> Integer,
+        kills_with -> Text,
+    }
+}
+
 diesel::table! {
     player_flags (steam_id, flag_type, source) {
         steam_id -> BigInt,
@@ -70,6 +140,8 @@ diesel::table! {
         first_seen -> BigInt,
         last_seen -> BigInt,
         notified -> Bool,
+        suggestion -> Bool,
+        from_marking -> Bool,
     }
 }
 
@@ -96,7 +168,11 @@ diesel::table! {
 
 diesel::joinable!(bans -> account (steam_id));
 diesel::joinable!(comments -> account (steam_id));
+diesel::joinable!(encounters -> account (steam_id));
 diesel::joinable!(friendship -> account (steam_id));
+diesel::joinable!(match_session_players -> match_sessions (lobby_id));
+diesel::joinable!(name_history -> account (steam_id));
+diesel::joinable!(player_flag_proofs -> account (steam_id));
 diesel::joinable!(player_flags -> account (steam_id));
 diesel::joinable!(playtime -> account (steam_id));
 // Note: steam_bans is NOT joinable with account - it's intentionally not a foreign key
@@ -106,7 +182,12 @@ diesel::allow_tables_to_appear_in_same_query!(
     bans,
     ban_sources,
     comments,
+    encounters,
     friendship,
+    match_session_players,
+    match_sessions,
+    name_history,
+    player_flag_proofs,
     player_flags,
     playtime,
     steam_bans,
@@ -41,6 +41,11 @@ pub fn add_chat(ui: &mut Ui, app_win: &mut AppWin) {
 }
 
 fn add_chat_row(ui: &mut Ui, app_win: &mut AppWin, chat_row: &LobbyChat) {
+    if chat_row.system {
+        add_system_chat_row(ui, app_win, chat_row);
+        return;
+    }
+
     ui.horizontal_wrapped(|ui| {
         let player = app_win.lobby.get_player(None, Some(chat_row.steamid));
 
@@ -159,3 +164,35 @@ fn add_chat_row(ui: &mut Ui, app_win: &mut AppWin, chat_row: &LobbyChat) {
         }
     });
 }
+
+/// A message from TF2Monitor about a player, e.g. a name change
+fn add_system_chat_row(ui: &mut Ui, app_win: &mut AppWin, chat_row: &LobbyChat) {
+    ui.horizontal_wrapped(|ui| {
+        if let Some(player) = app_win.lobby.get_player(None, Some(chat_row.steamid)) {
+            add_flags(ui, player);
+        }
+
+        let mut job = LayoutJob::default();
+        job.append(
+            "ℹ ",
+            0.0,
+            TextFormat {
+                color: Color32::WHITE,
+                ..Default::default()
+            },
+        );
+        job.append(
+            &chat_row.message,
+            0.0,
+            TextFormat {
+                color: Color32::GOLD,
+                italics: true,
+                ..Default::default()
+            },
+        );
+
+        if ui.label(job).clicked() {
+            app_win.set_selected_player(chat_row.steamid);
+        }
+    });
+}
@@ -0,0 +1,303 @@
+use crate::{
+    models::AppWin,
+    tf2bd::{
+        flag_exchange::{self, ConflictPolicy, ExportFilter},
+        formats::ListFormat,
+        models::{FileInfo, PlayerAttribute},
+    },
+};
+use chrono::Utc;
+use eframe::egui::{self, ComboBox, DragValue, Grid, TextEdit, Ui};
+
+const FLAG_EXCHANGE_ATTRIBUTES: [PlayerAttribute; 6] = [
+    PlayerAttribute::Cheater,
+    PlayerAttribute::Bot,
+    PlayerAttribute::Suspicious,
+    PlayerAttribute::Toxic,
+    PlayerAttribute::Exploiter,
+    PlayerAttribute::Cool,
+];
+
+/// State of the export/import window for the player flags database
+pub struct FlagExchangeState {
+    pub export_filename: String,
+    pub export_filter: ExportFilter,
+    pub export_recent_only: bool,
+    pub export_recent_days: u32,
+    pub export_format: ListFormat,
+
+    pub title: String,
+    pub description: String,
+
+    /// Comma separated
+    pub authors: String,
+    pub update_url: String,
+
+    pub import_filename: String,
+    pub import_source: String,
+    pub import_policy: ConflictPolicy,
+
+    /// Result of the last export or import, Err is shown in red
+    pub status: Option<Result<String, String>>,
+}
+
+impl Default for FlagExchangeState {
+    fn default() -> Self {
+        Self {
+            export_filename: "playerlist.export.json".to_string(),
+            export_filter: ExportFilter {
+                attributes: [PlayerAttribute::Cheater, PlayerAttribute::Bot].into(),
+                ..Default::default()
+            },
+            export_recent_only: false,
+            export_recent_days: 90,
+            export_format: ListFormat::Tf2bd,
+            title: String::new(),
+            description: String::new(),
+            authors: String::new(),
+            update_url: String::new(),
+            import_filename: String::new(),
+            import_source: String::new(),
+            import_policy: ConflictPolicy::default(),
+            status: None,
+        }
+    }
+}
+
+pub fn show_flag_exchange_window(app_win: &mut AppWin, ctx: &egui::Context) {
+    if !app_win.flag_exchange_window_open {
+        return;
+    }
+
+    let mut window_open = app_win.flag_exchange_window_open;
+
+    egui::Window::new("Export / Import Flags")
+        .open(&mut window_open)
+        .resizable(true)
+        .default_width(520.0)
+        .show(ctx, |ui| {
+            add_export_section(ui, app_win);
+            ui.add(egui::Separator::default().spacing(20.0));
+            add_import_section(ui, app_win);
+
+            match &app_win.flag_exchange.status {
+                Some(Ok(message)) => {
+                    ui.add_space(10.0);
+                    ui.label(message);
+                }
+                Some(Err(error)) => {
+                    ui.add_space(10.0);
+                    ui.colored_label(ui.visuals().error_fg_color, error);
+                }
+                None => {}
+            }
+        });
+
+    if !window_open {
+        app_win.flag_exchange_window_open = false;
+    }
+}
+
+fn add_export_section(ui: &mut Ui, app_win: &mut AppWin) {
+    let state = &mut app_win.flag_exchange;
+
+    ui.heading("Export");
+    ui.label(
+        "Writes the flags in the database to a TF2BD playerlist that others can subscribe to.",
+    );
+    ui.add_space(8.0);
+
+    ui.horizontal_wrapped(|ui| {
+        for player_attribute in FLAG_EXCHANGE_ATTRIBUTES {
+            let mut enabled = state.export_filter.attributes.contains(&player_attribute);
+            if ui
+                .checkbox(&mut enabled, format!("{:?}", player_attribute))
+                .changed()
+            {
+                if enabled {
+                    state.export_filter.attributes.insert(player_attribute);
+                } else {
+                    state.export_filter.attributes.remove(&player_attribute);
+                }
+            }
+        }
+    });
+
+    Grid::new("flag_export_grid").num_columns(2).show(ui, |ui| {
+        ui.label("Source contains:");
+        ui.add(
+            TextEdit::singleline(&mut state.export_filter.source)
+                .hint_text("All sources, e.g. manual")
+                .desired_width(300.0),
+        );
+        ui.end_row();
+
+        ui.label("");
+        ui.checkbox(
+            &mut state.export_filter.include_suggestions,
+            "Include suggestions",
+        )
+        .on_hover_text(
+            "Also export flags from suggestion lists and rules, which are not confirmed",
+        );
+        ui.end_row();
+
+        ui.checkbox(&mut state.export_recent_only, "Seen in the last");
+        ui.horizontal(|ui| {
+            ui.add_enabled(
+                state.export_recent_only,
+                DragValue::new(&mut state.export_recent_days).range(1..=3650),
+            );
+            ui.label("days");
+        });
+        ui.end_row();
+
+        ui.label("Title:");
+        ui.add(TextEdit::singleline(&mut state.title).desired_width(300.0));
+        ui.end_row();
+
+        ui.label("Description:");
+        ui.add(TextEdit::singleline(&mut state.description).desired_width(300.0));
+        ui.end_row();
+
+        ui.label("Authors:");
+        ui.add(
+            TextEdit::singleline(&mut state.authors)
+                .hint_text("Comma separated")
+                .desired_width(300.0),
+        );
+        ui.end_row();
+
+        ui.label("Update URL:");
+        ui.add(
+            TextEdit::singleline(&mut state.update_url)
+                .hint_text("Where the list will be published")
+                .desired_width(300.0),
+        );
+        ui.end_row();
+
+        ui.label("File:");
+        ui.add(TextEdit::singleline(&mut state.export_filename).desired_width(300.0));
+        ui.end_row();
+
+        ui.label("Format:");
+        ComboBox::from_id_salt("flag_export_format")
+            .selected_text(state.export_format.to_string())
+            .show_ui(ui, |ui| {
+                for format in ListFormat::ALL {
+                    ui.selectable_value(&mut state.export_format, format, format.to_string());
+                }
+            });
+        ui.end_row();
+    });
+
+    if ui.button("Export").clicked() {
+        export(app_win);
+    }
+}
+
+fn add_import_section(ui: &mut Ui, app_win: &mut AppWin) {
+    let state = &mut app_win.flag_exchange;
+
+    ui.heading("Import");
+    ui.label(
+        "Adds the players in a TF2BD playerlist to the database as flags from the given source.",
+    );
+    ui.add_space(8.0);
+
+    Grid::new("flag_import_grid").num_columns(2).show(ui, |ui| {
+        ui.label("File:");
+        let response =
+            ui.add(TextEdit::singleline(&mut state.import_filename).desired_width(300.0));
+        if response.lost_focus() && state.import_source.trim().is_empty() {
+            state.import_source =
+                flag_exchange::default_import_source(state.import_filename.trim());
+        }
+        ui.end_row();
+
+        ui.label("Source name:");
+        ui.add(
+            TextEdit::singleline(&mut state.import_source)
+                .hint_text("Shown as the source of the flags")
+                .desired_width(300.0),
+        );
+        ui.end_row();
+
+        ui.label("Existing flags:");
+        ComboBox::from_id_salt("flag_import_policy")
+            .selected_text(state.import_policy.to_string())
+            .show_ui(ui, |ui| {
+                for policy in ConflictPolicy::ALL {
+                    ui.selectable_value(&mut state.import_policy, policy, policy.to_string());
+                }
+            })
+            .response
+            .on_hover_text(
+                "What to do with players that already have flags from this source:\n\
+                    keep: leave them as they are\n\
+                    overwrite: replace them with the flags in the file\n\
+                    merge: add the flags in the file to them",
+            );
+        ui.end_row();
+    });
+
+    if ui.button("Import").clicked() {
+        import(app_win);
+    }
+}
+
+fn export(app_win: &mut AppWin) {
+    let state = &mut app_win.flag_exchange;
+
+    let mut filter = state.export_filter.clone();
+    if state.export_recent_only {
+        filter.seen_since =
+            Some(Utc::now().timestamp() - i64::from(state.export_recent_days) * 24 * 60 * 60);
+    }
+
+    let file_info = FileInfo {
+        authors: state
+            .authors
+            .split(',')
+            .map(str::trim)
+            .filter(|x| !x.is_empty())
+            .map(str::to_string)
+            .collect(),
+        description: state.description.trim().to_string(),
+        title: state.title.trim().to_string(),
+        update_url: state.update_url.trim().to_string(),
+    };
+
+    let filename = state.export_filename.trim().to_string();
+    let format = state.export_format;
+
+    state.status = Some(match app_win.db.get() {
+        Ok(mut conn) => {
+            flag_exchange::export_to_file(&mut conn, &filter, file_info, &filename, format)
+                .map(|players| format!("Exported {} players to {}", players, filename))
+                .map_err(|e| format!("Export failed: {}", e))
+        }
+        Err(e) => Err(format!("Database error: {}", e)),
+    });
+}
+
+fn import(app_win: &mut AppWin) {
+    let state = &mut app_win.flag_exchange;
+
+    let filename = state.import_filename.trim().to_string();
+    let source = state.import_source.trim().to_string();
+
+    state.status = Some(match app_win.db.get() {
+        Ok(mut conn) => flag_exchange::import_from_file(
+            &mut conn,
+            &filename,
+            &source,
+            state.import_policy,
+            None,
+            &app_win.app_settings.attribute_mapping,
+        )
+        .map(|summary| format!("Imported {}: {}", filename, summary))
+        .map_err(|e| format!("Import failed: {}", e)),
+        Err(e) => Err(format!("Database error: {}", e)),
+    });
+}
@@ -1,7 +1,7 @@
 use super::colors::color_for_flag;
 use crate::{
     tf2::lobby::{Player, player_attribute_description},
-    tf2bd::models::PlayerAttribute,
+    tf2bd::{MarkingSource, models::PlayerAttribute},
 };
 use eframe::egui::{Button, Color32, TextWrapMode, Ui};
 
@@ -43,21 +43,52 @@ pub fn add_flags(ui: &mut Ui, player: &Player) {
         PlayerAttribute::Exploiter,
     ];
 
-    if let Some(player_info) = &player.player_info {
-        for player_attribute in player_attributes_to_show {
-            if player_info.attributes.contains(&player_attribute) {
-                add_flag(ui, player_attribute);
-            }
+    let suggested_attributes = player.suggested_attributes();
+
+    for player_attribute in player_attributes_to_show {
+        let source = player.marking_sources.get(&player_attribute);
+        if let Some(player_info) = &player.player_info
+            && player_info.attributes.contains(&player_attribute)
+        {
+            add_flag(ui, player_attribute, source);
+            continue;
+        }
+
+        if suggested_attributes.contains(&player_attribute) {
+            add_suggested_flag(ui, player_attribute, source);
         }
     }
 }
 
-fn add_flag(ui: &mut Ui, player_attribute: PlayerAttribute) {
+fn add_flag(ui: &mut Ui, player_attribute: PlayerAttribute, source: Option<&MarkingSource>) {
     let (fgcolor, bgcolor) = color_for_flag(player_attribute);
 
     let (text, tooltip) = player_attribute_description(player_attribute);
+    let tooltip = match source {
+        Some(source) => format!("{}\nSource: {}", tooltip, source.source),
+        None => tooltip.to_string(),
+    };
+
+    add_badge(ui, text, fgcolor, bgcolor, &tooltip);
+}
+
+/// Suggested flags are drawn inverted with a question mark,
+/// to show they are not acted upon
+fn add_suggested_flag(
+    ui: &mut Ui,
+    player_attribute: PlayerAttribute,
+    source: Option<&MarkingSource>,
+) {
+    let (_fgcolor, bgcolor) = color_for_flag(player_attribute);
+
+    let (text, tooltip) = player_attribute_description(player_attribute);
+    let text = format!("{}?", text);
+    let tooltip = match source {
+        Some(source) => format!("Suggested: {}\nSource: {}", tooltip, source.source),
+        None => format!("Suggested: {}", tooltip),
+    };
 
-    add_badge(ui, text, fgcolor, bgcolor, tooltip);
+    add_badge(ui, &text, bgcolor, Color32::TRANSPARENT, &tooltip);
 }
 
 fn add_team_symbol_basic_rep(ui: &mut Ui, player: &Player) {
@@ -4,6 +4,7 @@ pub mod chat;
 pub mod colors;
 pub mod comments;
 pub mod db_statistics_window;
+pub mod flag_exchange_window;
 pub mod friendship_indicators;
 pub mod kill_feed;
 pub mod markings;
@@ -11,13 +12,17 @@ pub mod player_database_window;
 pub mod player_details_panel;
 pub mod player_flag_editor;
 pub mod player_tooltip;
+pub mod playerlist_editor_window;
 pub mod playtime;
 pub mod recently_left;
+pub mod rule_editor_window;
 pub mod scoreboard;
 pub mod scoreboard_team;
 pub mod settings_window;
+pub mod timeline;
 pub mod top_menu;
 pub mod ui_utils;
+pub mod vote_panel;
 pub mod window_status_row;
 
 use self::friendship_indicators::add_friendship_indicators;
@@ -31,15 +36,20 @@ use background_image::get_background_image_desc;
 use chat::add_chat;
 use db_statistics_window::show_db_statistics_window;
 use eframe::egui::{self};
+use flag_exchange_window::show_flag_exchange_window;
 use kill_feed::add_kill_feed;
 use player_database_window::show_player_database_window;
 use player_details_panel::{add_player_details_panel, show_player_details_windows};
+use playerlist_editor_window::show_playerlist_editor_window;
+use rule_editor_window::show_rule_editor_window;
 use settings_window::show_settings_window;
 use std::{
     sync::{Arc, Mutex},
     thread::sleep,
 };
+use timeline::add_timeline;
 use top_menu::add_top_menu;
+use vote_panel::add_vote_panel;
 use window_status_row::add_status_row;
 
 pub fn run(
@@ -107,12 +117,22 @@ impl eframe::App for AppWin {
 
             scoreboard::add_scoreboard(self, ui);
 
+            add_vote_panel(self, ui);
+
             ui.separator();
 
-            ui.columns(2, |ui| {
-                add_chat(&mut ui[0], self);
-                add_kill_feed(&mut ui[1], self);
-            });
+            if self.app_settings.show_timeline {
+                ui.columns(3, |ui| {
+                    add_chat(&mut ui[0], self);
+                    add_kill_feed(&mut ui[1], self);
+                    add_timeline(&mut ui[2], self);
+                });
+            } else {
+                ui.columns(2, |ui| {
+                    add_chat(&mut ui[0], self);
+                    add_kill_feed(&mut ui[1], self);
+                });
+            }
 
             if self.app_settings.show_friendship_indicators {
                 add_friendship_indicators(self, ui);
@@ -128,6 +148,15 @@ impl eframe::App for AppWin {
         // Show player database window if open
         show_player_database_window(self, ctx);
 
+        // Show playerlist editor window if open
+        show_playerlist_editor_window(self, ctx);
+
+        // Show rule editor window if open
+        show_rule_editor_window(self, ctx);
+
+        // Show flag export/import window if open
+        show_flag_exchange_window(self, ctx);
+
         // Show separate player detail windows
         show_player_details_windows(self, ctx);
 
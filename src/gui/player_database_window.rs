@@ -1,14 +1,28 @@
 use crate::{
     db::{
-        entities::{Account, PlayerFlag},
-        queries::{get_all_accounts, get_all_player_flags},
+        entities::{Account, Encounter, PlayerFlag},
+        queries::{get_all_accounts, get_all_encounters, get_all_player_flags},
     },
     models::AppWin,
+    tf2::lobby::encounters::format_encounter_time,
     tf2bd::models::PlayerAttribute,
+    utils::unix_time_i64_to_local_datetime,
 };
 use eframe::egui::{self, Grid, ScrollArea, TextEdit, Ui};
 use std::collections::{HashMap, HashSet};
 
+/// Column the player database is sorted by
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PlayerDatabaseSort {
+    Name,
+    SteamId,
+    Matches,
+    TimeTogether,
+    SameTeam,
+    EnemyTeam,
+    LastSeen,
+}
+
 const PLAYER_DATABASE_FILTERS: [PlayerAttribute; 6] = [
     PlayerAttribute::Cheater,
     PlayerAttribute::Bot,
@@ -73,6 +87,20 @@ fn show_player_database_content(ui: &mut Ui, app_win: &mut AppWin) {
         }
     };
 
+    let encounters: HashMap<i64, Encounter> = match get_all_encounters(&mut conn) {
+        Ok(encounters) => encounters
+            .into_iter()
+            .map(|encounter| (encounter.steam_id, encounter))
+            .collect(),
+        Err(e) => {
+            ui.colored_label(
+                ui.visuals().error_fg_color,
+                format!("Failed to load encounters: {}", e),
+            );
+            return;
+        }
+    };
+
     let flags_by_steamid = group_flags_by_steamid(&player_flags);
 
     ui.heading("Filters");
@@ -108,11 +136,15 @@ fn show_player_database_content(ui: &mut Ui, app_win: &mut AppWin) {
         }
     });
 
-    let filtered_accounts: Vec<&Account> = accounts
+    let mut filtered_accounts: Vec<&Account> = accounts
         .iter()
         .filter(|account| account_matches_filters(account, &flags_by_steamid, app_win))
         .collect();
 
+    if let Some((sort, descending)) = app_win.player_database_sort {
+        sort_accounts(&mut filtered_accounts, &encounters, sort, descending);
+    }
+
     ui.add(egui::Separator::default().spacing(30.0));
 
     ui.label(format!(
@@ -126,11 +158,16 @@ fn show_player_database_content(ui: &mut Ui, app_win: &mut AppWin) {
         .show(ui, |ui| {
             Grid::new("player_database_grid")
                 .striped(true)
-                .num_columns(3)
+                .num_columns(8)
                 .show(ui, |ui| {
-                    ui.strong("Name");
-                    ui.strong("SteamID64");
+                    add_sort_header(ui, app_win, PlayerDatabaseSort::Name, "Name");
+                    add_sort_header(ui, app_win, PlayerDatabaseSort::SteamId, "SteamID64");
                     ui.strong("Flags");
+                    add_sort_header(ui, app_win, PlayerDatabaseSort::Matches, "Matches");
+                    add_sort_header(ui, app_win, PlayerDatabaseSort::TimeTogether, "Time");
+                    add_sort_header(ui, app_win, PlayerDatabaseSort::SameTeam, "Same team");
+                    add_sort_header(ui, app_win, PlayerDatabaseSort::EnemyTeam, "Enemy");
+                    add_sort_header(ui, app_win, PlayerDatabaseSort::LastSeen, "Last seen");
                     ui.end_row();
 
                     for account in filtered_accounts {
@@ -150,12 +187,94 @@ fn show_player_database_content(ui: &mut Ui, app_win: &mut AppWin) {
                         }
 
                         ui.label(format_flags(flags_by_steamid.get(&account.steam_id)));
+
+                        match encounters.get(&account.steam_id) {
+                            Some(encounter) => {
+                                ui.label(encounter.matches.to_string());
+                                ui.label(format_encounter_time(encounter.seconds));
+                                ui.label(encounter.same_team.to_string());
+                                ui.label(encounter.enemy_team.to_string());
+                                ui.label(
+                                    unix_time_i64_to_local_datetime(Some(encounter.last_seen))
+                                        .map(|when| when.format("%Y-%m-%d").to_string())
+                                        .unwrap_or_default(),
+                                );
+                            }
+                            None => {
+                                for _ in 0..5 {
+                                    ui.label("-");
+                                }
+                            }
+                        }
                         ui.end_row();
                     }
                 });
         });
 }
 
+/// A column header that sorts by the column when clicked, and reverses the order when clicked again
+fn add_sort_header(ui: &mut Ui, app_win: &mut AppWin, sort: PlayerDatabaseSort, text: &str) {
+    let current = app_win.player_database_sort;
+    let text = match current {
+        Some((current_sort, true)) if current_sort == sort => format!("{} ⏷", text),
+        Some((current_sort, false)) if current_sort == sort => format!("{} ⏶", text),
+        _ => text.to_string(),
+    };
+
+    if ui
+        .selectable_label(
+            current.is_some_and(|(x, _)| x == sort),
+            egui::RichText::new(text).strong(),
+        )
+        .clicked()
+    {
+        app_win.player_database_sort = match current {
+            Some((current_sort, descending)) if current_sort == sort => Some((sort, !descending)),
+            // Names are sorted A-Z first, numbers highest first
+            _ => Some((
+                sort,
+                !matches!(sort, PlayerDatabaseSort::Name | PlayerDatabaseSort::SteamId),
+            )),
+        };
+    }
+}
+
+fn sort_accounts(
+    accounts: &mut [&Account],
+    encounters: &HashMap<i64, Encounter>,
+    sort: PlayerDatabaseSort,
+    descending: bool,
+) {
+    // The value of the numeric columns, 0 for players we have not met
+    let encounter_value = |account: &Account| {
+        let Some(encounter) = encounters.get(&account.steam_id) else {
+            return 0;
+        };
+        match sort {
+            PlayerDatabaseSort::Matches => i64::from(encounter.matches),
+            PlayerDatabaseSort::TimeTogether => encounter.seconds,
+            PlayerDatabaseSort::SameTeam => i64::from(encounter.same_team),
+            PlayerDatabaseSort::EnemyTeam => i64::from(encounter.enemy_team),
+            PlayerDatabaseSort::LastSeen => encounter.last_seen,
+            PlayerDatabaseSort::Name | PlayerDatabaseSort::SteamId => 0,
+        }
+    };
+
+    accounts.sort_by(|a, b| {
+        let ordering = match sort {
+            PlayerDatabaseSort::Name => a.name.to_lowercase().cmp(&b.name.to_lowercase()),
+            PlayerDatabaseSort::SteamId => a.steam_id.cmp(&b.steam_id),
+            _ => encounter_value(a).cmp(&encounter_value(b)),
+        };
+
+        if descending {
+            ordering.reverse()
+        } else {
+            ordering
+        }
+    });
+}
+
 fn group_flags_by_steamid(player_flags: &[PlayerFlag]) -> HashMap<i64, HashSet<String>> {
     let mut grouped = HashMap::new();
 
@@ -1,10 +1,16 @@
 use super::{
     colors::hex_to_rgb, comments::add_profile_comments, player_flag_editor::add_player_flag_editor,
-    playtime::add_playtime,
+    playtime::add_playtime, rule_editor_window::RuleEditorState,
 };
 use crate::{
+    db::queries,
     models::{AppWin, steamid::SteamID},
-    tf2::lobby::{Player, PlayerKill},
+    tf2::lobby::{
+        Player, PlayerKill,
+        encounters::{encounter_names, format_encounter_time},
+    },
+    tf2bd::flag_proof::proof_context,
+    utils::unix_time_i64_to_local_datetime,
 };
 use eframe::egui::{
     self, Color32, Image, OpenUrl, ScrollArea, TextFormat, Ui, Vec2, text::LayoutJob,
@@ -60,7 +66,7 @@ pub fn show_player_details_windows(app_win: &mut AppWin, ctx: &egui::Context) {
     app_win.open_player_details_windows = still_open;
 }
 
-pub fn add_player_details_for_steamid(app_win: &AppWin, ui: &mut Ui, steamid: SteamID) {
+pub fn add_player_details_for_steamid(app_win: &mut AppWin, ui: &mut Ui, steamid: SteamID) {
     let player = get_player_by_steamid(app_win, steamid);
 
     if player.is_none() {
@@ -70,7 +76,8 @@ pub fn add_player_details_for_steamid(app_win: &AppWin, ui: &mut Ui, steamid: St
         ));
         return;
     }
-    let player = player.unwrap();
+    let player = player.unwrap().clone();
+    let player = &player;
 
     ui.label("");
     add_player_kick_buttons(app_win, player, ui);
@@ -126,6 +133,26 @@ pub fn add_player_details_for_steamid(app_win: &AppWin, ui: &mut Ui, steamid: St
 
     ui.label("");
 
+    add_player_flag_proofs(app_win, player, ui);
+
+    ui.label("");
+
+    add_player_encounters(player, ui);
+
+    ui.label("");
+
+    add_player_name_history(app_win, player, ui);
+
+    ui.label("");
+
+    add_player_match_sessions(app_win, player, ui);
+
+    ui.label("");
+
+    add_player_rule_buttons(app_win, player, ui);
+
+    ui.label("");
+
     add_player_sourcebans(player, ui);
 
     ui.label("");
@@ -186,6 +213,182 @@ fn add_player_kills(player: &Player, ui: &mut Ui) {
     ui.label(names);
 }
 
+/// Shows why the player was flagged, as stored with their flags
+fn add_player_flag_proofs(app_win: &AppWin, player: &Player, ui: &mut Ui) {
+    ui.heading("Evidence");
+
+    let proofs = match app_win.db.get() {
+        Ok(mut conn) => queries::get_player_flag_proofs(&mut conn, player.steamid.to_u64() as i64),
+        Err(e) => {
+            ui.label(format!("Database error: {}", e));
+            return;
+        }
+    };
+    let proofs = match proofs {
+        Ok(proofs) => proofs,
+        Err(e) => {
+            ui.label(format!("Failed to load evidence: {}", e));
+            return;
+        }
+    };
+
+    if proofs.is_empty() {
+        ui.label("No evidence stored");
+        return;
+    }
+
+    for proof in proofs.iter().rev() {
+        let created = unix_time_i64_to_local_datetime(Some(proof.created))
+            .map(|created| created.format("%Y-%m-%d %H:%M").to_string())
+            .unwrap_or_default();
+
+        ui.label(
+            egui::RichText::new(format!(
+                "{} ({}) {}",
+                proof.flag_type, proof.source, created
+            ))
+            .color(Color32::GRAY),
+        );
+        if let Some(reason) = &proof.reason {
+            ui.label(format!("\"{}\"", reason));
+        }
+        if let Some(context) = proof_context(proof) {
+            ui.label(context);
+        }
+    }
+}
+
+fn add_player_encounters(player: &Player, ui: &mut Ui) {
+    ui.heading("Encounters");
+
+    let Some(encounter) = &player.encounter else {
+        ui.label("Not met before");
+        return;
+    };
+
+    let format_time = |unix_time| {
+        unix_time_i64_to_local_datetime(Some(unix_time))
+            .map(|when| when.format("%Y-%m-%d %H:%M").to_string())
+            .unwrap_or_default()
+    };
+
+    ui.label(format!("First seen: {}", format_time(encounter.first_seen)));
+    ui.label(format!("Last seen: {}", format_time(encounter.last_seen)));
+    ui.label(format!(
+        "{} matches, {} together",
+        encounter.matches,
+        format_encounter_time(encounter.seconds)
+    ));
+    ui.label(format!(
+        "{} times on our team, {} times on the enemy team",
+        encounter.same_team, encounter.enemy_team
+    ));
+    ui.label(format!(
+        "Names: {}",
+        encounter_names(encounter).collect::<Vec<_>>().join(", ")
+    ));
+}
+
+/// Every name the player has been seen with, latest first
+fn add_player_name_history(app_win: &AppWin, player: &Player, ui: &mut Ui) {
+    ui.heading("Name history");
+
+    let names = match app_win.db.get() {
+        Ok(mut conn) => queries::get_name_history(&mut conn, player.steamid.to_u64() as i64),
+        Err(e) => {
+            ui.label(format!("Database error: {}", e));
+            return;
+        }
+    };
+    let names = match names {
+        Ok(names) => names,
+        Err(e) => {
+            ui.label(format!("Failed to load name history: {}", e));
+            return;
+        }
+    };
+
+    if names.len() <= 1 {
+        ui.label("No name changes seen");
+        return;
+    }
+
+    for name in names.iter().rev() {
+        let seen = unix_time_i64_to_local_datetime(Some(name.seen))
+            .map(|seen| seen.format("%Y-%m-%d %H:%M").to_string())
+            .unwrap_or_default();
+
+        let mut text = format!("{} {} ({})", seen, name.name, name.source);
+        if name.mid_match {
+            text += ", renamed during a match";
+        }
+
+        if name.mid_match {
+            ui.colored_label(Color32::GOLD, text);
+        } else {
+            ui.label(text);
+        }
+    }
+}
+
+/// Earlier lobbies the player was in, from the match sessions in the database
+fn add_player_match_sessions(app_win: &AppWin, player: &Player, ui: &mut Ui) {
+    ui.heading("Played together");
+
+    let sessions = match app_win.db.get() {
+        Ok(mut conn) => {
+            queries::get_player_match_sessions(&mut conn, player.steamid.to_u64() as i64)
+        }
+        Err(e) => {
+            ui.label(format!("Database error: {}", e));
+            return;
+        }
+    };
+    let sessions = match sessions {
+        Ok(sessions) => sessions,
+        Err(e) => {
+            ui.label(format!("Failed to load match sessions: {}", e));
+            return;
+        }
+    };
+
+    let earlier: Vec<_> = sessions
+        .iter()
+        .filter(|(_, session)| session.lobby_id != app_win.lobby.lobby_id)
+        .collect();
+
+    if earlier.is_empty() {
+        ui.label("Not seen in earlier matches");
+        return;
+    }
+
+    ui.label(format!("Seen in {} earlier matches", earlier.len()));
+
+    for (session_player, session) in earlier.iter().take(10) {
+        let started = unix_time_i64_to_local_datetime(Some(session.started))
+            .map(|started| started.format("%Y-%m-%d %H:%M").to_string())
+            .unwrap_or_default();
+
+        let mut text = format!(
+            "{} {} as {}, {}, {}/{} K/D",
+            started,
+            session.map.as_deref().unwrap_or("unknown map"),
+            session_player.name,
+            session_player.team,
+            session_player.kills,
+            session_player.deaths
+        );
+        if !session_player.flags.is_empty() {
+            text += &format!(", {}", session_player.flags.replace(',', ", "));
+        }
+
+        let response = ui.label(text);
+        if let Some(server) = session.server.as_ref().or(session.address.as_ref()) {
+            response.on_hover_text(server);
+        }
+    }
+}
+
 fn add_player_community_links(player: &Player, ui: &mut Ui) {
     fn make_link(ui: &mut Ui, url: String, text: &str) {
         if ui.button(text).clicked() {
@@ -211,6 +414,34 @@ fn add_player_community_links(player: &Player, ui: &mut Ui) {
     });
 }
 
+fn add_player_rule_buttons(app_win: &mut AppWin, player: &Player, ui: &mut Ui) {
+    // The user picks the list the rule goes to in the editor
+    if app_win.app_settings.player_lists.is_empty() {
+        return;
+    }
+
+    let avatar_hash = player
+        .steam_info
+        .as_ref()
+        .and_then(|steam_info| steam_info.avatar_hash.clone());
+
+    ui.heading("Rules:");
+    ui.horizontal_wrapped(|ui| {
+        if let Some(avatar_hash) = &avatar_hash
+            && ui
+                .button("Create rule from this avatar")
+                .on_hover_text(format!("Avatar hash: {}", avatar_hash))
+                .clicked()
+        {
+            app_win.rule_editor = Some(RuleEditorState::from_avatar(player, avatar_hash));
+        }
+
+        if ui.button("Create rule from this name").clicked() {
+            app_win.rule_editor = Some(RuleEditorState::from_name(player));
+        }
+    });
+}
+
 fn add_player_kick_buttons(app_win: &AppWin, player: &Player, ui: &mut Ui) {
     ui.heading("Kick Player:");
     ui.horizontal_wrapped(|ui| {
@@ -5,9 +5,9 @@ use crate::{
     tf2::lobby::{Player, player_attribute_description},
     tf2bd::models::PlayerAttribute,
 };
-use eframe::egui::{Checkbox, Ui};
+use eframe::egui::{Checkbox, TextEdit, Ui};
 
-pub fn add_player_flag_editor(app_win: &AppWin, ui: &mut Ui, player: &Player) {
+pub fn add_player_flag_editor(app_win: &mut AppWin, ui: &mut Ui, player: &Player) {
     ui.heading("Flags:");
     ui.horizontal_wrapped(|ui| {
         let player_attributes_to_show = vec![
@@ -30,10 +30,28 @@ pub fn add_player_flag_editor(app_win: &AppWin, ui: &mut Ui, player: &Player) {
             add_flag(app_win, ui, player_attribute, enable, player);
         }
     });
+
+    let mut reason = app_win
+        .flag_reasons
+        .get(&player.steamid)
+        .cloned()
+        .unwrap_or_default();
+    let response = ui.add(
+        TextEdit::singleline(&mut reason)
+            .hint_text("Reason, saved as proof with the next flag you set")
+            .desired_width(f32::INFINITY),
+    );
+    if response.changed() {
+        if reason.is_empty() {
+            app_win.flag_reasons.remove(&player.steamid);
+        } else {
+            app_win.flag_reasons.insert(player.steamid, reason);
+        }
+    }
 }
 
 fn add_flag(
-    app_win: &AppWin,
+    app_win: &mut AppWin,
     ui: &mut Ui,
     player_attribute: PlayerAttribute,
     mut enable: bool,
@@ -74,6 +92,12 @@ fn add_flag(
         let response = ui.add(checkbox);
 
         if response.clicked() {
+            // The reason belongs to the flag being set, not to one being removed
+            let reason = app_win
+                .flag_reasons
+                .remove(&player.steamid)
+                .filter(|reason| enable && !reason.trim().is_empty());
+
             app_win
                 .bus
                 .lock()
@@ -81,9 +105,10 @@ fn add_flag(
                 .app_event_bus
                 .broadcast(AppEventMsg::SetPlayerFlag {
                     steamid: player.steamid,
-                    name: player.name.clone(),
+                    name: Some(player.name.clone()),
                     flag: player_attribute,
                     enable,
+                    reason,
                 });
         }
     });
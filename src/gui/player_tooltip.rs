@@ -1,4 +1,4 @@
-use crate::tf2::lobby::Player;
+use crate::tf2::lobby::{Player, encounters::format_encounter_time};
 use eframe::egui::{Image, Ui};
 
 use super::{markings::add_flags, playtime::add_playtime};
@@ -37,5 +37,15 @@ pub fn add_player_tooltip(ui: &mut Ui, player: &Player) {
         ui.label("No Steam bans");
     }
 
+    if let Some(encounter) = &player.encounter {
+        ui.label(format!(
+            "Met in {} matches, {} together",
+            encounter.matches,
+            format_encounter_time(encounter.seconds)
+        ));
+    } else {
+        ui.label("Not met before");
+    }
+
     add_flags(ui, player);
 }
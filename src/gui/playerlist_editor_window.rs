@@ -0,0 +1,551 @@
+use crate::{
+    appbus::AppEventMsg,
+    models::{AppWin, steamid::SteamID},
+    tf2::lobby::player_attribute_description,
+    tf2bd::{
+        formats,
+        models::{PlayerAttribute, PlayerInfo, PlayerLastSeen},
+    },
+    utils::unix_time_i64_to_local_datetime,
+};
+use chrono::Local;
+use eframe::egui::{self, ComboBox, Grid, ScrollArea, TextEdit, Ui};
+use std::collections::HashSet;
+
+const PLAYERLIST_EDITOR_ATTRIBUTES: [PlayerAttribute; 6] = [
+    PlayerAttribute::Cheater,
+    PlayerAttribute::Bot,
+    PlayerAttribute::Suspicious,
+    PlayerAttribute::Toxic,
+    PlayerAttribute::Exploiter,
+    PlayerAttribute::Cool,
+];
+
+/// State of the playerlist editor window
+#[derive(Default)]
+pub struct PlayerListEditorState {
+    /// All entries in all loaded lists, None until the window has been opened
+    pub entries: Option<Vec<PlayerListEntry>>,
+
+    pub search: String,
+    pub filters: HashSet<PlayerAttribute>,
+    pub selected: HashSet<SteamID>,
+
+    pub add_steamid: String,
+    pub add_name: String,
+    pub add_attribute: Option<PlayerAttribute>,
+    pub add_error: Option<String>,
+}
+
+/// A player in one of the loaded TF2BD lists
+pub struct PlayerListEntry {
+    pub filename: String,
+
+    /// The list is where TF2Monitor saves flags, entries in other lists are read only
+    pub own_list: bool,
+    pub steamid: SteamID,
+    pub player_info: PlayerInfo,
+}
+
+pub fn show_playerlist_editor_window(app_win: &mut AppWin, ctx: &egui::Context) {
+    if !app_win.playerlist_editor_window_open {
+        return;
+    }
+
+    if app_win.playerlist_editor.entries.is_none() {
+        load_entries(app_win);
+    }
+
+    let mut window_open = app_win.playerlist_editor_window_open;
+
+    egui::Window::new("Player Lists")
+        .open(&mut window_open)
+        .resizable(true)
+        .default_width(980.0)
+        .default_height(700.0)
+        .show(ctx, |ui| {
+            show_playerlist_editor_content(ui, app_win);
+        });
+
+    if !window_open {
+        app_win.playerlist_editor_window_open = false;
+        app_win.playerlist_editor.entries = None;
+    }
+}
+
+fn load_entries(app_win: &mut AppWin) {
+    let own_list = app_win
+        .app_settings
+        .player_lists
+        .iter()
+        .find(|player_list| !player_list.suggestion)
+        .map(|player_list| player_list.filename.clone());
+
+    let mut entries = Vec::new();
+    for player_list in &app_win.app_settings.player_lists {
+        let (rules_file, _format, _error) = formats::load_or_backup(
+            &player_list.filename,
+            player_list.format,
+            &app_win.app_settings.attribute_mapping,
+        );
+
+        for player_info in rules_file.players {
+            let Some(steamid) = SteamID::parse(&player_info.steamid32) else {
+                log::warn!(
+                    "Invalid SteamID '{}' in {}",
+                    player_info.steamid32,
+                    player_list.filename
+                );
+                continue;
+            };
+
+            entries.push(PlayerListEntry {
+                filename: player_list.filename.clone(),
+                own_list: Some(&player_list.filename) == own_list.as_ref(),
+                steamid,
+                player_info,
+            });
+        }
+    }
+
+    app_win.playerlist_editor.entries = Some(entries);
+}
+
+fn show_playerlist_editor_content(ui: &mut Ui, app_win: &mut AppWin) {
+    ui.heading("Filters");
+
+    ui.add_space(8.0);
+
+    ui.horizontal(|ui| {
+        ui.add(
+            TextEdit::singleline(&mut app_win.playerlist_editor.search)
+                .hint_text("Filter by name or SteamID")
+                .desired_width(280.0),
+        );
+
+        if ui
+            .button("Reload")
+            .on_hover_text("Read the player lists from disk again")
+            .clicked()
+        {
+            load_entries(app_win);
+        }
+    });
+
+    ui.add_space(15.0);
+
+    ui.horizontal_wrapped(|ui| {
+        let filters = &mut app_win.playerlist_editor.filters;
+        for player_attribute in PLAYERLIST_EDITOR_ATTRIBUTES {
+            let mut enabled = filters.contains(&player_attribute);
+            if ui
+                .checkbox(&mut enabled, format!("{:?}", player_attribute))
+                .changed()
+            {
+                if enabled {
+                    filters.insert(player_attribute);
+                } else {
+                    filters.remove(&player_attribute);
+                }
+            }
+        }
+
+        if ui.button("Clear filters").clicked() {
+            app_win.playerlist_editor.filters.clear();
+            app_win.playerlist_editor.search.clear();
+        }
+    });
+
+    ui.add(egui::Separator::default().spacing(30.0));
+
+    add_new_entry_row(ui, app_win);
+
+    ui.add(egui::Separator::default().spacing(30.0));
+
+    let state = &app_win.playerlist_editor;
+    let filtered: Vec<usize> = state
+        .entries
+        .as_deref()
+        .unwrap_or_default()
+        .iter()
+        .enumerate()
+        .filter(|(_, entry)| entry_matches_filters(entry, &state.search, &state.filters))
+        .map(|(index, _)| index)
+        .collect();
+
+    add_bulk_edit_row(ui, app_win, &filtered);
+
+    ui.add_space(8.0);
+
+    let entries = app_win
+        .playerlist_editor
+        .entries
+        .as_deref()
+        .unwrap_or_default();
+    ui.label(format!(
+        "Showing {} of {} entries",
+        filtered.len(),
+        entries.len()
+    ));
+
+    let mut edits: Vec<(SteamID, String, PlayerAttribute, bool)> = Vec::new();
+    let mut selected = app_win.playerlist_editor.selected.clone();
+
+    ScrollArea::both()
+        .auto_shrink([false, false])
+        .show(ui, |ui| {
+            Grid::new("playerlist_editor_grid")
+                .striped(true)
+                .num_columns(7)
+                .show(ui, |ui| {
+                    ui.label("");
+                    ui.strong("List");
+                    ui.strong("SteamID32");
+                    ui.strong("Last seen name");
+                    ui.strong("Last seen");
+                    ui.strong("Attributes");
+                    ui.label("");
+                    ui.end_row();
+
+                    for index in &filtered {
+                        let entry = &entries[*index];
+
+                        let mut is_selected = selected.contains(&entry.steamid);
+                        if ui.checkbox(&mut is_selected, "").changed() {
+                            if is_selected {
+                                selected.insert(entry.steamid);
+                            } else {
+                                selected.remove(&entry.steamid);
+                            }
+                        }
+
+                        ui.label(&entry.filename);
+                        ui.label(&entry.player_info.steamid32);
+                        ui.label(last_seen_name(&entry.player_info));
+                        ui.label(last_seen_time(&entry.player_info));
+                        ui.label(format_attributes(&entry.player_info));
+
+                        if entry.own_list {
+                            if ui
+                                .button("Remove")
+                                .on_hover_text("Remove all flags from this player")
+                                .clicked()
+                            {
+                                for attribute in &entry.player_info.attributes {
+                                    edits.push((
+                                        entry.steamid,
+                                        last_seen_name(&entry.player_info),
+                                        *attribute,
+                                        false,
+                                    ));
+                                }
+                            }
+                        } else {
+                            ui.label("");
+                        }
+                        ui.end_row();
+                    }
+                });
+        });
+
+    app_win.playerlist_editor.selected = selected;
+
+    for (steamid, name, attribute, enable) in edits {
+        set_player_flag(app_win, steamid, &name, attribute, enable);
+    }
+}
+
+fn add_new_entry_row(ui: &mut Ui, app_win: &mut AppWin) {
+    let mut add: Option<(SteamID, String, PlayerAttribute)> = None;
+
+    ui.horizontal(|ui| {
+        let state = &mut app_win.playerlist_editor;
+
+        ui.add(
+            TextEdit::singleline(&mut state.add_steamid)
+                .hint_text("SteamID64, [U:1:x], STEAM_0:y:z or profile URL")
+                .desired_width(320.0),
+        );
+        ui.add(
+            TextEdit::singleline(&mut state.add_name)
+                .hint_text("Name (optional)")
+                .desired_width(160.0),
+        );
+
+        let add_attribute = state.add_attribute.get_or_insert(PlayerAttribute::Cheater);
+        ComboBox::from_id_salt("playerlist_editor_add_attribute")
+            .selected_text(format!("{:?}", add_attribute))
+            .show_ui(ui, |ui| {
+                for player_attribute in PLAYERLIST_EDITOR_ATTRIBUTES {
+                    ui.selectable_value(
+                        add_attribute,
+                        player_attribute,
+                        format!("{:?}", player_attribute),
+                    );
+                }
+            });
+        let add_attribute = *add_attribute;
+
+        if ui.button("Add").clicked() {
+            match SteamID::parse(&state.add_steamid) {
+                Some(steamid) => {
+                    let name = state.add_name.trim().to_string();
+                    state.add_steamid.clear();
+                    state.add_name.clear();
+                    state.add_error = None;
+                    add = Some((steamid, name, add_attribute));
+                }
+                None => {
+                    state.add_error = Some(format!("Not a SteamID: '{}'", state.add_steamid));
+                }
+            }
+        }
+    });
+
+    if let Some((steamid, name, attribute)) = add {
+        set_player_flag(app_win, steamid, &name, attribute, true);
+    }
+
+    if let Some(add_error) = &app_win.playerlist_editor.add_error {
+        ui.colored_label(ui.visuals().error_fg_color, add_error);
+    }
+}
+
+fn add_bulk_edit_row(ui: &mut Ui, app_win: &mut AppWin, filtered: &[usize]) {
+    let entries = app_win
+        .playerlist_editor
+        .entries
+        .as_deref()
+        .unwrap_or_default();
+
+    ui.horizontal_wrapped(|ui| {
+        ui.label(format!(
+            "{} selected",
+            app_win.playerlist_editor.selected.len()
+        ));
+
+        if ui.button("Select shown").clicked() {
+            let shown: Vec<SteamID> = filtered.iter().map(|x| entries[*x].steamid).collect();
+            app_win.playerlist_editor.selected.extend(shown);
+        }
+
+        if ui.button("Select none").clicked() {
+            app_win.playerlist_editor.selected.clear();
+        }
+    });
+
+    if app_win.playerlist_editor.selected.is_empty() {
+        return;
+    }
+
+    // The name of each selected player, from the first list that has it
+    let mut selected: Vec<(SteamID, String)> = Vec::new();
+    for steamid in &app_win.playerlist_editor.selected {
+        let name = entries
+            .iter()
+            .find(|entry| entry.steamid == *steamid)
+            .map(|entry| last_seen_name(&entry.player_info))
+            .unwrap_or_default();
+        selected.push((*steamid, name));
+    }
+
+    let mut edits: Vec<(PlayerAttribute, bool)> = Vec::new();
+    let mut remove = false;
+
+    ui.horizontal_wrapped(|ui| {
+        for player_attribute in PLAYERLIST_EDITOR_ATTRIBUTES {
+            let (_, description) = player_attribute_description(player_attribute);
+            if ui
+                .button(format!("+ {}", description))
+                .on_hover_text(format!("Mark the selected players as {}", description))
+                .clicked()
+            {
+                edits.push((player_attribute, true));
+            }
+            if ui
+                .button(format!("- {}", description))
+                .on_hover_text(format!("Unmark {} on the selected players", description))
+                .clicked()
+            {
+                edits.push((player_attribute, false));
+            }
+        }
+
+        ui.separator();
+
+        if ui
+            .button("Remove selected")
+            .on_hover_text("Remove all flags from the selected players in your own list")
+            .clicked()
+        {
+            remove = true;
+        }
+    });
+
+    if remove {
+        let own_entries: Vec<(SteamID, String, Vec<PlayerAttribute>)> = entries
+            .iter()
+            .filter(|entry| {
+                entry.own_list && app_win.playerlist_editor.selected.contains(&entry.steamid)
+            })
+            .map(|entry| {
+                (
+                    entry.steamid,
+                    last_seen_name(&entry.player_info),
+                    entry.player_info.attributes.clone(),
+                )
+            })
+            .collect();
+
+        for (steamid, name, attributes) in own_entries {
+            for attribute in attributes {
+                set_player_flag(app_win, steamid, &name, attribute, false);
+            }
+        }
+        app_win.playerlist_editor.selected.clear();
+    }
+
+    for (attribute, enable) in edits {
+        for (steamid, name) in &selected {
+            set_player_flag(app_win, *steamid, name, attribute, enable);
+        }
+    }
+}
+
+/// Sends the change to the TF2BD thread, which saves it in the user's own list,
+/// and updates the entries shown in the window right away
+fn set_player_flag(
+    app_win: &mut AppWin,
+    steamid: SteamID,
+    name: &str,
+    flag: PlayerAttribute,
+    enable: bool,
+) {
+    let name = if name.is_empty() {
+        None
+    } else {
+        Some(name.to_string())
+    };
+
+    app_win
+        .bus
+        .lock()
+        .unwrap()
+        .app_event_bus
+        .broadcast(AppEventMsg::SetPlayerFlag {
+            steamid,
+            name: name.clone(),
+            flag,
+            enable,
+            reason: None,
+        });
+
+    let own_list = app_win
+        .app_settings
+        .player_lists
+        .iter()
+        .find(|player_list| !player_list.suggestion)
+        .map(|player_list| player_list.filename.clone());
+    let Some(own_list) = own_list else {
+        return;
+    };
+    let Some(entries) = app_win.playerlist_editor.entries.as_mut() else {
+        return;
+    };
+
+    match entries
+        .iter_mut()
+        .position(|entry| entry.own_list && entry.steamid == steamid)
+    {
+        Some(index) => {
+            let player_info = &mut entries[index].player_info;
+            player_info.attributes.retain(|x| *x != flag);
+            if enable {
+                player_info.attributes.push(flag);
+            }
+
+            if player_info.attributes.is_empty() && player_info.unknown_attributes.is_empty() {
+                entries.remove(index);
+            }
+        }
+        None if enable => {
+            entries.push(PlayerListEntry {
+                filename: own_list,
+                own_list: true,
+                steamid,
+                player_info: PlayerInfo {
+                    attributes: vec![flag],
+                    last_seen: Some(PlayerLastSeen {
+                        player_name: name,
+                        time: Local::now().timestamp(),
+                    }),
+                    steamid32: steamid.to_steam_id32(),
+                    ..Default::default()
+                },
+            });
+        }
+        None => {}
+    }
+}
+
+fn entry_matches_filters(
+    entry: &PlayerListEntry,
+    search: &str,
+    filters: &HashSet<PlayerAttribute>,
+) -> bool {
+    let search_text = search.trim();
+    if !search_text.is_empty() {
+        let search_text_lower = search_text.to_lowercase();
+        let name_matches = last_seen_name(&entry.player_info)
+            .to_lowercase()
+            .contains(&search_text_lower);
+        let steamid_matches = entry.player_info.steamid32.contains(search_text)
+            || entry.steamid.to_u64().to_string().contains(search_text);
+
+        if !name_matches && !steamid_matches {
+            return false;
+        }
+    }
+
+    if filters.is_empty() {
+        return true;
+    }
+
+    filters
+        .iter()
+        .any(|filter| entry.player_info.attributes.contains(filter))
+}
+
+fn last_seen_name(player_info: &PlayerInfo) -> String {
+    player_info
+        .last_seen
+        .as_ref()
+        .and_then(|last_seen| last_seen.player_name.clone())
+        .unwrap_or_default()
+}
+
+fn last_seen_time(player_info: &PlayerInfo) -> String {
+    let time = player_info
+        .last_seen
+        .as_ref()
+        .map(|last_seen| last_seen.time);
+    match unix_time_i64_to_local_datetime(time) {
+        Some(when) => when.format("%Y-%m-%d %H:%M").to_string(),
+        None => "-".to_string(),
+    }
+}
+
+fn format_attributes(player_info: &PlayerInfo) -> String {
+    let attributes: Vec<String> = player_info
+        .attributes
+        .iter()
+        .map(|attribute| format!("{:?}", attribute))
+        .chain(player_info.unknown_attributes.iter().cloned())
+        .collect();
+
+    if attributes.is_empty() {
+        return "-".to_string();
+    }
+
+    attributes.join(", ")
+}
@@ -0,0 +1,707 @@
+use crate::{
+    appbus::AppEventMsg,
+    db::{
+        entities::Account,
+        queries::{get_accounts_by_avatar_hash, get_all_accounts},
+    },
+    models::{AppWin, steamid::SteamID},
+    tf2::{
+        lobby::{Lobby, Player},
+        steamapi::avatar_hash_from_url,
+    },
+    tf2bd::{
+        models::{
+            AvatarMatch, PlayerAttribute, Rule, RuleAction, TextMatch, TextMatchMode, Trigger,
+            TriggerMode,
+        },
+        rule_engine::{RuleEngine, RuleInput},
+    },
+};
+use diesel::SqliteConnection;
+use eframe::egui::{self, ComboBox, Grid, ScrollArea, TextEdit, Ui};
+use regex::Regex;
+use std::cell::RefCell;
+
+const RULE_EDITOR_ATTRIBUTES: [PlayerAttribute; 6] = [
+    PlayerAttribute::Cheater,
+    PlayerAttribute::Bot,
+    PlayerAttribute::Suspicious,
+    PlayerAttribute::Toxic,
+    PlayerAttribute::Exploiter,
+    PlayerAttribute::Cool,
+];
+
+const TEXT_MATCH_MODES: [TextMatchMode; 6] = [
+    TextMatchMode::Equal,
+    TextMatchMode::Contains,
+    TextMatchMode::StartsWith,
+    TextMatchMode::EndsWith,
+    TextMatchMode::Word,
+    TextMatchMode::Regex,
+];
+
+/// How many matching players the preview lists
+const PREVIEW_MAX_ROWS: usize = 50;
+
+/// State of the rule editor window, a rule being written before it is added to a list
+pub struct RuleEditorState {
+    pub description: String,
+    pub trigger_mode: TriggerMode,
+
+    pub use_name: bool,
+    pub name_mode: TextMatchMode,
+    pub name_case_sensitive: bool,
+    /// One pattern per line
+    pub name_patterns: String,
+
+    pub use_avatar: bool,
+    /// One avatar hash per line
+    pub avatar_hashes: String,
+
+    pub mark: Vec<PlayerAttribute>,
+    pub unmark: Vec<PlayerAttribute>,
+
+    /// The list file the rule is added to, empty until the user picks one
+    pub filename: String,
+
+    pub preview: Option<RulePreview>,
+
+    /// The last regex patterns checked and the error, the rule is built every frame
+    regex_check: RefCell<Option<(String, Result<(), String>)>>,
+}
+
+/// The players a rule would match
+pub struct RulePreview {
+    pub lobby: Vec<(SteamID, String)>,
+    pub accounts: Vec<(SteamID, String)>,
+    pub error: Option<String>,
+}
+
+impl RuleEditorState {
+    /// A rule that marks every player with the same avatar as the player
+    pub fn from_avatar(player: &Player, avatar_hash: &str) -> Self {
+        Self {
+            description: format!("Avatar of {}", player.name),
+            use_avatar: true,
+            avatar_hashes: avatar_hash.to_string(),
+            ..Self::new()
+        }
+    }
+
+    /// A rule that marks every player with the same name as the player
+    pub fn from_name(player: &Player) -> Self {
+        Self {
+            description: format!("Name {}", player.name),
+            use_name: true,
+            name_patterns: player.name.clone(),
+            ..Self::new()
+        }
+    }
+
+    fn new() -> Self {
+        Self {
+            description: String::new(),
+            trigger_mode: TriggerMode::MatchAll,
+            use_name: false,
+            name_mode: TextMatchMode::Equal,
+            name_case_sensitive: true,
+            name_patterns: String::new(),
+            use_avatar: false,
+            avatar_hashes: String::new(),
+            mark: vec![PlayerAttribute::Bot],
+            unmark: Vec::new(),
+            filename: String::new(),
+            preview: None,
+            regex_check: RefCell::new(None),
+        }
+    }
+
+    /// Builds the TF2BD rule, or returns why the rule is not complete
+    pub fn build_rule(&self) -> Result<Rule, String> {
+        if self.description.trim().is_empty() {
+            return Err("The rule needs a description".to_string());
+        }
+
+        let username_text_match = if self.use_name {
+            let patterns = lines(&self.name_patterns);
+            if patterns.is_empty() {
+                return Err("Add at least one name pattern".to_string());
+            }
+            if self.name_mode == TextMatchMode::Regex {
+                self.check_regex_patterns(&patterns)?;
+            }
+
+            Some(TextMatch {
+                case_sensitive: self.name_case_sensitive,
+                mode: self.name_mode.clone(),
+                patterns,
+            })
+        } else {
+            None
+        };
+
+        let avatar_match = if self.use_avatar {
+            let avatar_hashes = lines(&self.avatar_hashes);
+            if avatar_hashes.is_empty() {
+                return Err("Add at least one avatar hash".to_string());
+            }
+
+            Some(
+                avatar_hashes
+                    .into_iter()
+                    .map(|avatar_hash| AvatarMatch { avatar_hash })
+                    .collect(),
+            )
+        } else {
+            None
+        };
+
+        if username_text_match.is_none() && avatar_match.is_none() {
+            return Err("Select a name or an avatar trigger".to_string());
+        }
+
+        if self.mark.is_empty() && self.unmark.is_empty() {
+            return Err("Select attributes to mark or unmark".to_string());
+        }
+
+        if self.filename.is_empty() {
+            return Err("Select the list to add the rule to".to_string());
+        }
+
+        Ok(Rule {
+            actions: RuleAction {
+                mark: self.mark.clone(),
+                unmark: self.unmark.clone(),
+                unknown_mark: Vec::new(),
+                unknown_unmark: Vec::new(),
+            },
+            description: self.description.trim().to_string(),
+            triggers: Trigger {
+                mode: self.trigger_mode.clone(),
+                username_text_match,
+                chatmsg_text_match: None,
+                avatar_match,
+            },
+        })
+    }
+
+    /// Compiles the patterns only when they changed since the last check
+    fn check_regex_patterns(&self, patterns: &[String]) -> Result<(), String> {
+        let mut regex_check = self.regex_check.borrow_mut();
+        if let Some((checked, result)) = regex_check.as_ref()
+            && *checked == self.name_patterns
+        {
+            return result.clone();
+        }
+
+        let result = patterns.iter().try_for_each(|pattern| {
+            Regex::new(pattern)
+                .map(|_| ())
+                .map_err(|e| format!("Invalid regex '{}': {}", pattern, e))
+        });
+        *regex_check = Some((self.name_patterns.clone(), result.clone()));
+
+        result
+    }
+}
+
+pub fn show_rule_editor_window(app_win: &mut AppWin, ctx: &egui::Context) {
+    if app_win.rule_editor.is_none() {
+        return;
+    }
+
+    let mut window_open = true;
+    let mut add_rule = false;
+
+    egui::Window::new("Rule Editor")
+        .open(&mut window_open)
+        .resizable(true)
+        .default_width(600.0)
+        .default_height(600.0)
+        .show(ctx, |ui| {
+            add_rule = show_rule_editor_content(ui, app_win);
+        });
+
+    if add_rule {
+        send_rule(app_win);
+    } else if !window_open {
+        app_win.rule_editor = None;
+    }
+}
+
+/// Returns true when the user wants to add the rule
+fn show_rule_editor_content(ui: &mut Ui, app_win: &mut AppWin) -> bool {
+    let filenames: Vec<String> = app_win
+        .app_settings
+        .player_lists
+        .iter()
+        .map(|player_list| player_list.filename.clone())
+        .collect();
+
+    // Lists that may be replaced by downloads, the TF2BD thread refuses rules for those with an update_url
+    let own_list = app_win.app_settings.own_player_list();
+    let auto_update_lists: Vec<String> = app_win
+        .app_settings
+        .player_lists
+        .iter()
+        .filter(|player_list| player_list.auto_update && Some(*player_list) != own_list)
+        .map(|player_list| player_list.filename.clone())
+        .collect();
+
+    let Some(state) = app_win.rule_editor.as_mut() else {
+        return false;
+    };
+
+    Grid::new("rule_editor_grid")
+        .num_columns(2)
+        .spacing([10.0, 8.0])
+        .show(ui, |ui| {
+            ui.label("Description:");
+            ui.add(TextEdit::singleline(&mut state.description).desired_width(400.0));
+            ui.end_row();
+
+            ui.label("Triggers:");
+            ui.horizontal(|ui| {
+                ui.radio_value(&mut state.trigger_mode, TriggerMode::MatchAll, "Match all");
+                ui.radio_value(&mut state.trigger_mode, TriggerMode::MatchAny, "Match any");
+            });
+            ui.end_row();
+
+            ui.checkbox(&mut state.use_name, "Name:");
+            ui.vertical(|ui| {
+                ui.horizontal(|ui| {
+                    ComboBox::from_id_salt("rule_editor_name_mode")
+                        .selected_text(format!("{:?}", state.name_mode))
+                        .show_ui(ui, |ui| {
+                            for mode in TEXT_MATCH_MODES {
+                                let text = format!("{:?}", mode);
+                                ui.selectable_value(&mut state.name_mode, mode, text);
+                            }
+                        });
+                    ui.checkbox(&mut state.name_case_sensitive, "Case sensitive");
+                });
+                ui.add(
+                    TextEdit::multiline(&mut state.name_patterns)
+                        .hint_text("One pattern per line")
+                        .desired_rows(2)
+                        .desired_width(400.0),
+                );
+            });
+            ui.end_row();
+
+            ui.checkbox(&mut state.use_avatar, "Avatar:");
+            ui.add(
+                TextEdit::multiline(&mut state.avatar_hashes)
+                    .hint_text("One avatar hash per line")
+                    .desired_rows(2)
+                    .desired_width(400.0),
+            );
+            ui.end_row();
+
+            ui.label("Mark:");
+            add_attribute_checkboxes(ui, &mut state.mark);
+            ui.end_row();
+
+            ui.label("Unmark:");
+            add_attribute_checkboxes(ui, &mut state.unmark);
+            ui.end_row();
+
+            ui.label("Add to list:");
+            let selected_text = if state.filename.is_empty() {
+                "Select a list"
+            } else {
+                state.filename.as_str()
+            };
+            ComboBox::from_id_salt("rule_editor_filename")
+                .selected_text(selected_text)
+                .show_ui(ui, |ui| {
+                    for filename in &filenames {
+                        ui.selectable_value(&mut state.filename, filename.clone(), filename);
+                    }
+                });
+            ui.end_row();
+        });
+
+    let rule = state.build_rule();
+
+    ui.add_space(8.0);
+
+    if let Err(e) = &rule {
+        ui.colored_label(ui.visuals().error_fg_color, e);
+    }
+    if auto_update_lists.contains(&state.filename) {
+        ui.colored_label(
+            ui.visuals().warn_fg_color,
+            "Auto update is on for this list. If it has an update_url the rule is not added, because the next update would remove it.",
+        );
+    }
+
+    let mut add_rule = false;
+    let mut preview = false;
+    ui.horizontal(|ui| {
+        if ui
+            .add_enabled(rule.is_ok(), egui::Button::new("Preview"))
+            .on_hover_text(
+                "Show the players in the lobby and in the database that the rule matches",
+            )
+            .clicked()
+        {
+            preview = true;
+        }
+
+        if ui
+            .add_enabled(rule.is_ok(), egui::Button::new("Add rule"))
+            .clicked()
+        {
+            add_rule = true;
+        }
+    });
+
+    if preview {
+        let preview = get_preview(app_win);
+        if let Some(state) = app_win.rule_editor.as_mut() {
+            state.preview = Some(preview);
+        }
+    }
+
+    if let Some(preview) = app_win
+        .rule_editor
+        .as_ref()
+        .and_then(|state| state.preview.as_ref())
+    {
+        ui.separator();
+        add_preview(ui, preview);
+    }
+
+    add_rule
+}
+
+fn add_attribute_checkboxes(ui: &mut Ui, attributes: &mut Vec<PlayerAttribute>) {
+    ui.horizontal_wrapped(|ui| {
+        for player_attribute in RULE_EDITOR_ATTRIBUTES {
+            let mut enabled = attributes.contains(&player_attribute);
+            if ui
+                .checkbox(&mut enabled, format!("{:?}", player_attribute))
+                .changed()
+            {
+                attributes.retain(|x| *x != player_attribute);
+                if enabled {
+                    attributes.push(player_attribute);
+                }
+            }
+        }
+    });
+}
+
+fn add_preview(ui: &mut Ui, preview: &RulePreview) {
+    if let Some(error) = &preview.error {
+        ui.colored_label(ui.visuals().error_fg_color, error);
+    }
+
+    ScrollArea::vertical()
+        .auto_shrink([false, true])
+        .max_height(300.0)
+        .show(ui, |ui| {
+            ui.strong(format!(
+                "Matches {} players in the lobby",
+                preview.lobby.len()
+            ));
+            for (steamid, name) in &preview.lobby {
+                ui.label(format!("{} ({})", name, steamid.to_steam_id32()));
+            }
+
+            ui.add_space(8.0);
+
+            ui.strong(format!(
+                "Matches {} accounts in the database",
+                preview.accounts.len()
+            ));
+            for (steamid, name) in preview.accounts.iter().take(PREVIEW_MAX_ROWS) {
+                ui.label(format!("{} ({})", name, steamid.to_steam_id32()));
+            }
+            if preview.accounts.len() > PREVIEW_MAX_ROWS {
+                ui.label(format!(
+                    "...and {} more",
+                    preview.accounts.len() - PREVIEW_MAX_ROWS
+                ));
+            }
+        });
+}
+
+/// Evaluates the rule against the players in the lobby and the accounts in the database
+fn get_preview(app_win: &AppWin) -> RulePreview {
+    let mut preview = RulePreview {
+        lobby: Vec::new(),
+        accounts: Vec::new(),
+        error: None,
+    };
+
+    let Some(state) = app_win.rule_editor.as_ref() else {
+        return preview;
+    };
+    let Ok(rule) = state.build_rule() else {
+        return preview;
+    };
+    let rule_engine = RuleEngine::new(&[rule]);
+
+    preview.lobby = preview_lobby(&rule_engine, &app_win.lobby);
+
+    let accounts = app_win
+        .db
+        .get()
+        .map_err(|e| e.to_string())
+        .and_then(|mut conn| get_candidate_accounts(&mut conn, state).map_err(|e| e.to_string()));
+
+    match accounts {
+        Ok(accounts) => preview.accounts = preview_accounts(&rule_engine, accounts),
+        Err(e) => preview.error = Some(format!("Failed to load accounts: {}", e)),
+    }
+
+    preview
+}
+
+/// The players in the lobby the rule matches, by name, avatar or chat
+fn preview_lobby(rule_engine: &RuleEngine, lobby: &Lobby) -> Vec<(SteamID, String)> {
+    let mut matches = Vec::new();
+
+    for player in &lobby.players {
+        let avatar_hash = player
+            .steam_info
+            .as_ref()
+            .and_then(|steam_info| steam_info.avatar_hash.clone());
+        let chat_messages: Vec<&str> = lobby
+            .chat
+            .iter()
+            .filter(|chat| chat.steamid == player.steamid && !chat.system)
+            .map(|chat| chat.message.as_str())
+            .collect();
+
+        let input = RuleInput {
+            name: &player.name,
+            avatar_hash: avatar_hash.as_deref(),
+            chat_messages,
+        };
+        if !rule_engine.evaluate(&input).is_empty() {
+            matches.push((player.steamid, player.name.clone()));
+        }
+    }
+
+    matches
+}
+
+/// The accounts from the database the rule matches, by name or avatar
+fn preview_accounts(rule_engine: &RuleEngine, accounts: Vec<Account>) -> Vec<(SteamID, String)> {
+    let mut matches = Vec::new();
+
+    for account in accounts {
+        let avatar_hash = account
+            .avatar_hash
+            .clone()
+            .or_else(|| avatar_hash_from_url(&account.avatar_full_url));
+        let input = RuleInput {
+            name: &account.name,
+            avatar_hash: avatar_hash.as_deref(),
+            chat_messages: Vec::new(),
+        };
+        if !rule_engine.evaluate(&input).is_empty() {
+            matches.push((SteamID::from_u64(account.steam_id as u64), account.name));
+        }
+    }
+
+    matches
+}
+
+/// The accounts the rule could match. When every match needs one of the avatars,
+/// the accounts are found with the avatar_hash index instead of reading all of them.
+fn get_candidate_accounts(
+    conn: &mut SqliteConnection,
+    state: &RuleEditorState,
+) -> Result<Vec<Account>, diesel::result::Error> {
+    let avatar_required =
+        state.use_avatar && (!state.use_name || state.trigger_mode == TriggerMode::MatchAll);
+    if !avatar_required {
+        return get_all_accounts(conn);
+    }
+
+    let mut accounts: Vec<Account> = Vec::new();
+    for avatar_hash in lines(&state.avatar_hashes) {
+        for account in get_accounts_by_avatar_hash(conn, &avatar_hash)? {
+            if !accounts.iter().any(|x| x.steam_id == account.steam_id) {
+                accounts.push(account);
+            }
+        }
+    }
+
+    Ok(accounts)
+}
+
+fn send_rule(app_win: &mut AppWin) {
+    let Some(state) = app_win.rule_editor.take() else {
+        return;
+    };
+    let Ok(rule) = state.build_rule() else {
+        app_win.rule_editor = Some(state);
+        return;
+    };
+
+    log::info!("Adding rule '{}' to {}", rule.description, state.filename);
+    app_win
+        .bus
+        .lock()
+        .unwrap()
+        .app_event_bus
+        .broadcast(AppEventMsg::AddRule {
+            filename: state.filename,
+            rule,
+        });
+}
+
+fn lines(text: &str) -> Vec<String> {
+    text.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tf2::lobby::{LobbyChat, PlayerSteamInfo};
+    use chrono::Local;
+
+    fn player(steamid: u64, name: &str, avatar_hash: Option<&str>) -> Player {
+        Player {
+            steamid: SteamID::from_u64(steamid),
+            name: name.to_string(),
+            steam_info: avatar_hash.map(|avatar_hash| PlayerSteamInfo {
+                steamid: SteamID::from_u64(steamid),
+                public_profile: true,
+                avatar_thumb: String::new(),
+                avatar_full: String::new(),
+                avatar_hash: Some(avatar_hash.to_string()),
+                account_age: None,
+            }),
+            ..Default::default()
+        }
+    }
+
+    fn account(steam_id: i64, name: &str, avatar_hash: Option<&str>) -> Account {
+        Account {
+            steam_id,
+            name: name.to_string(),
+            created_date: None,
+            avatar_thumb_url: String::new(),
+            avatar_full_url: String::new(),
+            public_profile: true,
+            last_updated: 0,
+            friends_fetched: None,
+            comments_fetched: None,
+            playtimes_fetched: None,
+            reputation_fetched: None,
+            steam_bans_last_fetched: None,
+            avatar_hash: avatar_hash.map(str::to_string),
+        }
+    }
+
+    fn name_rule_state(patterns: &str) -> RuleEditorState {
+        let mut state = RuleEditorState::from_name(&player(1, patterns, None));
+        state.filename = "playerlist.json".to_string();
+        state
+    }
+
+    #[test]
+    fn test_build_rule() {
+        let mut state = name_rule_state("bot one\n\n  bot two  ");
+        let rule = state.build_rule().unwrap();
+        assert_eq!(rule.description, "Name bot one\n\n  bot two");
+        let text_match = rule.triggers.username_text_match.unwrap();
+        assert_eq!(text_match.patterns, vec!["bot one", "bot two"]);
+        assert_eq!(text_match.mode, TextMatchMode::Equal);
+        assert_eq!(rule.actions.mark, vec![PlayerAttribute::Bot]);
+        assert!(rule.triggers.avatar_match.is_none());
+
+        // Every part of the rule is required
+        state.filename.clear();
+        assert!(state.build_rule().unwrap_err().contains("list"));
+        state.filename = "playerlist.json".to_string();
+
+        state.mark.clear();
+        assert!(state.build_rule().is_err());
+        state.unmark.push(PlayerAttribute::Cheater);
+        assert!(state.build_rule().is_ok());
+
+        state.use_name = false;
+        assert!(state.build_rule().is_err());
+        state.use_avatar = true;
+        assert!(state.build_rule().is_err());
+        state.avatar_hashes = "abc\ndef".to_string();
+        let rule = state.build_rule().unwrap();
+        assert_eq!(rule.triggers.avatar_match.unwrap().len(), 2);
+
+        state.description = " ".to_string();
+        assert!(state.build_rule().is_err());
+    }
+
+    #[test]
+    fn test_build_rule_regex() {
+        let mut state = name_rule_state("^bot[0-9]+$");
+        state.name_mode = TextMatchMode::Regex;
+        assert!(state.build_rule().is_ok());
+
+        state.name_patterns = "bot(".to_string();
+        assert!(state.build_rule().unwrap_err().contains("Invalid regex"));
+        // The cached result is used until the patterns change
+        assert!(state.build_rule().unwrap_err().contains("Invalid regex"));
+
+        state.name_patterns = "bot[0-9]+".to_string();
+        assert!(state.build_rule().is_ok());
+    }
+
+    #[test]
+    fn test_preview() {
+        let mut state = name_rule_state("bot");
+        state.name_mode = TextMatchMode::Contains;
+        state.name_case_sensitive = false;
+        state.use_avatar = true;
+        state.avatar_hashes = "abc".to_string();
+        state.trigger_mode = TriggerMode::MatchAny;
+        let rule_engine = RuleEngine::new(&[state.build_rule().unwrap()]);
+
+        let mut lobby = Lobby::new(SteamID::from_u64(100));
+        lobby.players = vec![
+            player(1, "BOT Alice", None),
+            player(2, "Bob", Some("abc")),
+            player(3, "Carol", Some("def")),
+        ];
+        // A chat message with the pattern doesn't match a name rule
+        lobby.chat.push(LobbyChat {
+            chat_msg_id: 0,
+            when: Local::now(),
+            steamid: SteamID::from_u64(3),
+            player_name: "Carol".to_string(),
+            message: "bot".to_string(),
+            translated_message: None,
+            dead: false,
+            team: false,
+            system: false,
+        });
+
+        let names: Vec<String> = preview_lobby(&rule_engine, &lobby)
+            .into_iter()
+            .map(|(_, name)| name)
+            .collect();
+        assert_eq!(names, vec!["BOT Alice", "Bob"]);
+
+        let accounts = vec![
+            account(4, "robot", None),
+            account(5, "Dave", Some("abc")),
+            account(6, "Eve", None),
+        ];
+        let steamids: Vec<u64> = preview_accounts(&rule_engine, accounts)
+            .into_iter()
+            .map(|(steamid, _)| steamid.to_u64())
+            .collect();
+        assert_eq!(steamids, vec![4, 5]);
+    }
+}
@@ -47,6 +47,9 @@ pub fn scoreboard_team(app_win: &mut AppWin, ui: &mut Ui, title: &str, players:
     if app_win.app_settings.show_health {
         num_columns += 1;
     }
+    if app_win.app_settings.show_score {
+        num_columns += 1;
+    }
     if app_win.app_settings.show_weapons {
         num_columns += 1;
     }
@@ -86,6 +89,11 @@ pub fn scoreboard_team(app_win: &mut AppWin, ui: &mut Ui, title: &str, players:
                     ui.label("Health").on_hover_text("Current health");
                 });
             }
+            if app_win.app_settings.show_score {
+                ui.with_layout(Layout::top_down(Align::RIGHT), |ui| {
+                    ui.label("Score").on_hover_text("Score in this round");
+                });
+            }
             ui.with_layout(Layout::top_down(Align::LEFT), |ui| {
                 ui.label("Rep.")
                     .on_hover_text("Reputation (SourceBans) and flags");
@@ -193,6 +201,17 @@ pub fn scoreboard_team(app_win: &mut AppWin, ui: &mut Ui, title: &str, players:
                     });
                 }
 
+                if app_win.app_settings.show_score {
+                    ui.with_layout(Layout::top_down(Align::RIGHT), |ui| {
+                        if let Some(score) = player.score {
+                            ui.label(format!("{}", score))
+                                .on_hover_text_at_pointer("Score in this round");
+                        } else {
+                            show_empty_value(ui);
+                        }
+                    });
+                }
+
                 add_reputation(ui, player);
 
                 if app_win.app_settings.show_weapons {
@@ -287,11 +306,39 @@ fn add_player_name(app_win: &mut AppWin, ui: &mut Ui, player: &Player) {
                 if response.double_clicked() {
                     app_win.open_player_details_window(player.steamid);
                 }
+
+                add_impersonation_badge(app_win, ui, player);
             });
         });
     });
 }
 
+/// Warns about a player whose name looks the same as the name of a player that joined earlier
+fn add_impersonation_badge(app_win: &mut AppWin, ui: &mut Ui, player: &Player) {
+    let Some(original_steamid) = player.impersonating else {
+        return;
+    };
+
+    let original_name = app_win
+        .lobby
+        .get_player(None, Some(original_steamid))
+        .map(|original| original.name.clone())
+        .unwrap_or_default();
+
+    let response = ui
+        .colored_label(Color32::ORANGE, "👥")
+        .on_hover_cursor(CursorIcon::PointingHand)
+        .on_hover_text(format!(
+            "Possible impersonator, the name looks like a copy of {} ({}) who joined earlier",
+            original_name,
+            original_steamid.to_u64()
+        ));
+
+    if response.clicked() {
+        app_win.set_selected_player(original_steamid);
+    }
+}
+
 fn add_team_symbol(app_win: &mut AppWin, ui: &mut Ui, self_steamid: SteamID, player: &Player) {
     let color = match player.team {
         Team::Blue => TEAM_BLU_COLOR,
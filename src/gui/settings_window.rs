@@ -1,6 +1,8 @@
 use crate::models::{
     AppWin, TempSettings,
-    app_settings::{DEFAULT_EXE_FILENAME, DEFAULT_LAUNCH_OPTIONS, DEFAULT_LOG_FILENAME},
+    app_settings::{
+        DEFAULT_EXE_FILENAME, DEFAULT_LAUNCH_OPTIONS, DEFAULT_LOG_FILENAME, PlayerListSettings,
+    },
 };
 use eframe::egui::{self, Grid, Ui};
 
@@ -21,6 +23,7 @@ pub fn show_settings_window(app_win: &mut AppWin, ctx: &egui::Context) {
             log_filename: app_win.app_settings.log_filename.clone(),
             exe_filename: app_win.app_settings.exe_filename.clone(),
             launch_options: app_win.app_settings.launch_options.clone(),
+            player_lists: app_win.app_settings.player_lists.clone(),
         });
     }
 
@@ -223,9 +226,63 @@ fn show_settings_content(ui: &mut Ui, temp: &mut TempSettings) {
             }
         });
         ui.end_row();
+
+        // player_lists
+        ui.with_layout(egui::Layout::top_down(egui::Align::LEFT), |ui| {
+            ui.label("TF2BD Player Lists:");
+        });
+        ui.vertical(|ui| {
+            add_player_lists_editor(ui, &mut temp.player_lists);
+
+            ui.add_space(10.0);
+            ui.colored_label(info_label_color, "Flags you set are saved to the first trusted list. Suggestion lists are only shown, they never trigger votekicks or party notifications.");
+        });
+        ui.end_row();
     });
 }
 
+fn add_player_lists_editor(ui: &mut Ui, player_lists: &mut Vec<PlayerListSettings>) {
+    let mut list_to_remove = None;
+
+    for (i, player_list) in player_lists.iter_mut().enumerate() {
+        ui.horizontal(|ui| {
+            ui.add(egui::TextEdit::singleline(&mut player_list.filename).desired_width(260.0));
+            ui.checkbox(&mut player_list.suggestion, "Suggestion only");
+            ui.checkbox(&mut player_list.auto_update, "Auto update")
+                .on_hover_text("Download the list from the update_url in the file, or from the URL set here");
+            let mut update_url = player_list.update_url.clone().unwrap_or_default();
+            if ui
+                .add(
+                    egui::TextEdit::singleline(&mut update_url)
+                        .hint_text("update_url")
+                        .desired_width(200.0),
+                )
+                .changed()
+            {
+                player_list.update_url =
+                    Some(update_url.trim().to_string()).filter(|url| !url.is_empty());
+            }
+            if ui.button("Remove").clicked() {
+                list_to_remove = Some(i);
+            }
+        });
+    }
+
+    if let Some(i) = list_to_remove {
+        player_lists.remove(i);
+    }
+
+    if ui.button("Add list").clicked() {
+        player_lists.push(PlayerListSettings {
+            filename: String::new(),
+            suggestion: true,
+            auto_update: true,
+            format: None,
+            update_url: None,
+        });
+    }
+}
+
 fn save_settings(app_win: &mut AppWin, temp: &TempSettings) {
     use crate::models::steamid::SteamID;
 
@@ -256,6 +313,12 @@ fn save_settings(app_win: &mut AppWin, temp: &TempSettings) {
     app_win.app_settings.log_filename = temp.log_filename.clone();
     app_win.app_settings.exe_filename = temp.exe_filename.clone();
     app_win.app_settings.launch_options = temp.launch_options.clone();
+    app_win.app_settings.player_lists = temp
+        .player_lists
+        .iter()
+        .filter(|player_list| !player_list.filename.trim().is_empty())
+        .cloned()
+        .collect();
 
     // Also update self_steamid in AppWin
     app_win.self_steamid = steamid;
@@ -0,0 +1,90 @@
+use super::colors::{CHAT_BLU_COLOR, CHAT_RED_COLOR, hex_to_rgb};
+use crate::{
+    models::AppWin,
+    tf2::lobby::{LobbyEvent, LobbyEventKind, Team},
+};
+use eframe::egui::{Color32, ScrollArea, TextFormat, TextStyle, Ui, text::LayoutJob};
+
+pub fn add_timeline(ui: &mut Ui, app_win: &mut AppWin) {
+    ui.heading("Timeline");
+
+    let text_style = TextStyle::Body;
+    let text_height = ui.text_style_height(&text_style);
+    let button_height = ui.spacing().interact_size.y;
+    let row_height = text_height.max(button_height);
+    let num_rows = app_win.lobby.events.len();
+
+    ScrollArea::vertical()
+        .stick_to_bottom(true)
+        .auto_shrink(false)
+        .show_rows(ui, row_height, num_rows, |ui, row_range| {
+            ui.scope(|ui| {
+                ui.style_mut().visuals.panel_fill = hex_to_rgb(0xffffff);
+
+                for row in row_range {
+                    let row = &app_win.lobby.events[row].clone();
+                    add_event_row(ui, app_win, row);
+                }
+            });
+        });
+}
+
+fn add_event_row(ui: &mut Ui, app_win: &mut AppWin, event: &LobbyEvent) {
+    ui.horizontal_wrapped(|ui| {
+        let (icon, color) = match event.kind {
+            LobbyEventKind::Connected => ("➕ ", Color32::GRAY),
+            LobbyEventKind::Disconnected => ("➖ ", Color32::GRAY),
+            LobbyEventKind::TeamSwitch => ("⇄ ", Color32::LIGHT_GRAY),
+            LobbyEventKind::Vote => ("🗳 ", Color32::LIGHT_GRAY),
+            LobbyEventKind::VoteAgainstMe => ("🗳 ", Color32::ORANGE),
+            LobbyEventKind::Capture => ("⚑ ", Color32::LIGHT_GRAY),
+            LobbyEventKind::Kicked => ("⚠ ", CHAT_RED_COLOR),
+        };
+
+        // Team color for events about a player
+        let player_color = event
+            .steamid
+            .and_then(|steamid| app_win.lobby.get_player(None, Some(steamid)))
+            .map(|player| match player.team {
+                Team::Blue => CHAT_BLU_COLOR,
+                Team::Red => CHAT_RED_COLOR,
+                _ => color,
+            });
+
+        let mut job = LayoutJob::default();
+        job.append(
+            &format!("{} ", event.when.format("%H:%M:%S")),
+            0.0,
+            TextFormat {
+                color: Color32::DARK_GRAY,
+                ..Default::default()
+            },
+        );
+        job.append(
+            icon,
+            0.0,
+            TextFormat {
+                color: Color32::WHITE,
+                ..Default::default()
+            },
+        );
+        job.append(
+            &event.text,
+            0.0,
+            TextFormat {
+                color: match event.kind {
+                    LobbyEventKind::VoteAgainstMe | LobbyEventKind::Kicked => color,
+                    _ => player_color.unwrap_or(color),
+                },
+                ..Default::default()
+            },
+        );
+
+        let response = ui.label(job);
+        if let Some(steamid) = event.steamid
+            && response.clicked()
+        {
+            app_win.set_selected_player(steamid);
+        }
+    });
+}
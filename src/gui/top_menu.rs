@@ -70,6 +70,13 @@ pub fn add_top_menu(ui: &mut Ui, app_win: &mut AppWin) {
                 app_win.updated_settings();
             }
 
+            if ui
+                .checkbox(&mut app_win.app_settings.show_score, "Show score")
+                .changed()
+            {
+                app_win.updated_settings();
+            }
+
             if ui
                 .checkbox(&mut app_win.app_settings.show_ping, "Show ping")
                 .changed()
@@ -83,6 +90,13 @@ pub fn add_top_menu(ui: &mut Ui, app_win: &mut AppWin) {
             {
                 app_win.updated_settings();
             }
+
+            if ui
+                .checkbox(&mut app_win.app_settings.show_timeline, "Show timeline")
+                .changed()
+            {
+                app_win.updated_settings();
+            }
         });
 
         ui.menu_button("Actions", |ui| {
@@ -102,6 +116,65 @@ pub fn add_top_menu(ui: &mut Ui, app_win: &mut AppWin) {
 
             ui.separator();
 
+            if ui
+                .checkbox(&mut app_win.app_settings.auto_vote, "Auto vote on kick votes")
+                .changed()
+            {
+                app_win.updated_settings();
+            }
+
+            ui.add_enabled_ui(app_win.app_settings.auto_vote, |ui| {
+                if ui
+                    .checkbox(
+                        &mut app_win.app_settings.auto_vote_no_on_friends,
+                        " No on kicking friends",
+                    )
+                    .changed()
+                {
+                    app_win.updated_settings();
+                }
+
+                if ui
+                    .checkbox(
+                        &mut app_win.app_settings.auto_vote_no_on_self,
+                        " No on kicking me",
+                    )
+                    .changed()
+                {
+                    app_win.updated_settings();
+                }
+
+                for player_attribute in [PlayerAttribute::Cheater, PlayerAttribute::Bot] {
+                    let mut enabled = app_win
+                        .app_settings
+                        .auto_vote_yes_on
+                        .contains(&player_attribute);
+                    if ui
+                        .checkbox(
+                            &mut enabled,
+                            format!(" Yes on kicking {:?}s", player_attribute),
+                        )
+                        .changed()
+                    {
+                        app_win
+                            .app_settings
+                            .auto_vote_yes_on
+                            .retain(|&x| x != player_attribute);
+
+                        if enabled {
+                            app_win
+                                .app_settings
+                                .auto_vote_yes_on
+                                .push(player_attribute);
+                        }
+
+                        app_win.updated_settings();
+                    }
+                }
+            });
+
+            ui.separator();
+
             ui.label("Notify party about joining:");
 
             let player_attributes_to_show = vec![
@@ -180,6 +253,16 @@ pub fn add_top_menu(ui: &mut Ui, app_win: &mut AppWin) {
 
             ui.separator();
 
+            if ui
+                .checkbox(&mut app_win.app_settings.record_sessions, "Record sessions")
+                .on_hover_text_at_pointer("Saves the console log and player list to the sessions folder from the next start.\nReplay a session with: tf2monitor --replay <file>")
+                .changed()
+            {
+                app_win.updated_settings();
+            }
+
+            ui.separator();
+
             if ui.button("DB Statistics").clicked() {
                 app_win.db_statistics_window_open = true;
                 ui.close();
@@ -189,6 +272,16 @@ pub fn add_top_menu(ui: &mut Ui, app_win: &mut AppWin) {
                 app_win.player_database_window_open = true;
                 ui.close();
             }
+
+            if ui.button("Player Lists").clicked() {
+                app_win.playerlist_editor_window_open = true;
+                ui.close();
+            }
+
+            if ui.button("Export / Import Flags").clicked() {
+                app_win.flag_exchange_window_open = true;
+                ui.close();
+            }
         });
     });
 }
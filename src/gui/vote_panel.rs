@@ -0,0 +1,69 @@
+use crate::{
+    models::AppWin,
+    tf2::lobby::votes::{LobbyVote, VoteResult},
+};
+use chrono::Local;
+use eframe::egui::{Color32, Ui};
+
+/// How long the result of the last vote is shown after it ended
+const VOTE_RESULT_SECONDS: i64 = 30;
+
+/// Shows the running vote with F1/F2 buttons, or the result of the last vote for a while
+pub fn add_vote_panel(app_win: &mut AppWin, ui: &mut Ui) {
+    let Some(vote) = app_win.lobby.votes.last().cloned() else {
+        return;
+    };
+
+    if !vote.is_active()
+        && vote
+            .ended
+            .is_none_or(|ended| (Local::now() - ended).num_seconds() > VOTE_RESULT_SECONDS)
+    {
+        return;
+    }
+
+    ui.separator();
+
+    ui.horizontal(|ui| {
+        let against_me = vote.target == Some(app_win.self_steamid);
+        let color = if against_me {
+            Color32::ORANGE
+        } else {
+            Color32::WHITE
+        };
+        ui.colored_label(
+            color,
+            format!("🗳 {} called a vote: {}", vote.caller_name, vote.issue),
+        );
+
+        ui.label(format!("Yes: {}  No: {}", vote.yes, vote.no));
+
+        match &vote.result {
+            None => add_vote_buttons(app_win, ui, &vote),
+            Some(VoteResult::Passed) => {
+                ui.colored_label(Color32::GREEN, "Passed");
+            }
+            Some(VoteResult::Failed(reason)) => {
+                ui.colored_label(Color32::GRAY, format!("Failed: {}", reason));
+            }
+        }
+    });
+}
+
+fn add_vote_buttons(app_win: &mut AppWin, ui: &mut Ui, vote: &LobbyVote) {
+    if let Some(yes) = vote.my_vote {
+        ui.label(format!("You voted {}", if yes { "yes" } else { "no" }));
+        return;
+    }
+
+    if vote.caller == Some(app_win.self_steamid) {
+        return;
+    }
+
+    if ui.button("F1 Yes").clicked() {
+        app_win.bus.lock().unwrap().send_rcon_cmd("vote option1");
+    }
+    if ui.button("F2 No").clicked() {
+        app_win.bus.lock().unwrap().send_rcon_cmd("vote option2");
+    }
+}
@@ -1,6 +1,14 @@
-use super::background_image::ImageDescription;
-use crate::models::AppWin;
-use eframe::egui::{Align, Layout, Ui};
+use super::{background_image::ImageDescription, colors::CHAT_RED_COLOR};
+use crate::{
+    models::AppWin,
+    tf2::{
+        lobby::{LobbyServer, name_resolver::NameResolutionStats},
+        rcon::RconState,
+    },
+    tf2bd::kick_scheduler::{KickSchedule, seconds_until},
+};
+use chrono::Local;
+use eframe::egui::{Align, Color32, Layout, Ui};
 
 pub fn add_status_row(app_win: &AppWin, ui: &mut Ui, _image_desc: &ImageDescription) {
     ui.horizontal(|ui| {
@@ -11,11 +19,143 @@ pub fn add_status_row(app_win: &AppWin, ui: &mut Ui, _image_desc: &ImageDescript
             lobby.chat.len()
         ));
 
+        add_rcon_state(&lobby.rcon_state, ui);
+
+        add_server_info(&lobby.server, ui);
+
         // ui.label("Status: Online");
 
+        if !lobby.player_list_errors.is_empty() {
+            ui.colored_label(
+                CHAT_RED_COLOR,
+                format!(
+                    "{} player list(s) failed to load",
+                    lobby.player_list_errors.len()
+                ),
+            )
+            .on_hover_text(lobby.player_list_errors.join("\n"));
+        }
+
+        add_name_resolution(&lobby.name_resolution, ui);
+
+        add_kick_schedule(&lobby.kick_schedule, ui);
+
         ui.with_layout(Layout::right_to_left(Align::Center), |ui| {
             ui.label("Zoom with ctrl +/-");
             // add_image_creds(ui, image_desc);
         });
     });
 }
+
+/// Whether we can talk to TF2, the player list and kicking need RCON
+fn add_rcon_state(rcon_state: &RconState, ui: &mut Ui) {
+    let text = format!("RCON: {}", rcon_state.description());
+    match rcon_state {
+        RconState::Connected => {
+            ui.label(text);
+        }
+        RconState::Connecting => {
+            ui.colored_label(Color32::GRAY, text);
+        }
+        _ => {
+            ui.colored_label(CHAT_RED_COLOR, text).on_hover_text(
+                "Start TF2 with -usercon and check the RCON password and port in the settings",
+            );
+        }
+    }
+}
+
+/// The players the kicker will call votes on, and when the next vote is called
+fn add_kick_schedule(schedule: &KickSchedule, ui: &mut Ui) {
+    if schedule.queue.is_empty() {
+        return;
+    }
+
+    let now = Local::now();
+    let next = match schedule.next_attempt {
+        Some(next_attempt) if next_attempt > now => {
+            format!("next vote in {}s", seconds_until(next_attempt, now))
+        }
+        Some(_) => "voting now".to_string(),
+        None => "waiting".to_string(),
+    };
+
+    let mut details: Vec<String> = schedule
+        .queue
+        .iter()
+        .enumerate()
+        .map(|(i, target)| {
+            let mut line = format!("{}. {} ({:?})", i + 1, target.name, target.attribute);
+            if let Some(not_before) = target.not_before {
+                line += &format!(
+                    ", vote failed, retry in {}s",
+                    seconds_until(not_before, now)
+                );
+            }
+            line
+        })
+        .collect();
+    if let Some(waiting_for) = &schedule.waiting_for {
+        details.push(String::new());
+        details.push(waiting_for.clone());
+    }
+
+    ui.separator();
+    ui.label(format!("Kick queue: {}, {}", schedule.queue.len(), next))
+        .on_hover_text(details.join("\n"));
+}
+
+/// Kill and chat lines whose names did not match the players in the lobby
+fn add_name_resolution(stats: &NameResolutionStats, ui: &mut Ui) {
+    if stats.unresolved == 0 {
+        return;
+    }
+
+    let mut details = vec![
+        format!("Resolved lines: {}", stats.resolved),
+        format!("Ambiguous names: {}", stats.ambiguous),
+        format!("Unresolved lines: {}", stats.unresolved),
+        String::new(),
+    ];
+    details.extend(stats.unresolved_lines.iter().cloned());
+
+    ui.separator();
+    ui.colored_label(
+        CHAT_RED_COLOR,
+        format!("{} unresolved name(s)", stats.unresolved),
+    )
+    .on_hover_text(details.join("\n"));
+}
+
+/// Map and server of the lobby, known once the status command has been run in the console
+fn add_server_info(server: &LobbyServer, ui: &mut Ui) {
+    let mut text = match (&server.map, server.datacenter().or(server.name())) {
+        (Some(map), Some(name)) => format!("{} on {}", map, name),
+        (Some(map), None) => map.clone(),
+        (None, Some(name)) => name.to_string(),
+        (None, None) => return,
+    };
+    if let (Some(humans), Some(max_players)) = (server.humans, server.max_players) {
+        text += &format!(", {}/{} players", humans, max_players);
+    }
+
+    let mut details = Vec::new();
+    if let Some(hostname) = &server.hostname {
+        details.push(format!("Hostname: {}", hostname));
+    }
+    if let Some(address) = &server.address {
+        details.push(format!("Address: {}", address));
+    }
+    if let Some(bots) = server.bots {
+        details.push(format!("Bots: {}", bots));
+    }
+    if !server.tags.is_empty() {
+        details.push(format!("Tags: {}", server.tags.join(", ")));
+    }
+    if let Some(last_updated) = server.last_updated {
+        details.push(format!("Updated: {}", last_updated.format("%H:%M:%S")));
+    }
+
+    ui.separator();
+    ui.label(text).on_hover_text(details.join("\n"));
+}
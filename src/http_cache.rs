@@ -1,5 +1,6 @@
-use crate::config::HTTP_CACHE_BASE_DIR;
+use crate::config::{HTTP_CACHE_BASE_DIR, HTTP_REQUEST_TIMEOUT};
 use fs_err::create_dir_all;
+use serde::{Deserialize, Serialize};
 use std::{
     fs::{metadata, write},
     path::PathBuf,
@@ -118,8 +119,15 @@ fn get_modified_time(path: &PathBuf) -> Option<SystemTime> {
     }
 }
 
+fn http_client() -> reqwest::Result<reqwest::blocking::Client> {
+    reqwest::blocking::Client::builder()
+        .timeout(HTTP_REQUEST_TIMEOUT)
+        .build()
+}
+
 fn get_html(url: &str) -> Option<String> {
-    match reqwest::blocking::get(url) {
+    let response = http_client().and_then(|client| client.get(url).send());
+    match response {
         Ok(resp) => match resp.text() {
             Ok(text) => Some(text),
             Err(err) => {
@@ -133,3 +141,117 @@ fn get_html(url: &str) -> Option<String> {
         }
     }
 }
+
+/// The result of a conditional GET, see fetch_if_modified()
+#[derive(Debug, Clone, PartialEq)]
+pub enum ConditionalResponse {
+    /// The server replied 304, our copy is still current
+    NotModified,
+
+    Modified {
+        body: String,
+        etag: Option<String>,
+        last_modified: Option<String>,
+    },
+}
+
+/// ETag and Last-Modified from the last response for a URL
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Validators {
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
+}
+
+fn get_validators_path(domain: &str, url: &str) -> PathBuf {
+    let mut path: PathBuf = [HTTP_CACHE_BASE_DIR, domain].iter().collect();
+    path.push(format!("{}.validators.json", get_filename_from_url(url)));
+    path
+}
+
+/// The validators stored in the cache for the URL, empty when there are none
+pub fn load_validators(domain: &str, url: &str) -> Validators {
+    let path = get_validators_path(domain, url);
+    std::fs::read_to_string(&path)
+        .ok()
+        .and_then(|json| serde_json::from_str(&json).ok())
+        .unwrap_or_default()
+}
+
+/// Stores the validators in the cache, so they are sent again after a restart
+pub fn save_validators(domain: &str, url: &str, validators: &Validators) {
+    let path = get_validators_path(domain, url);
+    if let Some(dir) = path.parent() {
+        ensure_directories_exist(&dir.to_path_buf());
+    }
+
+    let result = serde_json::to_string_pretty(validators)
+        .map_err(|e| e.to_string())
+        .and_then(|json| write(&path, json).map_err(|e| e.to_string()));
+    if let Err(e) = result {
+        log::error!(
+            "http_cache: Failed to write to file: {}, full path = {}",
+            e,
+            path.display()
+        );
+    }
+}
+
+/// Fetches the URL unless it has not changed since the ETag or Last-Modified
+/// values from an earlier response.
+/// The body is not cached, the caller keeps the data, and stores the validators with save_validators().
+pub fn fetch_if_modified(url: &str, validators: &Validators) -> Option<ConditionalResponse> {
+    let client = match http_client() {
+        Ok(client) => client,
+        Err(err) => {
+            log::error!("http_cache: Failed to create HTTP client: {err}");
+            return None;
+        }
+    };
+
+    let mut request = client.get(url);
+    if let Some(etag) = &validators.etag {
+        request = request.header(reqwest::header::IF_NONE_MATCH, etag);
+    }
+    if let Some(last_modified) = &validators.last_modified {
+        request = request.header(reqwest::header::IF_MODIFIED_SINCE, last_modified);
+    }
+
+    let resp = match request.send() {
+        Ok(resp) => resp,
+        Err(err) => {
+            log::error!("http_cache: Failed to fetch URL {}: {err}", url);
+            return None;
+        }
+    };
+
+    if resp.status() == reqwest::StatusCode::NOT_MODIFIED {
+        log::info!("http_cache: Not modified: {}", url);
+        return Some(ConditionalResponse::NotModified);
+    }
+
+    if !resp.status().is_success() {
+        log::error!("http_cache: URL {} replied {}", url, resp.status());
+        return None;
+    }
+
+    let header_value = |name: reqwest::header::HeaderName| {
+        resp.headers()
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_string)
+    };
+    let etag = header_value(reqwest::header::ETAG);
+    let last_modified = header_value(reqwest::header::LAST_MODIFIED);
+
+    match resp.text() {
+        Ok(body) => Some(ConditionalResponse::Modified {
+            body,
+            etag,
+            last_modified,
+        }),
+        Err(err) => {
+            log::error!("http_cache: Failed to get text from response: {err}");
+            None
+        }
+    }
+}
@@ -1,4 +1,5 @@
 mod appbus;
+mod cli;
 mod config;
 mod db;
 mod gui;
@@ -20,17 +21,50 @@ fn main() -> Result<(), eframe::Error> {
 
     log::info!("TF2Monitor is starting...");
 
+    let replay = cli::replay_options().unwrap_or_else(|e| {
+        eprintln!("Could not replay the session: {}", e);
+        std::process::exit(1);
+    });
+
     // Connect to database
+    // The players of a replayed match must not be added to the player history again
     log::info!("Connecting to database...");
-    let db = crate::db::db::connect().expect("Failed to connect to database");
+    let db = match replay {
+        Some(_) => crate::db::db::connect_memory(),
+        None => crate::db::db::connect(),
+    }
+    .expect("Failed to connect to database");
     log::info!("Database connection established");
 
     let settings = AppSettings::load_or_default();
+
+    if let Some(exit_code) = cli::run(&settings, &db) {
+        std::process::exit(exit_code);
+    }
+
     let bus = Arc::new(Mutex::new(AppBus::new(settings.self_steamid64)));
 
-    tf2::start(&settings, &bus, &db);
+    let lobby_thread = match replay {
+        Some(replay) => tf2::start_replay(&settings, &bus, &db, replay.archive, replay.speed),
+        None => tf2::start(&settings, &bus, &db),
+    };
     tf2bd::tf2bd_thread::start(&settings, &bus, &db);
+    // A replay must not overwrite the subscribed lists
+    if !bus.lock().unwrap().replaying {
+        tf2bd::list_updater::start(&settings, &bus);
+    }
     reputation_thread::start(&settings, &bus, &db);
 
-    gui::run(&settings, &bus, Arc::new(db))
+    let result = gui::run(&settings, &bus, Arc::new(db));
+
+    // Let the lobby thread save what it has recorded since the last save
+    bus.lock()
+        .unwrap()
+        .app_event_bus
+        .broadcast(appbus::AppEventMsg::Exit);
+    if lobby_thread.join().is_err() {
+        log::error!("The lobby thread panicked");
+    }
+
+    result
 }
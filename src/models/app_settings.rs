@@ -1,5 +1,8 @@
 use super::steamid::SteamID;
-use crate::tf2bd::models::PlayerAttribute;
+use crate::tf2bd::{
+    formats::{AttributeMapping, ListFormat, default_attribute_mapping},
+    models::PlayerAttribute,
+};
 use crate::utils::BoxResult;
 use serde::{Deserialize, Serialize};
 use std::fs::File;
@@ -21,6 +24,20 @@ fn default_party_notifications_for() -> Vec<PlayerAttribute> {
     vec![PlayerAttribute::Cheater, PlayerAttribute::Bot]
 }
 
+fn default_auto_vote_yes_on() -> Vec<PlayerAttribute> {
+    vec![PlayerAttribute::Cheater, PlayerAttribute::Bot]
+}
+
+fn default_player_lists() -> Vec<PlayerListSettings> {
+    vec![PlayerListSettings {
+        filename: "playerlist.json".to_string(),
+        suggestion: false,
+        auto_update: true,
+        format: None,
+        update_url: None,
+    }]
+}
+
 #[cfg(target_os = "windows")]
 pub const DEFAULT_EXE_FILENAME: &str =
     "C:\\Program Files (x86)\\Steam\\steamapps\\common\\Team Fortress 2\\tf_win64.exe";
@@ -39,6 +56,31 @@ pub const DEFAULT_LOG_FILENAME: &str =
 
 pub const DEFAULT_LAUNCH_OPTIONS: &str = "-usercon -high +developer 1 +contimes 0 +ip 0.0.0.0 +net_start  +sv_rcon_whitelist_address 127.0.0.1 +rcon_password rconpwd +hostport 40434 +net_start +con_timestamp 1 -condebug -conclearlog -console -g15 -novid -nojoy -nosteamcontroller -nohltv -particles 1 -console";
 
+/// A TF2BD playerlist/rules file to load
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct PlayerListSettings {
+    pub filename: String,
+
+    /// Markings from suggestion lists are only shown.
+    /// They never trigger votekicks or party notifications.
+    #[serde(default = "get_false")]
+    pub suggestion: bool,
+
+    /// Download the list from the update_url in its file_info section.
+    /// The list where flags are saved is never updated.
+    #[serde(default = "get_true")]
+    pub auto_update: bool,
+
+    /// The format of the file, detected from its content when not set
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub format: Option<ListFormat>,
+
+    /// Where the list is downloaded from, instead of the update_url in its file_info section.
+    /// Needed for MAC lists, which have no file_info.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub update_url: Option<String>,
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct AppSettings {
     pub log_filename: String,
@@ -70,12 +112,19 @@ pub struct AppSettings {
     #[serde(default = "get_false")]
     pub show_health: bool,
 
+    #[serde(default = "get_true")]
+    pub show_score: bool,
+
     #[serde(default = "get_true")]
     pub show_ping: bool,
 
     #[serde(default = "get_true")]
     pub show_weapons: bool,
 
+    /// Connects, team switches, votes and captures next to the kill feed
+    #[serde(default = "get_true")]
+    pub show_timeline: bool,
+
     // Auto actions
     #[serde(default = "bool::default")]
     pub kick_cheaters: bool,
@@ -86,6 +135,37 @@ pub struct AppSettings {
     #[serde(default = "default_party_notifications_for")]
     pub party_notifications_for: Vec<PlayerAttribute>,
 
+    /// Answer kick votes called by others with F1/F2
+    #[serde(default = "get_false")]
+    pub auto_vote: bool,
+
+    /// Vote no on kicking players on our friendlist
+    #[serde(default = "get_true")]
+    pub auto_vote_no_on_friends: bool,
+
+    /// Vote no on kicking us
+    #[serde(default = "get_true")]
+    pub auto_vote_no_on_self: bool,
+
+    /// Vote yes on kicking players with any of these trusted flags
+    #[serde(default = "default_auto_vote_yes_on")]
+    pub auto_vote_yes_on: Vec<PlayerAttribute>,
+
+    /// TF2BD playerlist/rules files to load.
+    /// Flags set in TF2Monitor are saved to the first trusted list.
+    #[serde(default = "default_player_lists")]
+    pub player_lists: Vec<PlayerListSettings>,
+
+    /// Maps attribute names used by other tools, e.g. "racism" or "trusted",
+    /// to TF2Monitor's attributes when loading their lists
+    #[serde(default = "default_attribute_mapping")]
+    pub attribute_mapping: AttributeMapping,
+
+    /// Save the console.log lines and g15_dumpplayer replies to the sessions folder,
+    /// to replay them with --replay. Used from the next start.
+    #[serde(default = "get_false")]
+    pub record_sessions: bool,
+
     /// Flag indicating if configuration is complete and valid.
     /// Not saved to settings.json - computed at runtime.
     #[serde(skip)]
@@ -113,20 +193,38 @@ impl Default for AppSettings {
             show_friendship_indicators: true,
             show_crits: true,
             show_health: false,
+            show_score: true,
             show_ping: false,
             show_weapons: true,
+            show_timeline: true,
 
             kick_cheaters: false,
             kick_bots: true,
 
             party_notifications_for: default_party_notifications_for(),
 
+            auto_vote: false,
+            auto_vote_no_on_friends: true,
+            auto_vote_no_on_self: true,
+            auto_vote_yes_on: default_auto_vote_yes_on(),
+
+            player_lists: default_player_lists(),
+            attribute_mapping: default_attribute_mapping(),
+            record_sessions: false,
+
             config_is_ok: false,
         }
     }
 }
 
 impl AppSettings {
+    /// The list where flags set in TF2Monitor are saved, the first trusted list
+    pub fn own_player_list(&self) -> Option<&PlayerListSettings> {
+        self.player_lists
+            .iter()
+            .find(|player_list| !player_list.suggestion)
+    }
+
     /// Tries to load the preferences.rust_bot_detector.json file from the current directory.
     /// If the file don't exist, use default values.
     pub fn load_or_default() -> Self {
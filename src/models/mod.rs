@@ -1,10 +1,17 @@
 pub mod app_settings;
 pub mod steamid;
 
-use self::{app_settings::AppSettings, steamid::SteamID};
+use self::{
+    app_settings::{AppSettings, PlayerListSettings},
+    steamid::SteamID,
+};
 use crate::{
     appbus::{AppBus, AppEventMsg},
     db::db::DbPool,
+    gui::{
+        flag_exchange_window::FlagExchangeState, player_database_window::PlayerDatabaseSort,
+        playerlist_editor_window::PlayerListEditorState, rule_editor_window::RuleEditorState,
+    },
     tf2::lobby::{Lobby, shared_lobby::SharedLobby},
     tf2bd::models::PlayerAttribute,
 };
@@ -41,6 +48,23 @@ pub struct AppWin {
     pub player_database_filters: HashSet<PlayerAttribute>,
     pub player_database_search: String,
 
+    /// Column and descending order of the player database, unsorted when None
+    pub player_database_sort: Option<(PlayerDatabaseSort, bool)>,
+
+    // Reasons typed in the player details, sent with the next flag that is set on the player
+    pub flag_reasons: HashMap<SteamID, String>,
+
+    // Playerlist editor window state
+    pub playerlist_editor_window_open: bool,
+    pub playerlist_editor: PlayerListEditorState,
+
+    // Rule editor window state, the window is open when this is set
+    pub rule_editor: Option<RuleEditorState>,
+
+    // Export/import window state for the player flags database
+    pub flag_exchange_window_open: bool,
+    pub flag_exchange: FlagExchangeState,
+
     // Database connection pool
     pub db: Arc<DbPool>,
 }
@@ -56,6 +80,7 @@ pub struct TempSettings {
     pub log_filename: String,
     pub exe_filename: String,
     pub launch_options: String,
+    pub player_lists: Vec<PlayerListSettings>,
 }
 
 impl AppWin {
@@ -84,6 +109,13 @@ impl AppWin {
             player_database_window_open: false,
             player_database_filters: HashSet::from([PlayerAttribute::Cheater]),
             player_database_search: String::new(),
+            player_database_sort: None,
+            flag_reasons: HashMap::new(),
+            playerlist_editor_window_open: false,
+            playerlist_editor: PlayerListEditorState::default(),
+            rule_editor: None,
+            flag_exchange_window_open: false,
+            flag_exchange: FlagExchangeState::default(),
             db,
         }
     }
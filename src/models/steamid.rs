@@ -36,6 +36,46 @@ impl SteamID {
         }
     }
 
+    /// Parses the forms a SteamID is usually pasted in: SteamID64, [U:1:x],
+    /// STEAM_0:y:z and steamcommunity.com/profiles/<SteamID64> URLs
+    pub fn parse(input: &str) -> Option<Self> {
+        let input = input.trim().trim_end_matches('/');
+
+        if let Some((_, steamid64)) = input.split_once("/profiles/") {
+            let steamid64 = steamid64.split(['/', '?']).next()?;
+            return Self::parse_steamid64(steamid64);
+        }
+
+        if let Some(account_id) = input
+            .strip_prefix("[U:1:")
+            .and_then(|x| x.strip_suffix(']'))
+        {
+            let account_id = account_id.parse::<u64>().ok()?;
+            return account_id.checked_add(MIN_STEAMID64).map(Self::from_u64);
+        }
+
+        if let Some(steamid) = input.strip_prefix("STEAM_") {
+            // STEAM_X:Y:Z where X is the universe, which is 0 or 1 for the public universe
+            let parts: Vec<&str> = steamid.split(':').collect();
+            let [_universe, y, z] = parts.as_slice() else {
+                return None;
+            };
+            let y = y.parse::<u64>().ok().filter(|y| *y <= 1)?;
+            let z = z.parse::<u64>().ok()?;
+            return z
+                .checked_mul(2)
+                .and_then(|z| z.checked_add(MIN_STEAMID64 + y))
+                .map(Self::from_u64);
+        }
+
+        Self::parse_steamid64(input)
+    }
+
+    fn parse_steamid64(steamid64: &str) -> Option<Self> {
+        let steamid = Self::from_u64(steamid64.parse::<u64>().ok()?);
+        steamid.is_valid().then_some(steamid)
+    }
+
     /// Converts a SteamID64 to a SteamID32
     pub fn to_steam_id32(self) -> String {
         format!("[U:1:{}]", self.0 - MIN_STEAMID64)
@@ -121,4 +161,37 @@ mod tests {
             "STEAM_0:1:219096410".to_string()
         );
     }
+
+    #[test]
+    fn test_parse() {
+        let steamid = SteamID::from_u64(76561198398458549);
+        let inputs = [
+            "76561198398458549",
+            " 76561198398458549 ",
+            "[U:1:438192821]",
+            "STEAM_0:1:219096410",
+            "STEAM_1:1:219096410",
+            "https://steamcommunity.com/profiles/76561198398458549",
+            "https://steamcommunity.com/profiles/76561198398458549/",
+            "steamcommunity.com/profiles/76561198398458549/?xml=1",
+        ];
+        for input in inputs {
+            assert_eq!(SteamID::parse(input), Some(steamid), "{}", input);
+        }
+
+        let invalid = [
+            "",
+            "123",
+            "[U:1:abc]",
+            "STEAM_0:2:219096410",
+            "STEAM_0:1",
+            "[U:1:18446744073709551000]",
+            "STEAM_0:1:18446744073709551000",
+            "STEAM_0:1:9223372036854775807",
+            "https://steamcommunity.com/id/somename",
+        ];
+        for input in invalid {
+            assert_eq!(SteamID::parse(input), None, "{}", input);
+        }
+    }
 }
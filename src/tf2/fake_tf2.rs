@@ -0,0 +1,361 @@
+//
+// A fake TF2 to run the app and its tests without the game.
+// It serves Source RCON, answers g15_dumpplayer from a scripted roster that changes over time,
+// records the commands it receives and writes scripted lines to a console.log.
+//
+// The fake_tf2 binary includes this file, so it only uses std and external crates.
+//
+
+use chrono::Local;
+use fs_err as fs;
+use serde::{Deserialize, Serialize};
+use std::{
+    error::Error,
+    io::{Read, Write},
+    net::{Shutdown, TcpListener, TcpStream},
+    path::{Path, PathBuf},
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicBool, Ordering},
+    },
+    thread::{self, JoinHandle, sleep},
+    time::{Duration, Instant},
+};
+
+type BoxResult<T> = Result<T, Box<dyn Error>>;
+
+// The Source RCON package types and sizes, like in rcon_connection.rs
+const PKT_TYPE_RESPONSE_VALUE: u32 = 0;
+const PKT_TYPE_EXEC_COMMAND: u32 = 2;
+const PKT_TYPE_AUTH_RESPONSE: u32 = 2;
+const PKT_TYPE_AUTH: u32 = 3;
+const PKT_ID_AUTH_FAILED: u32 = u32::MAX;
+const PKT_HEADER_SIZE: usize = 4 + 4 + 2;
+
+/// TF2 splits long RCON replies into packages with bodies of this size
+const MAX_REPLY_BODY: usize = 4096;
+
+/// How often the script thread checks if it should stop while waiting for the next step
+const STOP_CHECK_DELAY: Duration = Duration::from_millis(100);
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FakePlayer {
+    pub user_id: u32,
+
+    /// The Steam account ID, the SteamID64 minus 76561197960265728
+    pub account_id: u32,
+    pub name: String,
+
+    /// 2 for RED and 3 for BLU, like in the playerresource
+    pub team: u32,
+
+    #[serde(default = "default_alive")]
+    pub alive: bool,
+    #[serde(default)]
+    pub ping: u32,
+    #[serde(default)]
+    pub health: u32,
+
+    #[serde(default)]
+    pub score: u32,
+}
+
+fn default_alive() -> bool {
+    true
+}
+
+/// What happens at a point in time after the fake TF2 was started
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct FakeTf2Step {
+    pub at_ms: u64,
+
+    /// Replaces the players in the server, when set
+    #[serde(default)]
+    pub roster: Option<Vec<FakePlayer>>,
+
+    /// Written to console.log with the current time as the timestamp
+    #[serde(default)]
+    pub log_lines: Vec<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct FakeTf2Script {
+    pub steps: Vec<FakeTf2Step>,
+}
+
+impl FakeTf2Script {
+    pub fn load(filename: &str) -> BoxResult<Self> {
+        let json = fs::read_to_string(filename)?;
+        Ok(serde_json::from_str(&json)?)
+    }
+}
+
+#[derive(Default)]
+struct FakeTf2State {
+    roster: Vec<FakePlayer>,
+
+    /// Every RCON command received, in order
+    commands: Vec<String>,
+
+    /// The connected RCON clients, so they can be disconnected when stopping
+    clients: Vec<TcpStream>,
+}
+
+/// Stops serving RCON and playing the script when dropped
+pub struct FakeTf2 {
+    port: u16,
+    state: Arc<Mutex<FakeTf2State>>,
+    stop: Arc<AtomicBool>,
+    threads: Vec<JoinHandle<()>>,
+}
+
+impl FakeTf2 {
+    /// Serves RCON on the port, 0 for any free port, and plays the script in the background.
+    /// The log file is cleared first, like TF2 does with -conclearlog.
+    pub fn start(
+        script: FakeTf2Script,
+        port: u16,
+        password: &str,
+        log_filename: &Path,
+    ) -> BoxResult<Self> {
+        let listener = TcpListener::bind(("127.0.0.1", port))?;
+        let port = listener.local_addr()?.port();
+        fs::write(log_filename, "")?;
+
+        let state = Arc::new(Mutex::new(FakeTf2State::default()));
+        let stop = Arc::new(AtomicBool::new(false));
+
+        let password = password.to_string();
+        let rcon_state = Arc::clone(&state);
+        let rcon_stop = Arc::clone(&stop);
+        let listener_thread = thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                if rcon_stop.load(Ordering::Relaxed) {
+                    break;
+                }
+                if let Ok(client) = stream.try_clone() {
+                    rcon_state.lock().unwrap().clients.push(client);
+                }
+
+                let password = password.clone();
+                let state = Arc::clone(&rcon_state);
+                thread::spawn(move || serve_rcon(stream, &password, &state));
+            }
+        });
+
+        let log_filename = log_filename.to_path_buf();
+        let script_state = Arc::clone(&state);
+        let script_stop = Arc::clone(&stop);
+        let script_thread =
+            thread::spawn(move || play_script(script, &log_filename, &script_state, &script_stop));
+
+        Ok(Self {
+            port,
+            state,
+            stop,
+            threads: vec![listener_thread, script_thread],
+        })
+    }
+
+    pub fn port(&self) -> u16 {
+        self.port
+    }
+
+    pub fn commands(&self) -> Vec<String> {
+        self.state.lock().unwrap().commands.clone()
+    }
+}
+
+impl Drop for FakeTf2 {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+
+        // Wakes up the listener so it sees the stop flag, and ends the RCON connections
+        let _ = TcpStream::connect(("127.0.0.1", self.port));
+        for client in self.state.lock().unwrap().clients.drain(..) {
+            let _ = client.shutdown(Shutdown::Both);
+        }
+
+        for thread in self.threads.drain(..) {
+            if thread.join().is_err() {
+                log::error!("A fake TF2 thread panicked");
+            }
+        }
+    }
+}
+
+fn play_script(
+    script: FakeTf2Script,
+    log_filename: &PathBuf,
+    state: &Mutex<FakeTf2State>,
+    stop: &AtomicBool,
+) {
+    let started = Instant::now();
+
+    for step in script.steps {
+        let at = Duration::from_millis(step.at_ms);
+        while started.elapsed() < at {
+            if stop.load(Ordering::Relaxed) {
+                return;
+            }
+            sleep(STOP_CHECK_DELAY.min(at.saturating_sub(started.elapsed())));
+        }
+
+        if let Some(roster) = step.roster {
+            state.lock().unwrap().roster = roster;
+        }
+
+        if let Err(e) = append_log_lines(log_filename, &step.log_lines) {
+            log::error!("Fake TF2 could not write to {:?}: {}", log_filename, e);
+        }
+    }
+}
+
+fn append_log_lines(log_filename: &PathBuf, lines: &[String]) -> BoxResult<()> {
+    let mut file = fs::OpenOptions::new().append(true).open(log_filename)?;
+    let timestamp = Local::now().format("%m/%d/%Y - %H:%M:%S");
+    for line in lines {
+        write!(file, "{}: {}\r\n", timestamp, line)?;
+    }
+
+    Ok(())
+}
+
+fn serve_rcon(mut stream: TcpStream, password: &str, state: &Mutex<FakeTf2State>) {
+    let mut authorized = false;
+
+    while let Some(pkt) = read_packet(&mut stream) {
+        match pkt.typ {
+            PKT_TYPE_AUTH => {
+                authorized = pkt.body == password;
+                let id = if authorized {
+                    pkt.id
+                } else {
+                    PKT_ID_AUTH_FAILED
+                };
+                write_packet(&mut stream, pkt.id, PKT_TYPE_RESPONSE_VALUE, "");
+                write_packet(&mut stream, id, PKT_TYPE_AUTH_RESPONSE, "");
+            }
+            // TF2 drops connections that send commands without a password
+            PKT_TYPE_EXEC_COMMAND if !authorized => return,
+            PKT_TYPE_EXEC_COMMAND if pkt.body.is_empty() => {
+                // The reply to the halt package
+                write_packet(&mut stream, pkt.id, PKT_TYPE_RESPONSE_VALUE, "");
+            }
+            PKT_TYPE_EXEC_COMMAND => {
+                let reply = {
+                    let mut state = state.lock().unwrap();
+                    state.commands.push(pkt.body.clone());
+                    if pkt.body == "g15_dumpplayer" {
+                        g15_dump(&state.roster)
+                    } else {
+                        String::new()
+                    }
+                };
+
+                for body in split_reply(&reply, MAX_REPLY_BODY) {
+                    write_packet(&mut stream, pkt.id, PKT_TYPE_RESPONSE_VALUE, body);
+                }
+            }
+            _ => {
+                log::warn!("Fake TF2 got an unknown RCON package: {:?}", pkt);
+                return;
+            }
+        }
+    }
+}
+
+/// The playerresource section of a g15_dumpplayer reply, with the player in slot 1 first
+pub fn g15_dump(roster: &[FakePlayer]) -> String {
+    let mut dump = "(playerresource)\n\n".to_string();
+
+    let mut add_field = |field: &str, typ: &str, value: &dyn Fn(&FakePlayer) -> String| {
+        for (i, player) in roster.iter().enumerate() {
+            dump += &format!("{}[{}] {} ({})\n", field, i + 1, typ, value(player));
+        }
+    };
+
+    add_field("m_iPing", "integer", &|p| p.ping.to_string());
+    add_field("m_iTeam", "integer", &|p| p.team.to_string());
+    add_field("m_bAlive", "bool", &|p| p.alive.to_string());
+    add_field("m_iHealth", "integer", &|p| p.health.to_string());
+    add_field("m_iScore", "integer", &|p| p.score.to_string());
+    add_field("m_iAccountID", "integer", &|p| p.account_id.to_string());
+    add_field("m_bValid", "bool", &|_| "true".to_string());
+    add_field("m_iUserID", "integer", &|p| p.user_id.to_string());
+    add_field("m_szName", "string", &|p| p.name.clone());
+    add_field("m_bConnected", "bool", &|_| "true".to_string());
+
+    dump
+}
+
+/// Splits a reply into package bodies of max bytes without splitting characters.
+/// An empty reply is still one package.
+fn split_reply(reply: &str, max: usize) -> Vec<&str> {
+    let mut bodies = Vec::new();
+    let mut rest = reply;
+    loop {
+        let mut end = rest.len().min(max);
+        while !rest.is_char_boundary(end) {
+            end -= 1;
+        }
+        let (body, tail) = rest.split_at(end);
+        bodies.push(body);
+        rest = tail;
+        if rest.is_empty() {
+            return bodies;
+        }
+    }
+}
+
+#[derive(Debug)]
+pub(crate) struct RconPacket {
+    pub id: u32,
+    pub typ: u32,
+    pub body: String,
+}
+
+/// Reads a package the way a Source server does, None when the client has closed the connection
+pub(crate) fn read_packet(stream: &mut TcpStream) -> Option<RconPacket> {
+    let mut header = [0u8; 12];
+    stream.read_exact(&mut header).ok()?;
+    let size = u32::from_le_bytes(header[0..4].try_into().unwrap()) as usize;
+    let id = u32::from_le_bytes(header[4..8].try_into().unwrap());
+    let typ = u32::from_le_bytes(header[8..12].try_into().unwrap());
+
+    // The id and type are already read
+    let mut body = vec![0u8; size.checked_sub(8).filter(|len| *len >= 2)?];
+    stream.read_exact(&mut body).ok()?;
+    body.truncate(body.len() - 2);
+
+    Some(RconPacket {
+        id,
+        typ,
+        body: String::from_utf8_lossy(&body).to_string(),
+    })
+}
+
+pub(crate) fn write_packet(stream: &mut TcpStream, id: u32, typ: u32, body: &str) {
+    let mut data = Vec::new();
+    data.extend_from_slice(&((PKT_HEADER_SIZE + body.len()) as u32).to_le_bytes());
+    data.extend_from_slice(&id.to_le_bytes());
+    data.extend_from_slice(&typ.to_le_bytes());
+    data.extend_from_slice(body.as_bytes());
+    data.extend_from_slice(&[0, 0]);
+
+    if let Err(e) = stream.write_all(&data) {
+        log::warn!("Fake TF2 could not send an RCON reply: {}", e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_reply() {
+        assert_eq!(split_reply("", 4), vec![""]);
+        assert_eq!(split_reply("abcdefghij", 4), vec!["abcd", "efgh", "ij"]);
+        assert_eq!(split_reply("aéé", 4), vec!["aé", "é"]);
+    }
+}
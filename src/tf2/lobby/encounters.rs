@@ -0,0 +1,263 @@
+//
+// Encounter history: how often, how long and on which team we have been in a lobby with a player.
+// The lobby thread records every g15_dumpplayer output and stores the totals with the match session.
+//
+
+use super::{Lobby, Team};
+use crate::{
+    config::ENCOUNTER_MAX_GAP,
+    db::entities::{Encounter, NewEncounter},
+    models::steamid::SteamID,
+};
+use chrono::{DateTime, Local};
+use std::collections::{HashMap, HashSet};
+
+/// What has been recorded about a player since the encounters were last stored
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PendingEncounter {
+    pub first_seen: i64,
+    pub last_seen: i64,
+    pub matches: i32,
+    pub seconds: i64,
+    pub same_team: i32,
+    pub enemy_team: i32,
+    pub names: Vec<String>,
+}
+
+#[derive(Debug, Default)]
+pub struct EncounterTracker {
+    lobby_id: String,
+    last_dump: Option<DateTime<Local>>,
+
+    /// Players whose match and team has been counted in this lobby
+    counted_matches: HashSet<SteamID>,
+    counted_teams: HashSet<SteamID>,
+
+    pending: HashMap<SteamID, PendingEncounter>,
+}
+
+impl EncounterTracker {
+    /// Records the players in a g15_dumpplayer output, after it has been merged into the lobby
+    pub fn record(&mut self, lobby: &Lobby, steamids: &HashSet<SteamID>, now: DateTime<Local>) {
+        if self.lobby_id != lobby.lobby_id {
+            self.lobby_id = lobby.lobby_id.clone();
+            self.last_dump = None;
+            self.counted_matches.clear();
+            self.counted_teams.clear();
+        }
+
+        // Time since the previous output is spent with everyone in this one,
+        // unless the outputs stopped for a while, e.g. while TF2 was loading a map
+        let seconds = self
+            .last_dump
+            .map(|last_dump| (now - last_dump).num_seconds())
+            .filter(|seconds| *seconds as u64 <= ENCOUNTER_MAX_GAP.as_secs())
+            .unwrap_or(0)
+            .max(0);
+        self.last_dump = Some(now);
+
+        let my_team = lobby
+            .get_me()
+            .map(|me| me.team)
+            .filter(|team| matches!(team, Team::Red | Team::Blue));
+
+        for player in &lobby.players {
+            if player.steamid == lobby.self_steamid || !steamids.contains(&player.steamid) {
+                continue;
+            }
+
+            let pending = self
+                .pending
+                .entry(player.steamid)
+                .or_insert_with(|| PendingEncounter {
+                    first_seen: now.timestamp(),
+                    last_seen: now.timestamp(),
+                    matches: 0,
+                    seconds: 0,
+                    same_team: 0,
+                    enemy_team: 0,
+                    names: Vec::new(),
+                });
+
+            pending.last_seen = now.timestamp();
+            pending.seconds += seconds;
+
+            if self.counted_matches.insert(player.steamid) {
+                pending.matches += 1;
+            }
+
+            if let Some(my_team) = my_team
+                && matches!(player.team, Team::Red | Team::Blue)
+                && self.counted_teams.insert(player.steamid)
+            {
+                if player.team == my_team {
+                    pending.same_team += 1;
+                } else {
+                    pending.enemy_team += 1;
+                }
+            }
+
+            if !player.name.is_empty() && !pending.names.contains(&player.name) {
+                pending.names.push(player.name.clone());
+            }
+        }
+    }
+
+    /// The encounters recorded since the last call
+    pub fn take_pending(&mut self) -> HashMap<SteamID, PendingEncounter> {
+        std::mem::take(&mut self.pending)
+    }
+}
+
+/// Adds what has been recorded to the stored encounter
+pub fn merge_encounter(
+    steamid: SteamID,
+    existing: Option<&Encounter>,
+    pending: &PendingEncounter,
+) -> NewEncounter {
+    let Some(existing) = existing else {
+        return NewEncounter {
+            steam_id: steamid.to_u64() as i64,
+            first_seen: pending.first_seen,
+            last_seen: pending.last_seen,
+            matches: pending.matches,
+            seconds: pending.seconds,
+            same_team: pending.same_team,
+            enemy_team: pending.enemy_team,
+            names: pending.names.join("\n"),
+        };
+    };
+
+    let mut names: Vec<&str> = encounter_names(existing).collect();
+    for name in &pending.names {
+        if !names.contains(&name.as_str()) {
+            names.push(name);
+        }
+    }
+
+    NewEncounter {
+        steam_id: existing.steam_id,
+        first_seen: existing.first_seen.min(pending.first_seen),
+        last_seen: existing.last_seen.max(pending.last_seen),
+        matches: existing.matches + pending.matches,
+        seconds: existing.seconds + pending.seconds,
+        same_team: existing.same_team + pending.same_team,
+        enemy_team: existing.enemy_team + pending.enemy_team,
+        names: names.join("\n"),
+    }
+}
+
+/// The names seen in the encounters, oldest first
+pub fn encounter_names(encounter: &Encounter) -> impl Iterator<Item = &str> {
+    encounter.names.lines().filter(|name| !name.is_empty())
+}
+
+/// Time as "2h 5m", "5m" or "30s"
+pub fn format_encounter_time(seconds: i64) -> String {
+    match seconds {
+        0..60 => format!("{}s", seconds.max(0)),
+        60..3600 => format!("{}m", seconds / 60),
+        _ => format!("{}h {}m", seconds / 3600, seconds % 3600 / 60),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tf2::lobby::Player;
+    use chrono::{Duration, TimeZone};
+
+    fn player(steamid: SteamID, name: &str, team: Team) -> Player {
+        Player {
+            steamid,
+            name: name.to_string(),
+            team,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_record_encounters() {
+        let me = SteamID::from_u64(76561198012345678);
+        let friend = SteamID::from_u64(76561197960287930);
+        let enemy = SteamID::from_u64(76561198000000001);
+        let start = Local.with_ymd_and_hms(2024, 5, 6, 17, 0, 0).unwrap();
+
+        let mut lobby = Lobby::new(me);
+        lobby.players = vec![
+            player(me, "Me", Team::Red),
+            player(friend, "Friend", Team::Red),
+            player(enemy, "Enemy", Team::Blue),
+        ];
+        let everyone: HashSet<SteamID> = [me, friend, enemy].into();
+
+        let mut tracker = EncounterTracker::default();
+        tracker.record(&lobby, &everyone, start);
+        tracker.record(&lobby, &everyone, start + Duration::seconds(2));
+
+        // The enemy renames and leaves, the dumps stop for a while
+        lobby.players[2].name = "Enemy renamed".to_string();
+        tracker.record(&lobby, &everyone, start + Duration::seconds(4));
+        tracker.record(&lobby, &[me, friend].into(), start + Duration::seconds(6));
+        tracker.record(&lobby, &[me, friend].into(), start + Duration::seconds(60));
+
+        let pending = tracker.take_pending();
+        assert_eq!(pending.len(), 2);
+        assert!(tracker.take_pending().is_empty());
+
+        let friend_encounter = &pending[&friend];
+        assert_eq!(friend_encounter.matches, 1);
+        assert_eq!(friend_encounter.seconds, 6);
+        assert_eq!(
+            (friend_encounter.same_team, friend_encounter.enemy_team),
+            (1, 0)
+        );
+        assert_eq!(
+            friend_encounter.last_seen,
+            (start + Duration::seconds(60)).timestamp()
+        );
+
+        let enemy_encounter = &pending[&enemy];
+        assert_eq!(enemy_encounter.seconds, 4);
+        assert_eq!(
+            (enemy_encounter.same_team, enemy_encounter.enemy_team),
+            (0, 1)
+        );
+        assert_eq!(enemy_encounter.names, vec!["Enemy", "Enemy renamed"]);
+
+        // A new lobby is another match
+        lobby.lobby_id = "next".to_string();
+        tracker.record(&lobby, &everyone, start + Duration::seconds(3600));
+        assert_eq!(tracker.take_pending()[&enemy].matches, 1);
+
+        let stored = Encounter {
+            steam_id: enemy.to_u64() as i64,
+            first_seen: 100,
+            last_seen: 200,
+            matches: 3,
+            seconds: 600,
+            same_team: 2,
+            enemy_team: 1,
+            names: "Old name\nEnemy".to_string(),
+        };
+        let merged = merge_encounter(enemy, Some(&stored), enemy_encounter);
+        assert_eq!(
+            (merged.first_seen, merged.last_seen),
+            (100, enemy_encounter.last_seen)
+        );
+        assert_eq!((merged.matches, merged.seconds), (4, 604));
+        assert_eq!((merged.same_team, merged.enemy_team), (2, 2));
+        assert_eq!(merged.names, "Old name\nEnemy\nEnemy renamed");
+
+        let new = merge_encounter(friend, None, friend_encounter);
+        assert_eq!(new.steam_id, friend.to_u64() as i64);
+        assert_eq!(new.names, "Friend");
+    }
+
+    #[test]
+    fn test_format_encounter_time() {
+        assert_eq!(format_encounter_time(42), "42s");
+        assert_eq!(format_encounter_time(300), "5m");
+        assert_eq!(format_encounter_time(7500), "2h 5m");
+    }
+}
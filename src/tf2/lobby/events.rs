@@ -0,0 +1,235 @@
+use super::name_resolver::resolve_name;
+use super::{Lobby, LobbyEvent, LobbyEventKind, Team};
+use crate::tf2::logfile::LogLine;
+
+/// The team of a "joined team" line
+pub fn team_from_name(team: &str) -> Team {
+    match team {
+        "RED" => Team::Red,
+        "BLU" => Team::Blue,
+        "Spectator" => Team::Spec,
+        _ => Team::Unknown,
+    }
+}
+
+/// The team of a capture line, "for team #3"
+fn team_from_number(team: u32) -> Team {
+    match team {
+        2 => Team::Red,
+        3 => Team::Blue,
+        _ => Team::Unknown,
+    }
+}
+
+fn team_label(team: Team) -> &'static str {
+    match team {
+        Team::Red => "RED",
+        Team::Blue => "BLU",
+        Team::Spec => "Spectator",
+        Team::Unknown => "unknown team",
+    }
+}
+
+/// Adds the event of a console line to the timeline of the lobby.
+/// Team switches also change the team of the player.
+/// Lines that are not events are ignored.
+pub fn add_event(lobby: &mut Lobby, line: LogLine) {
+    let (when, kind, name, text) = match line {
+        LogLine::PlayerConnected { when, name } => (
+            when,
+            LobbyEventKind::Connected,
+            Some(name.clone()),
+            format!("{} connected", name),
+        ),
+        LogLine::PlayerDisconnected { when, name, reason } => (
+            when,
+            LobbyEventKind::Disconnected,
+            Some(name.clone()),
+            format!("{} left ({})", name, reason),
+        ),
+        LogLine::TeamSwitch { when, name, team } => {
+            let team = team_from_name(&team);
+            if let Some((steamid, _)) = resolve_name(&lobby.players, &name, None)
+                && let Some(player) = lobby.get_player_mut(None, Some(steamid))
+            {
+                player.team = team;
+            }
+
+            (
+                when,
+                LobbyEventKind::TeamSwitch,
+                Some(name.clone()),
+                format!("{} joined {}", name, team_label(team)),
+            )
+        }
+        LogLine::VoteStarted {
+            when,
+            caller,
+            issue,
+            kick_target,
+        } => {
+            let target = kick_target
+                .as_deref()
+                .and_then(|target| resolve_name(&lobby.players, target, None));
+            let against_me = target.is_some_and(|(steamid, _)| steamid == lobby.self_steamid);
+            if against_me {
+                log::warn!("{} called a vote to kick us", caller);
+            }
+
+            (
+                when,
+                if against_me {
+                    LobbyEventKind::VoteAgainstMe
+                } else {
+                    LobbyEventKind::Vote
+                },
+                kick_target.or(Some(caller.clone())),
+                format!("{} called a vote: {}", caller, issue),
+            )
+        }
+        LogLine::VotePassed {
+            when,
+            issue,
+            kick_target,
+        } => (
+            when,
+            LobbyEventKind::Vote,
+            kick_target,
+            format!("Vote passed: {}", issue),
+        ),
+        LogLine::VoteFailed { when, reason } => (
+            when,
+            LobbyEventKind::Vote,
+            None,
+            format!("Vote failed: {}", reason),
+        ),
+        LogLine::PointCaptured {
+            when,
+            cappers,
+            point,
+            team,
+        } => (
+            when,
+            LobbyEventKind::Capture,
+            None,
+            format!(
+                "{} captured {} for {}",
+                cappers.join(", "),
+                point,
+                team_label(team_from_number(team))
+            ),
+        ),
+        LogLine::Kicked { when, reason } => {
+            log::warn!("Kicked from the server: {}", reason);
+            (
+                when,
+                LobbyEventKind::Kicked,
+                None,
+                format!("You were kicked: {}", reason),
+            )
+        }
+        _ => return,
+    };
+
+    let steamid = name
+        .and_then(|name| resolve_name(&lobby.players, &name, None))
+        .map(|(steamid, _)| steamid);
+
+    lobby.events.push(LobbyEvent {
+        when,
+        kind,
+        steamid,
+        text,
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::steamid::SteamID;
+    use crate::tf2::lobby::Player;
+    use chrono::Local;
+
+    fn lobby() -> Lobby {
+        let me = SteamID::from_u64(76561198012345678);
+        let mut lobby = Lobby::new(me);
+        lobby.players.push(Player {
+            id: 1,
+            steamid: me,
+            name: "Me".to_string(),
+            team: Team::Red,
+            ..Default::default()
+        });
+        lobby.players.push(Player {
+            id: 2,
+            steamid: SteamID::from_u64(76561198000000001),
+            name: "Player1".to_string(),
+            team: Team::Red,
+            ..Default::default()
+        });
+        lobby
+    }
+
+    #[test]
+    fn test_team_switch() {
+        let mut lobby = lobby();
+        add_event(
+            &mut lobby,
+            LogLine::TeamSwitch {
+                when: Local::now(),
+                name: "Player1".to_string(),
+                team: "BLU".to_string(),
+            },
+        );
+
+        assert_eq!(lobby.players[1].team, Team::Blue);
+        assert_eq!(lobby.events.len(), 1);
+        assert_eq!(lobby.events[0].kind, LobbyEventKind::TeamSwitch);
+        assert_eq!(lobby.events[0].steamid, Some(lobby.players[1].steamid));
+        assert_eq!(lobby.events[0].text, "Player1 joined BLU");
+    }
+
+    #[test]
+    fn test_vote_against_me() {
+        let mut lobby = lobby();
+        add_event(
+            &mut lobby,
+            LogLine::VoteStarted {
+                when: Local::now(),
+                caller: "Player1".to_string(),
+                issue: "Kick Me (cheating)".to_string(),
+                kick_target: Some("Me".to_string()),
+            },
+        );
+        add_event(
+            &mut lobby,
+            LogLine::VoteStarted {
+                when: Local::now(),
+                caller: "Me".to_string(),
+                issue: "Kick Player1 (cheating)".to_string(),
+                kick_target: Some("Player1".to_string()),
+            },
+        );
+
+        let kinds: Vec<LobbyEventKind> = lobby.events.iter().map(|e| e.kind).collect();
+        assert_eq!(
+            kinds,
+            vec![LobbyEventKind::VoteAgainstMe, LobbyEventKind::Vote]
+        );
+        assert_eq!(lobby.events[0].steamid, Some(lobby.self_steamid));
+    }
+
+    #[test]
+    fn test_other_lines_are_ignored() {
+        let mut lobby = lobby();
+        add_event(
+            &mut lobby,
+            LogLine::Suicide {
+                when: Local::now(),
+                name: "Player1".to_string(),
+            },
+        );
+
+        assert!(lobby.events.is_empty());
+    }
+}
@@ -0,0 +1,211 @@
+use super::Player;
+use crate::models::steamid::SteamID;
+use std::collections::HashMap;
+
+/// Reduces a name to what it looks like on screen, so copies of a name
+/// made with invisible characters or look-alike letters compare equal.
+pub fn normalize_name(name: &str) -> String {
+    let normalized: String = name
+        .chars()
+        .filter(|c| !is_invisible(*c))
+        .map(unconfuse)
+        .flat_map(char::to_lowercase)
+        .collect();
+
+    normalized.trim().to_string()
+}
+
+/// Zero-width, formatting and control characters that do not show up in the scoreboard
+fn is_invisible(c: char) -> bool {
+    c.is_control()
+        || matches!(
+            c,
+            '\u{00AD}'
+                | '\u{034F}'
+                | '\u{061C}'
+                | '\u{115F}'
+                | '\u{1160}'
+                | '\u{17B4}'
+                | '\u{17B5}'
+                | '\u{180E}'
+                | '\u{200B}'..='\u{200F}'
+                | '\u{202A}'..='\u{202E}'
+                | '\u{2060}'..='\u{206F}'
+                | '\u{3164}'
+                | '\u{FE00}'..='\u{FE0F}'
+                | '\u{FEFF}'
+                | '\u{FFA0}'
+                | '\u{E0000}'..='\u{E007F}'
+        )
+}
+
+/// Maps a character to the ASCII character it looks like, if any
+fn unconfuse(c: char) -> char {
+    let code = c as u32;
+
+    // Fullwidth forms, ＡＢＣ
+    if (0xFF01..=0xFF5E).contains(&code) {
+        return char::from_u32(code - 0xFEE0).unwrap_or(c);
+    }
+
+    // Mathematical alphanumeric symbols, 𝐀𝖁𝓒. Each style is a block of A-Z followed by a-z.
+    if (0x1D400..=0x1D6A3).contains(&code) {
+        let offset = ((code - 0x1D400) % 52) as u8;
+        return if offset < 26 {
+            (b'A' + offset) as char
+        } else {
+            (b'a' + offset - 26) as char
+        };
+    }
+
+    // Mathematical digits, 𝟎𝟙𝟸
+    if (0x1D7CE..=0x1D7FF).contains(&code) {
+        return (b'0' + ((code - 0x1D7CE) % 10) as u8) as char;
+    }
+
+    match c {
+        // Whitespace that looks like a regular space
+        '\u{00A0}' | '\u{2000}'..='\u{200A}' | '\u{202F}' | '\u{205F}' | '\u{3000}' => ' ',
+
+        // Cyrillic
+        'А' => 'A',
+        'В' => 'B',
+        'Е' => 'E',
+        'К' => 'K',
+        'М' => 'M',
+        'Н' => 'H',
+        'О' => 'O',
+        'Р' => 'P',
+        'С' => 'C',
+        'Т' => 'T',
+        'Х' => 'X',
+        'Ѕ' => 'S',
+        'І' => 'I',
+        'Ј' => 'J',
+        'а' => 'a',
+        'е' => 'e',
+        'о' => 'o',
+        'р' => 'p',
+        'с' => 'c',
+        'у' => 'y',
+        'х' => 'x',
+        'ѕ' => 's',
+        'і' => 'i',
+        'ј' => 'j',
+        'һ' => 'h',
+        'ԁ' => 'd',
+        'ԛ' => 'q',
+        'ԝ' => 'w',
+
+        // Greek
+        'Α' => 'A',
+        'Β' => 'B',
+        'Ε' => 'E',
+        'Ζ' => 'Z',
+        'Η' => 'H',
+        'Ι' => 'I',
+        'Κ' => 'K',
+        'Μ' => 'M',
+        'Ν' => 'N',
+        'Ο' => 'O',
+        'Ρ' => 'P',
+        'Τ' => 'T',
+        'Υ' => 'Y',
+        'Χ' => 'X',
+        'ο' => 'o',
+        'ν' => 'v',
+        'ι' => 'i',
+
+        // Latin look-alikes
+        'ı' => 'i',
+        'ɡ' => 'g',
+        'ℓ' => 'l',
+        'ǀ' => 'l',
+
+        _ => c,
+    }
+}
+
+/// Finds players whose name looks the same as the name of another player in the lobby,
+/// and marks the one that joined last as impersonating the other.
+/// Players that joined later get a higher user id from the server.
+pub fn update_impersonators(players: &mut [Player]) {
+    // The first player (lowest user id) seen with each normalized name
+    let mut originals: HashMap<String, (i64, SteamID)> = HashMap::new();
+    for player in players.iter() {
+        let name = normalize_name(&player.name);
+        if name.is_empty() {
+            continue;
+        }
+
+        originals
+            .entry(name)
+            .and_modify(|original| {
+                if player.id < original.0 {
+                    *original = (player.id, player.steamid);
+                }
+            })
+            .or_insert((player.id, player.steamid));
+    }
+
+    for player in players.iter_mut() {
+        player.impersonating = originals
+            .get(&normalize_name(&player.name))
+            .map(|(_, steamid)| *steamid)
+            .filter(|steamid| *steamid != player.steamid);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn player(id: i64, steamid: u64, name: &str) -> Player {
+        Player {
+            id,
+            steamid: SteamID::from_u64(steamid),
+            name: name.to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_normalize_name() {
+        assert_eq!(normalize_name("Player1"), "player1");
+        assert_eq!(normalize_name("Player1\u{200B}"), "player1");
+        assert_eq!(normalize_name("\u{202E}Player1\u{2060} "), "player1");
+        assert_eq!(normalize_name("Plаyer1"), "player1"); // Cyrillic a
+        assert_eq!(normalize_name("ＰｌａｙｅｒＯｎｅ"), "playerone");
+        assert_eq!(normalize_name("𝖁𝖆𝖘𝖎𝖑𝖎𝖘"), "vasilis");
+        assert_eq!(
+            normalize_name("used to facetime\u{00A0}with your mom"),
+            "used to facetime with your mom"
+        );
+        assert_ne!(normalize_name("Player1"), normalize_name("Player2"));
+    }
+
+    #[test]
+    fn test_update_impersonators() {
+        let mut players = vec![
+            player(12, 3, "used to facetime with your mom\u{200B}"),
+            player(5, 1, "used to facetime with your mom"),
+            player(7, 2, "1Pleaseburger"),
+            player(9, 4, "rafailnn306"),
+            player(14, 5, "rаfailnn306 "),
+        ];
+
+        update_impersonators(&mut players);
+
+        let impersonating: Vec<Option<u64>> = players
+            .iter()
+            .map(|p| p.impersonating.map(|s| s.to_u64()))
+            .collect();
+        assert_eq!(impersonating, vec![Some(1), None, None, None, Some(4)]);
+
+        // The impersonator leaves
+        players.remove(0);
+        update_impersonators(&mut players);
+        assert!(players[0].impersonating.is_none());
+        assert_eq!(players[3].impersonating.map(|s| s.to_u64()), Some(4));
+    }
+}
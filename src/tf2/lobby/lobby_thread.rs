@@ -1,14 +1,22 @@
-use super::LobbyKill;
+use super::encounters::{EncounterTracker, merge_encounter};
+use super::events::add_event;
+use super::impersonation::update_impersonators;
+use super::name_resolver::{NameResolutionStats, resolve_chat, resolve_kill, resolve_name};
+use super::sessions;
 use super::shared_lobby::SharedLobby;
+use super::votes::update_votes;
 use super::{LobbyChat, Player, PlayerKill};
-use crate::config::LOBBY_LOOP_DELAY;
+use super::{LobbyKill, LobbyServer, new_lobby_id};
+use crate::config::{LOBBY_LOOP_DELAY, MATCH_SESSION_SAVE_PERIOD};
 use crate::db::db::DbPool;
+use crate::db::entities::{Encounter, NAME_SOURCE_GAME, NewNameHistory};
+use crate::db::queries;
 use crate::tf2::lobby::AccountAge;
-use crate::tf2::rcon::{G15DumpPlayerOutput, G15PlayerData};
+use crate::tf2::rcon::{G15DumpPlayerOutput, G15PlayerData, RconState};
 use crate::tf2::steamapi::SteamApiMsg;
 use crate::tf2bd::Tf2bdMsg;
 use crate::{
-    appbus::AppBus,
+    appbus::{AppBus, AppEventMsg},
     models::{app_settings::AppSettings, steamid::SteamID},
     tf2::logfile::LogLine,
 };
@@ -18,6 +26,7 @@ use std::collections::HashSet;
 use std::{
     sync::{Arc, Mutex},
     thread::{self, sleep},
+    time::Instant,
 };
 use translators::{GoogleTranslator, Translator};
 
@@ -29,24 +38,37 @@ pub struct LobbyThread {
     steamapi_bus_rx: BusReader<SteamApiMsg>,
     tf2bd_bus_rx: BusReader<Tf2bdMsg>,
     g15_bus_rx: BusReader<G15DumpPlayerOutput>,
+    rcon_state_bus_rx: BusReader<RconState>,
+    app_event_bus_rx: BusReader<AppEventMsg>,
     shared_lobby: SharedLobby,
 
     text_translator: GoogleTranslator,
+
+    db: DbPool,
+    last_session_save: Instant,
+    encounters: EncounterTracker,
+    exit: bool,
 }
 
 /// Start the background thread for the lobby module
-pub fn start(settings: &AppSettings, bus: &Arc<Mutex<AppBus>>) -> thread::JoinHandle<()> {
-    let mut lobby_thread = LobbyThread::new(settings, bus);
+pub fn start(
+    settings: &AppSettings,
+    bus: &Arc<Mutex<AppBus>>,
+    db: &DbPool,
+) -> thread::JoinHandle<()> {
+    let mut lobby_thread = LobbyThread::new(settings, bus, db);
 
     thread::spawn(move || lobby_thread.run())
 }
 
 impl LobbyThread {
-    pub fn new(_settings: &AppSettings, bus: &Arc<Mutex<AppBus>>) -> Self {
+    pub fn new(_settings: &AppSettings, bus: &Arc<Mutex<AppBus>>, db: &DbPool) -> Self {
         let logfile_bus_rx = bus.lock().unwrap().logfile_bus.add_rx();
         let steamapi_bus_rx = bus.lock().unwrap().steamapi_bus.add_rx();
         let tf2bd_bus_rx = bus.lock().unwrap().tf2bd_bus.add_rx();
         let g15_bus_rx = bus.lock().unwrap().g15_report_bus.add_rx();
+        let rcon_state_bus_rx = bus.lock().unwrap().rcon_state_bus.add_rx();
+        let app_event_bus_rx = bus.lock().unwrap().app_event_bus.add_rx();
         let shared_lobby = bus.lock().unwrap().shared_lobby.clone();
 
         let google_translator = GoogleTranslator::default();
@@ -56,16 +78,23 @@ impl LobbyThread {
             steamapi_bus_rx,
             tf2bd_bus_rx,
             g15_bus_rx,
+            rcon_state_bus_rx,
+            app_event_bus_rx,
             shared_lobby,
 
             text_translator: google_translator,
+
+            db: db.clone(),
+            last_session_save: Instant::now(),
+            encounters: EncounterTracker::default(),
+            exit: false,
         }
     }
 
     pub fn run(&mut self) {
         log::info!("Lobby background thread started");
 
-        loop {
+        while !self.exit {
             self.process_bus();
 
             let mut lobby = self.shared_lobby.get();
@@ -74,8 +103,16 @@ impl LobbyThread {
             self.shared_lobby.set(lobby);
             self.translate_chat();
 
+            if self.last_session_save.elapsed() >= MATCH_SESSION_SAVE_PERIOD {
+                self.save_session();
+            }
+
             sleep(LOBBY_LOOP_DELAY);
         }
+
+        // The encounters since the last save would be lost otherwise
+        self.end_session(Local::now());
+        log::info!("Lobby background thread stopped");
     }
 
     fn process_bus(&mut self) {
@@ -84,6 +121,24 @@ impl LobbyThread {
         self.process_logfile_bus();
         self.process_steamapi_bus();
         self.process_tf2bd_bus();
+        self.process_rcon_state_bus();
+        self.process_app_event_bus();
+    }
+
+    fn process_app_event_bus(&mut self) {
+        while let Ok(app_event) = self.app_event_bus_rx.try_recv() {
+            if let AppEventMsg::Exit = app_event {
+                self.exit = true;
+            }
+        }
+    }
+
+    fn process_rcon_state_bus(&mut self) {
+        while let Ok(rcon_state) = self.rcon_state_bus_rx.try_recv() {
+            let mut lobby = self.shared_lobby.get();
+            lobby.rcon_state = rcon_state;
+            self.shared_lobby.set(lobby);
+        }
     }
 
     fn process_g15_bus(&mut self) {
@@ -96,10 +151,17 @@ impl LobbyThread {
         let now = Local::now();
         let mut lobby = self.shared_lobby.get();
 
+        // Names to add to the name history, and whether they were changed during the match
+        let mut names: Vec<(SteamID, String, bool)> = Vec::new();
+
         // Merge data from the G15 dump into the lobby
         for player in g15_dump.players.iter() {
             if let Some(lobby_player) = lobby.get_player_mut(None, Some(player.steamid)) {
                 // Player already exists in the lobby
+                if !lobby_player.name.is_empty() && lobby_player.name != player.name {
+                    log::info!("Player {} renamed to {}", lobby_player.name, player.name);
+                    names.push((player.steamid, lobby_player.name.clone(), true));
+                }
                 Self::merge_player_g15_data(lobby_player, player);
                 lobby_player.last_seen = now;
             } else {
@@ -108,6 +170,8 @@ impl LobbyThread {
                 let mut lobby_player = Player::default();
                 Self::merge_player_g15_data(&mut lobby_player, player);
                 lobby_player.last_seen = now;
+                lobby_player.encounter = self.load_encounter(player.steamid);
+                names.push((player.steamid, player.name.clone(), false));
 
                 lobby.players.push(lobby_player);
             };
@@ -128,9 +192,105 @@ impl LobbyThread {
         lobby.players = players_to_keep;
         lobby.recently_left_players.append(&mut players_to_move);
 
+        update_impersonators(&mut lobby.players);
+
+        self.encounters.record(&lobby, &g15_steamids, now);
+
+        for (steamid, name, mid_match) in names {
+            let Some(player) = lobby.get_player(None, Some(steamid)) else {
+                continue;
+            };
+            let current_name = player.name.clone();
+
+            if mid_match {
+                lobby.add_system_chat(
+                    now,
+                    steamid,
+                    &current_name,
+                    format!("{} changed their name to {}", name, current_name),
+                );
+            }
+
+            self.record_name(steamid, &current_name, &lobby.lobby_id, mid_match, now);
+        }
+
         self.shared_lobby.set(lobby);
     }
 
+    /// Adds the name to the name history when it differs from the last known name
+    fn record_name(
+        &self,
+        steamid: SteamID,
+        name: &str,
+        lobby_id: &str,
+        mid_match: bool,
+        when: DateTime<Local>,
+    ) {
+        if name.is_empty() {
+            return;
+        }
+
+        let new_name = NewNameHistory {
+            steam_id: steamid.to_u64() as i64,
+            name: name.to_string(),
+            source: NAME_SOURCE_GAME.to_string(),
+            seen: when.timestamp(),
+            lobby_id: Some(lobby_id.to_string()),
+            mid_match,
+        };
+
+        let result = self
+            .db
+            .get()
+            .map_err(|e| e.to_string())
+            .and_then(|mut conn| {
+                queries::record_name(&mut conn, &new_name).map_err(|e| e.to_string())
+            });
+        if let Err(e) = result {
+            log::error!("Failed to record name of {}: {}", steamid.to_u64(), e);
+        }
+    }
+
+    fn load_encounter(&self, steamid: SteamID) -> Option<Encounter> {
+        let mut conn = self.db.get().ok()?;
+        queries::get_encounter(&mut conn, steamid.to_u64() as i64).unwrap_or_else(|e| {
+            log::error!("Failed to load encounter with {}: {}", steamid.to_u64(), e);
+            None
+        })
+    }
+
+    /// Adds the encounters recorded since the last save to the database
+    fn save_encounters(&mut self) {
+        let pending = self.encounters.take_pending();
+        if pending.is_empty() {
+            return;
+        }
+
+        let mut conn = match self.db.get() {
+            Ok(conn) => conn,
+            Err(e) => {
+                log::error!("Failed to save {} encounters: {}", pending.len(), e);
+                return;
+            }
+        };
+
+        for (steamid, pending) in pending {
+            let steam_id = steamid.to_u64() as i64;
+            let result = queries::get_encounter(&mut conn, steam_id).and_then(|existing| {
+                let encounter = merge_encounter(steamid, existing.as_ref(), &pending);
+                queries::replace_encounter(&mut conn, &encounter)?;
+                queries::get_encounter(&mut conn, steam_id)
+            });
+
+            match result {
+                Ok(encounter) => self.shared_lobby.update_player(steamid, |player| {
+                    player.encounter = encounter;
+                }),
+                Err(e) => log::error!("Failed to save encounter with {}: {}", steam_id, e),
+            }
+        }
+    }
+
     fn merge_player_g15_data(lobby_player: &mut Player, player: &G15PlayerData) {
         lobby_player.steamid = player.steamid;
 
@@ -142,16 +302,28 @@ impl LobbyThread {
         lobby_player.alive = player.alive;
         lobby_player.ping_ms = player.ping_ms;
         lobby_player.health = player.health;
+        lobby_player.score = player.score;
     }
 
     fn process_tf2bd_bus(&mut self) {
         while let Ok(msg) = self.tf2bd_bus_rx.try_recv() {
             match msg {
-                Tf2bdMsg::Tf2bdPlayerMarking(steamid, player_info) => {
+                Tf2bdMsg::Tf2bdPlayerMarking(steamid, player_info, marking_sources) => {
                     self.shared_lobby.update_player(steamid, |player| {
                         player.player_info = player_info;
+                        player.marking_sources = marking_sources;
                     });
                 }
+                Tf2bdMsg::PlayerListErrors(errors) => {
+                    let mut lobby = self.shared_lobby.get();
+                    lobby.player_list_errors = errors;
+                    self.shared_lobby.set(lobby);
+                }
+                Tf2bdMsg::KickSchedule(kick_schedule) => {
+                    let mut lobby = self.shared_lobby.get();
+                    lobby.kick_schedule = kick_schedule;
+                    self.shared_lobby.set(lobby);
+                }
             }
         }
     }
@@ -221,7 +393,7 @@ impl LobbyThread {
                 } => self.kill(when, killer, victim, weapon, crit),
                 LogLine::Suicide { when, name } => self.suicide(when, name),
                 LogLine::LobbyCreated { when } => self.new_lobby(when),
-                LogLine::LobbyDestroyed { when: _when } => {}
+                LogLine::LobbyDestroyed { when } => self.end_session(when),
                 LogLine::Chat {
                     when,
                     name,
@@ -229,6 +401,42 @@ impl LobbyThread {
                     dead,
                     team,
                 } => self.chat(when, name, message, dead, team),
+                LogLine::ServerHostname { when, hostname } => {
+                    self.update_server(when, |server| server.hostname = Some(hostname))
+                }
+                LogLine::ServerAddress { when, address } => {
+                    self.update_server(when, |server| server.address = Some(address))
+                }
+                LogLine::ServerMap { when, map } => {
+                    self.update_server(when, |server| server.map = Some(map))
+                }
+                LogLine::ServerTags { when, tags } => {
+                    self.update_server(when, |server| server.tags = tags)
+                }
+                LogLine::ServerPlayerCount {
+                    when,
+                    humans,
+                    bots,
+                    max_players,
+                } => self.update_server(when, |server| {
+                    server.humans = Some(humans);
+                    server.bots = Some(bots);
+                    server.max_players = Some(max_players);
+                }),
+                LogLine::PlayerConnected { .. }
+                | LogLine::PlayerDisconnected { .. }
+                | LogLine::TeamSwitch { .. }
+                | LogLine::VoteStarted { .. }
+                | LogLine::VoteCast { .. }
+                | LogLine::VotePassed { .. }
+                | LogLine::VoteFailed { .. }
+                | LogLine::PointCaptured { .. }
+                | LogLine::Kicked { .. } => {
+                    let mut lobby = self.shared_lobby.get();
+                    update_votes(&mut lobby, &cmd);
+                    add_event(&mut lobby, cmd);
+                    self.shared_lobby.set(lobby);
+                }
             }
         }
     }
@@ -236,6 +444,9 @@ impl LobbyThread {
     fn new_lobby(&mut self, when: DateTime<Local>) {
         log::info!("*** Creating new lobby ***");
 
+        // The previous lobby ends when the new one starts, if it wasn't destroyed already
+        self.end_session(when);
+
         let mut lobby = self.shared_lobby.get();
 
         for player in lobby.players.iter_mut() {
@@ -256,11 +467,73 @@ impl LobbyThread {
         );
 
         lobby.chat_msg_id = 0;
-        lobby.lobby_id = Local::now().format("%Y-%m-%d").to_string();
+        lobby.lobby_id = new_lobby_id(when);
+        lobby.started = when;
 
         lobby.players.clear();
         lobby.chat.clear();
         lobby.kill_feed.clear();
+        lobby.events.clear();
+        lobby.votes.clear();
+        lobby.server = LobbyServer::default();
+        lobby.name_resolution = NameResolutionStats::default();
+
+        self.shared_lobby.set(lobby);
+    }
+
+    /// Stores the current lobby and its roster in the database
+    fn save_session(&mut self) {
+        self.last_session_save = Instant::now();
+        self.save_encounters();
+
+        let lobby = self.shared_lobby.get();
+        let result = self
+            .db
+            .get()
+            .map_err(|e| e.to_string())
+            .and_then(|mut conn| {
+                sessions::save_session(&mut conn, &lobby, Local::now().timestamp())
+                    .map_err(|e| e.to_string())
+            });
+        if let Err(e) = result {
+            log::error!("Failed to save match session {}: {}", lobby.lobby_id, e);
+        }
+    }
+
+    /// Stores the final roster of the lobby and when it ended
+    fn end_session(&mut self, when: DateTime<Local>) {
+        self.save_session();
+
+        let lobby_id = self.shared_lobby.get().lobby_id;
+        let result = self
+            .db
+            .get()
+            .map_err(|e| e.to_string())
+            .and_then(|mut conn| {
+                queries::end_match_session(&mut conn, &lobby_id, when.timestamp())
+                    .map_err(|e| e.to_string())
+            });
+        if let Err(e) = result {
+            log::error!("Failed to end match session {}: {}", lobby_id, e);
+        }
+    }
+
+    fn update_server(&mut self, when: DateTime<Local>, update: impl FnOnce(&mut LobbyServer)) {
+        let mut lobby = self.shared_lobby.get();
+
+        let previous_map = lobby.server.map.clone();
+        update(&mut lobby.server);
+        lobby.server.last_updated = Some(when);
+
+        if lobby.server.map != previous_map
+            && let Some(map) = &lobby.server.map
+        {
+            log::info!(
+                "Playing {} on {}",
+                map,
+                lobby.server.name().unwrap_or("unknown server")
+            );
+        }
 
         self.shared_lobby.set(lobby);
     }
@@ -275,11 +548,24 @@ impl LobbyThread {
     ) {
         let mut lobby = self.shared_lobby.get();
 
+        let resolved = resolve_kill(&lobby.players, &killer_name, &victim_name);
+        if resolved.killer.is_some() && resolved.victim.is_some() {
+            lobby.name_resolution.add_resolved(resolved.ambiguous);
+        } else {
+            lobby.name_resolution.add_unresolved(format!(
+                "{} killed {} with {}",
+                killer_name, victim_name, weapon
+            ));
+        }
+
         let mut killer_steamid = None;
         let mut victim_steamid = None;
 
         // Change the counts of kills to the killer player
-        if let Some(killer) = lobby.get_player_mut(Some(killer_name.as_str()), None) {
+        if let Some(killer) = resolved
+            .killer
+            .and_then(|steamid| lobby.get_player_mut(None, Some(steamid)))
+        {
             killer_steamid = Some(killer.steamid);
             killer.kills += 1;
             if crit {
@@ -290,11 +576,14 @@ impl LobbyThread {
                 crit,
             });
         } else {
-            log::warn!("Killer not found: '{}'", victim_name);
+            log::warn!("Killer not found: '{}'", killer_name);
         }
 
         // Change the counts of deaths to the victim player
-        if let Some(victim) = lobby.get_player_mut(Some(victim_name.as_str()), None) {
+        if let Some(victim) = resolved
+            .victim
+            .and_then(|steamid| lobby.get_player_mut(None, Some(steamid)))
+        {
             victim_steamid = Some(victim.steamid);
             victim.deaths += 1;
             if crit {
@@ -327,10 +616,17 @@ impl LobbyThread {
     fn suicide(&mut self, _when: DateTime<Local>, name: String) {
         let mut lobby = self.shared_lobby.get();
 
-        if let Some(player) = lobby.get_player_mut(Some(name.as_str()), None) {
+        let resolved = resolve_name(&lobby.players, &name, None);
+        if let Some((steamid, ambiguous)) = resolved
+            && let Some(player) = lobby.get_player_mut(None, Some(steamid))
+        {
             player.deaths += 1;
+            lobby.name_resolution.add_resolved(ambiguous);
         } else {
             log::warn!("Player not found: '{}'", name);
+            lobby
+                .name_resolution
+                .add_unresolved(format!("{} suicided", name));
         }
 
         self.shared_lobby.set(lobby);
@@ -346,24 +642,33 @@ impl LobbyThread {
     ) {
         let mut lobby = self.shared_lobby.get();
 
-        let (steamid, chat_msg_id) = {
-            if let Some(player) = lobby.get_player(Some(name.as_str()), None) {
-                (player.steamid, lobby.chat_msg_id)
-            } else {
-                log::warn!("Player not found: '{}'", name);
-                return;
-            }
+        // Team chat is only seen from the own team
+        let own_team = if team {
+            lobby.get_me().map(|me| me.team)
+        } else {
+            None
         };
 
+        let Some(resolved) = resolve_chat(&lobby.players, &name, &message, own_team) else {
+            log::warn!("Player not found: '{}'", name);
+            lobby
+                .name_resolution
+                .add_unresolved(format!("{} :  {}", name, message));
+            self.shared_lobby.set(lobby);
+            return;
+        };
+        lobby.name_resolution.add_resolved(resolved.ambiguous);
+
         lobby.chat.push(LobbyChat {
-            chat_msg_id,
+            chat_msg_id: lobby.chat_msg_id,
             when,
-            steamid,
-            player_name: name,
-            message: message.trim().to_string(),
+            steamid: resolved.steamid,
+            player_name: resolved.name,
+            message: resolved.message.trim().to_string(),
             translated_message: None,
             dead,
             team,
+            system: false,
         });
 
         lobby.chat_msg_id += 1;
@@ -451,3 +756,53 @@ impl LobbyThread {
         self.shared_lobby.set(lobby);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::db::connect_memory;
+
+    fn dump(name: &str) -> G15DumpPlayerOutput {
+        G15DumpPlayerOutput {
+            players: vec![G15PlayerData {
+                steamid: SteamID::from_u64(76561197960265729),
+                id: 2,
+                name: name.to_string(),
+                ..Default::default()
+            }],
+        }
+    }
+
+    #[test]
+    fn test_rename_mid_match() {
+        let db = connect_memory().unwrap();
+        let bus = Arc::new(Mutex::new(AppBus::default()));
+        let mut lobby_thread = LobbyThread::new(&AppSettings::default(), &bus, &db);
+
+        lobby_thread.process_g15_dump(dump("Old name"));
+        lobby_thread.process_g15_dump(dump("Old name"));
+        lobby_thread.process_g15_dump(dump("New name"));
+
+        let mut conn = db.get().unwrap();
+        let names: Vec<(String, bool)> = queries::get_name_history(&mut conn, 76561197960265729)
+            .unwrap()
+            .into_iter()
+            .map(|name| (name.name, name.mid_match))
+            .collect();
+        assert_eq!(
+            names,
+            vec![
+                ("Old name".to_string(), false),
+                ("New name".to_string(), true),
+            ]
+        );
+
+        let lobby = lobby_thread.shared_lobby.get();
+        assert_eq!(lobby.players[0].name, "New name");
+        assert!(
+            lobby.chat.iter().any(
+                |chat| chat.system && chat.message == "Old name changed their name to New name"
+            )
+        );
+    }
+}
@@ -1,33 +1,110 @@
+pub mod encounters;
+pub mod events;
 pub mod friendships;
+pub mod impersonation;
 pub mod lobby_thread;
+pub mod name_resolver;
+pub mod sessions;
 pub mod shared_lobby;
+pub mod votes;
 
+use super::rcon::RconState;
 use super::steamapi::{SteamPlayerBan, SteamProfileComment};
 use crate::{
+    db::entities::Encounter,
     models::steamid::SteamID,
     reputation::Reputation,
-    tf2bd::models::{PlayerAttribute, PlayerInfo},
+    tf2bd::{
+        MarkingSources,
+        kick_scheduler::KickSchedule,
+        models::{PlayerAttribute, PlayerInfo},
+    },
 };
 use chrono::{DateTime, Local};
 use friendships::Friendships;
-use std::collections::HashSet;
+use name_resolver::NameResolutionStats;
+use std::{
+    collections::HashSet,
+    sync::atomic::{AtomicU32, Ordering},
+};
+use votes::LobbyVote;
+
+/// Makes lobby IDs unique even when two lobbies are created in the same millisecond
+static LOBBY_COUNTER: AtomicU32 = AtomicU32::new(0);
 
 #[derive(Default, Debug, Clone)]
 pub struct Lobby {
+    /// Unique for each lobby, also the ID of the match session in the database
     pub lobby_id: String,
+
+    /// When the lobby was created, or when the app was started
+    pub started: DateTime<Local>,
     pub self_steamid: SteamID,
     pub players: Vec<Player>,
     pub chat: Vec<LobbyChat>,
     pub kill_feed: Vec<LobbyKill>,
+    pub events: Vec<LobbyEvent>,
+
+    /// The votes called in the lobby, the running vote last
+    pub votes: Vec<LobbyVote>,
+
+    /// Who the kicker will call votes on and when, from the TF2BD thread
+    pub kick_schedule: KickSchedule,
     pub friendships: Friendships,
 
+    /// The server of the lobby, from the status command output in the console log
+    pub server: LobbyServer,
+
     /// Players who no longer show up in the status command output
     /// or in tf_lobby_debug output. Players are kept in here for 1 minute.
     pub recently_left_players: Vec<Player>,
 
+    /// Problems loading the TF2BD player lists, shown in the status row
+    pub player_list_errors: Vec<String>,
+
+    /// How well the names in kill and chat lines matched the players, shown in the status row
+    pub name_resolution: NameResolutionStats,
+
+    /// The RCON connection to TF2, from the RCON thread
+    pub rcon_state: RconState,
+
     chat_msg_id: i64,
 }
 
+#[derive(Default, Debug, Clone, PartialEq, Eq)]
+pub struct LobbyServer {
+    /// e.g. "Valve Matchmaking Server (Frankfurt srcds101-fra2 #70)"
+    pub hostname: Option<String>,
+
+    /// IP and port, a relay address on Valve servers
+    pub address: Option<String>,
+    pub map: Option<String>,
+    pub tags: Vec<String>,
+    pub humans: Option<u32>,
+    pub bots: Option<u32>,
+    pub max_players: Option<u32>,
+
+    /// When the status header was last seen
+    pub last_updated: Option<DateTime<Local>>,
+}
+
+impl LobbyServer {
+    /// The Valve datacenter, e.g. "Frankfurt", from the hostname of a Valve server
+    pub fn datacenter(&self) -> Option<&str> {
+        let hostname = self.hostname.as_deref()?;
+        let details = hostname
+            .strip_prefix("Valve Matchmaking Server (")?
+            .strip_suffix(')')?;
+        let (datacenter, _) = details.split_once(" srcds")?;
+        Some(datacenter)
+    }
+
+    /// How the server is shown to the user, the hostname or else the address
+    pub fn name(&self) -> Option<&str> {
+        self.hostname.as_deref().or(self.address.as_deref())
+    }
+}
+
 #[derive(Default, Debug, Clone)]
 pub struct LobbyKill {
     pub when: DateTime<Local>,
@@ -37,6 +114,30 @@ pub struct LobbyKill {
     pub crit: bool,
 }
 
+/// Something that happened in the lobby besides kills and chat, shown in the timeline
+#[derive(Debug, Clone)]
+pub struct LobbyEvent {
+    pub when: DateTime<Local>,
+    pub kind: LobbyEventKind,
+
+    /// The player the event is about, when the name matched a player in the lobby
+    pub steamid: Option<SteamID>,
+    pub text: String,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum LobbyEventKind {
+    Connected,
+    Disconnected,
+    TeamSwitch,
+    Vote,
+    /// A vote to kick us
+    VoteAgainstMe,
+    Capture,
+    /// We were kicked from the server
+    Kicked,
+}
+
 #[derive(Default, Debug, Clone)]
 pub struct LobbyChat {
     pub chat_msg_id: i64,
@@ -52,6 +153,10 @@ pub struct LobbyChat {
     pub translated_message: Option<String>,
     pub dead: bool,
     pub team: bool,
+
+    /// Written by TF2Monitor about the player, e.g. when they change their name.
+    /// Not something the player said, so it is not matched by rules.
+    pub system: bool,
 }
 
 #[derive(Default, Debug, Clone, PartialEq, Eq)]
@@ -95,6 +200,7 @@ pub struct Player {
     pub alive: bool,
     pub ping_ms: i64,
     pub health: Option<u32>,
+    pub score: Option<u32>,
     pub kills: u32,
     pub deaths: u32,
     pub crit_kills: u32,
@@ -113,10 +219,18 @@ pub struct Player {
 
     pub account_age: AccountAge,
 
-    // This is the PlayerFlags(Cheater etc) for the player
-    // The String is the source of the flags.
-    // The source is filename of the rules file that set the flags.
+    /// How often we have met the player, loaded from the database when they join
+    pub encounter: Option<Encounter>,
+
+    /// The player that this player looks like a copy of, see impersonation::update_impersonators
+    pub impersonating: Option<SteamID>,
+
+    // This is the PlayerFlags(Cheater etc) for the player from trusted lists
     pub player_info: Option<PlayerInfo>,
+
+    // The source of each of the flags, both from trusted and suggestion lists.
+    // The source is filename of the rules file or the description of the rule that set the flag.
+    pub marking_sources: MarkingSources,
 }
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Default)]
@@ -142,6 +256,9 @@ pub struct PlayerSteamInfo {
     pub avatar_thumb: String,
     // pub avatarmedium: String,
     pub avatar_full: String,
+
+    /// The hash of the avatar, as used in the avatar_match rules of TF2BD
+    pub avatar_hash: Option<String>,
     pub account_age: Option<DateTime<Local>>,
 }
 
@@ -229,6 +346,15 @@ impl Player {
         None
     }
 
+    /// Attributes that only come from suggestion lists
+    pub fn suggested_attributes(&self) -> Vec<PlayerAttribute> {
+        self.marking_sources
+            .iter()
+            .filter(|(_, source)| source.suggestion)
+            .map(|(attribute, _)| *attribute)
+            .collect()
+    }
+
     pub fn is_newbie(&self) -> Option<String> {
         let mut is_new_account = false;
         if let Some(steam_info) = &self.steam_info {
@@ -284,13 +410,21 @@ impl Lobby {
     pub fn new(self_steamid: SteamID) -> Self {
         Self {
             chat_msg_id: 0,
-            lobby_id: Local::now().format("%Y-%m-%d").to_string(),
+            lobby_id: new_lobby_id(Local::now()),
+            started: Local::now(),
             self_steamid,
             players: Vec::new(),
             chat: Vec::new(),
             kill_feed: Vec::new(),
+            events: Vec::new(),
+            votes: Vec::new(),
+            kick_schedule: KickSchedule::default(),
             friendships: Friendships::default(),
+            server: LobbyServer::default(),
             recently_left_players: Vec::new(),
+            player_list_errors: Vec::new(),
+            name_resolution: NameResolutionStats::default(),
+            rcon_state: RconState::default(),
         }
     }
 
@@ -314,11 +448,40 @@ impl Lobby {
             .find(|player| Some(player.name.as_str()) == name || Some(player.steamid) == steamid)
     }
 
+    /// Adds a message about a player to the chat
+    pub fn add_system_chat(
+        &mut self,
+        when: DateTime<Local>,
+        steamid: SteamID,
+        player_name: &str,
+        message: String,
+    ) {
+        self.chat.push(LobbyChat {
+            chat_msg_id: self.chat_msg_id,
+            when,
+            steamid,
+            player_name: player_name.to_string(),
+            // Nothing to translate
+            translated_message: Some(message.clone()),
+            message,
+            dead: false,
+            team: false,
+            system: true,
+        });
+        self.chat_msg_id += 1;
+    }
+
     fn update_friendships(&mut self) {
         self.friendships = Friendships::from_lobby(self);
     }
 }
 
+/// A new lobby ID, e.g. "20240506-170550.123-1"
+pub fn new_lobby_id(when: DateTime<Local>) -> String {
+    let counter = LOBBY_COUNTER.fetch_add(1, Ordering::Relaxed);
+    format!("{}-{}", when.format("%Y%m%d-%H%M%S%.3f"), counter)
+}
+
 /// Returns text and tooltip for a player attribute
 pub fn player_attribute_description(
     player_attribute: PlayerAttribute,
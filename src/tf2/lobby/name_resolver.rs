@@ -0,0 +1,312 @@
+use super::{Player, Team};
+use crate::models::steamid::SteamID;
+
+const KILLED_SEPARATOR: &str = " killed ";
+const CHAT_SEPARATOR: &str = " :  ";
+
+/// The number of unresolved lines kept for the status row
+const MAX_UNRESOLVED_LINES: usize = 10;
+
+/// How well the names in the console log lines matched the players in the lobby
+#[derive(Debug, Default, Clone)]
+pub struct NameResolutionStats {
+    /// Lines where all names matched a player
+    pub resolved: u32,
+
+    /// Lines where a name matched several players, or the line could be split several ways
+    pub ambiguous: u32,
+
+    /// Lines with a name that did not match any player
+    pub unresolved: u32,
+
+    /// The last few unresolved lines, latest last
+    pub unresolved_lines: Vec<String>,
+}
+
+impl NameResolutionStats {
+    pub fn add_resolved(&mut self, ambiguous: bool) {
+        self.resolved += 1;
+        if ambiguous {
+            self.ambiguous += 1;
+        }
+    }
+
+    pub fn add_unresolved(&mut self, line: String) {
+        self.unresolved += 1;
+        self.unresolved_lines.push(line);
+        if self.unresolved_lines.len() > MAX_UNRESOLVED_LINES {
+            self.unresolved_lines.remove(0);
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResolvedKill {
+    pub killer: Option<SteamID>,
+    pub victim: Option<SteamID>,
+    pub ambiguous: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResolvedChat {
+    pub steamid: SteamID,
+    pub name: String,
+    pub message: String,
+    pub ambiguous: bool,
+}
+
+/// The players with exactly this name, the last one to join first
+fn candidates<'a>(players: &'a [Player], name: &str) -> Vec<&'a Player> {
+    let mut candidates: Vec<&Player> = players.iter().filter(|p| p.name == name).collect();
+    candidates.sort_by_key(|p| std::cmp::Reverse(p.id));
+    candidates
+}
+
+/// Picks one of the players with the same name, preferring players in the given team
+/// and then the last one to join. Returns the player and whether there was more than one choice.
+fn pick(candidates: &[&Player], team: Option<Team>) -> Option<(SteamID, bool)> {
+    let in_team: Vec<&Player> = candidates
+        .iter()
+        .filter(|p| Some(p.team) == team)
+        .copied()
+        .collect();
+    let preferred = if in_team.is_empty() {
+        candidates
+    } else {
+        &in_team
+    };
+
+    preferred.first().map(|p| (p.steamid, preferred.len() > 1))
+}
+
+/// Finds the player with the name, see pick() for how players with the same name are handled
+pub fn resolve_name(players: &[Player], name: &str, team: Option<Team>) -> Option<(SteamID, bool)> {
+    pick(&candidates(players, name), team)
+}
+
+/// Finds the killer and the victim of a kill line. The parser splits the line at the first " killed ",
+/// which is wrong when the killer has " killed " in the name, so all the splits of the line
+/// are tried against the names of the players in the lobby.
+pub fn resolve_kill(players: &[Player], killer: &str, victim: &str) -> ResolvedKill {
+    let text = format!("{}{}{}", killer, KILLED_SEPARATOR, victim);
+
+    // The splits of the line where both sides are names of players in the lobby
+    let splits: Vec<(Vec<&Player>, Vec<&Player>)> = text
+        .match_indices(KILLED_SEPARATOR)
+        .map(|(i, _)| {
+            (
+                candidates(players, &text[..i]),
+                candidates(players, &text[i + KILLED_SEPARATOR.len()..]),
+            )
+        })
+        .filter(|(killers, victims)| !killers.is_empty() && !victims.is_empty())
+        .collect();
+
+    let Some((first_killers, first_victims)) = splits.first() else {
+        // Resolve what we can with the split of the parser
+        let killer = resolve_name(players, killer, None);
+        let victim = resolve_name(players, victim, None);
+        return ResolvedKill {
+            killer: killer.map(|(steamid, _)| steamid),
+            victim: victim.map(|(steamid, _)| steamid),
+            ambiguous: killer.is_some_and(|(_, ambiguous)| ambiguous)
+                || victim.is_some_and(|(_, ambiguous)| ambiguous),
+        };
+    };
+
+    let mut pairs: Vec<(&Player, &Player)> = Vec::new();
+    for (killers, victims) in splits.iter() {
+        for killer in killers {
+            for victim in victims {
+                if killer.steamid != victim.steamid {
+                    pairs.push((killer, victim));
+                }
+            }
+        }
+    }
+
+    // The killer and the victim are in different teams
+    let opposing: Vec<(&Player, &Player)> = pairs
+        .iter()
+        .filter(|(killer, victim)| killer.team != victim.team)
+        .copied()
+        .collect();
+    let preferred = if opposing.is_empty() {
+        &pairs
+    } else {
+        &opposing
+    };
+
+    match preferred.first() {
+        Some((killer, victim)) => ResolvedKill {
+            killer: Some(killer.steamid),
+            victim: Some(victim.steamid),
+            ambiguous: preferred.len() > 1,
+        },
+        // Only one player has the name on both sides
+        None => ResolvedKill {
+            killer: Some(first_killers[0].steamid),
+            victim: Some(first_victims[0].steamid),
+            ambiguous: false,
+        },
+    }
+}
+
+/// Finds the player who wrote a chat message. The parser splits the line at the first " :  ",
+/// so names containing it are tried against the players in the lobby.
+/// Team chat is only seen from the own team, which is given as the team.
+pub fn resolve_chat(
+    players: &[Player],
+    name: &str,
+    message: &str,
+    team: Option<Team>,
+) -> Option<ResolvedChat> {
+    let text = format!("{}{}{}", name, CHAT_SEPARATOR, message);
+
+    let splits: Vec<(usize, Vec<&Player>)> = text
+        .match_indices(CHAT_SEPARATOR)
+        .map(|(i, _)| (i, candidates(players, &text[..i])))
+        .filter(|(_, candidates)| !candidates.is_empty())
+        .collect();
+
+    let (i, candidates) = splits.first()?;
+    let (steamid, ambiguous) = pick(candidates, team)?;
+
+    Some(ResolvedChat {
+        steamid,
+        name: text[..*i].to_string(),
+        message: text[i + CHAT_SEPARATOR.len()..].to_string(),
+        ambiguous: ambiguous || splits.len() > 1,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn player(id: i64, steamid: u64, name: &str, team: Team) -> Player {
+        Player {
+            id,
+            steamid: SteamID::from_u64(steamid),
+            name: name.to_string(),
+            team,
+            ..Default::default()
+        }
+    }
+
+    fn kill(players: &[Player], killer: &str, victim: &str) -> (Option<u64>, Option<u64>, bool) {
+        let resolved = resolve_kill(players, killer, victim);
+        (
+            resolved.killer.map(|s| s.to_u64()),
+            resolved.victim.map(|s| s.to_u64()),
+            resolved.ambiguous,
+        )
+    }
+
+    #[test]
+    fn test_resolve_kill() {
+        let players = vec![
+            player(2, 1, "1Pleaseburger", Team::Red),
+            player(3, 2, "used to facetime with your mom", Team::Blue),
+            player(4, 3, "Player1", Team::Red),
+            player(5, 4, "Player1 killed Player2", Team::Red),
+            player(6, 5, "Player2", Team::Blue),
+        ];
+
+        assert_eq!(
+            kill(&players, "1Pleaseburger", "used to facetime with your mom"),
+            (Some(1), Some(2), false)
+        );
+
+        // The parser splits at the first " killed "
+        assert_eq!(
+            kill(&players, "Player1", "Player2 killed 1Pleaseburger"),
+            (Some(4), Some(1), false)
+        );
+
+        // Only one split has a player name on both sides
+        assert_eq!(
+            kill(&players, "Player1", "Player2"),
+            (Some(3), Some(5), false)
+        );
+
+        assert_eq!(kill(&players, "Nobody", "Player2"), (None, Some(5), false));
+    }
+
+    #[test]
+    fn test_resolve_kill_duplicate_names() {
+        let players = vec![
+            player(2, 1, "used to facetime with your mom", Team::Red),
+            player(7, 2, "used to facetime with your mom", Team::Blue),
+            player(3, 3, "Player1", Team::Red),
+            player(4, 4, "Player2", Team::Blue),
+        ];
+
+        // The killer is in the other team than the victim
+        assert_eq!(
+            kill(&players, "used to facetime with your mom", "Player1"),
+            (Some(2), Some(3), false)
+        );
+        assert_eq!(
+            kill(&players, "used to facetime with your mom", "Player2"),
+            (Some(1), Some(4), false)
+        );
+
+        // The two players with the same name kill each other
+        assert_eq!(
+            kill(
+                &players,
+                "used to facetime with your mom",
+                "used to facetime with your mom"
+            ),
+            (Some(2), Some(1), true)
+        );
+    }
+
+    #[test]
+    fn test_resolve_chat() {
+        let players = vec![
+            player(2, 1, "Player1", Team::Red),
+            player(3, 2, "A :  B", Team::Blue),
+            player(4, 3, "Twin", Team::Red),
+            player(5, 4, "Twin", Team::Blue),
+        ];
+
+        let resolved = resolve_chat(&players, "Player1", "hello :  there", None).unwrap();
+        assert_eq!(resolved.steamid.to_u64(), 1);
+        assert_eq!(resolved.message, "hello :  there");
+        assert!(!resolved.ambiguous);
+
+        let resolved = resolve_chat(&players, "A", "B :  hi", None).unwrap();
+        assert_eq!(resolved.steamid.to_u64(), 2);
+        assert_eq!(resolved.name, "A :  B");
+        assert_eq!(resolved.message, "hi");
+
+        // Team chat is from the own team, otherwise the last one to join
+        let resolved = resolve_chat(&players, "Twin", "gg", Some(Team::Red)).unwrap();
+        assert_eq!(resolved.steamid.to_u64(), 3);
+        assert!(!resolved.ambiguous);
+        let resolved = resolve_chat(&players, "Twin", "gg", None).unwrap();
+        assert_eq!(resolved.steamid.to_u64(), 4);
+        assert!(resolved.ambiguous);
+
+        assert!(resolve_chat(&players, "Nobody", "hi", None).is_none());
+    }
+
+    #[test]
+    fn test_name_resolution_stats() {
+        let mut stats = NameResolutionStats::default();
+        stats.add_resolved(false);
+        stats.add_resolved(true);
+        for i in 0..MAX_UNRESOLVED_LINES + 2 {
+            stats.add_unresolved(format!("line {}", i));
+        }
+
+        assert_eq!(
+            (stats.resolved, stats.ambiguous, stats.unresolved),
+            (2, 1, 12)
+        );
+        assert_eq!(stats.unresolved_lines.len(), MAX_UNRESOLVED_LINES);
+        assert_eq!(stats.unresolved_lines[0], "line 2");
+    }
+}
@@ -0,0 +1,137 @@
+//
+// Match sessions: each lobby is stored in the database with its server, map and roster,
+// so it can be seen later who we played with and when.
+//
+
+use super::Lobby;
+use crate::db::{
+    entities::{NewMatchSession, NewMatchSessionPlayer},
+    queries,
+};
+use diesel::{Connection, SqliteConnection};
+
+/// The session and roster of the lobby as they are stored in the database
+pub fn session_records(lobby: &Lobby, now: i64) -> (NewMatchSession, Vec<NewMatchSessionPlayer>) {
+    let session = NewMatchSession {
+        lobby_id: lobby.lobby_id.clone(),
+        started: lobby.started.timestamp(),
+        map: lobby.server.map.clone(),
+        server: lobby.server.hostname.clone(),
+        address: lobby.server.address.clone(),
+    };
+
+    let players = lobby
+        .players
+        .iter()
+        .filter(|player| player.steamid != lobby.self_steamid)
+        .map(|player| NewMatchSessionPlayer {
+            lobby_id: lobby.lobby_id.clone(),
+            steam_id: player.steamid.to_u64() as i64,
+            name: player.name.clone(),
+            team: format!("{:?}", player.team),
+            kills: player.kills as i32,
+            deaths: player.deaths as i32,
+            flags: player
+                .player_info
+                .as_ref()
+                .map(|player_info| {
+                    player_info
+                        .attributes
+                        .iter()
+                        .map(|attribute| format!("{:?}", attribute))
+                        .collect::<Vec<_>>()
+                        .join(",")
+                })
+                .unwrap_or_default(),
+            first_seen: now,
+            last_seen: player.last_seen.timestamp(),
+        })
+        .collect();
+
+    (session, players)
+}
+
+/// Stores the lobby and the players currently in it. Players who left stay in the roster
+/// with their stats from when they were last stored. An empty lobby is not stored.
+pub fn save_session(
+    conn: &mut SqliteConnection,
+    lobby: &Lobby,
+    now: i64,
+) -> Result<(), diesel::result::Error> {
+    let (session, players) = session_records(lobby, now);
+    if players.is_empty() {
+        return Ok(());
+    }
+
+    conn.transaction(|conn| {
+        queries::upsert_match_session(conn, &session)?;
+        for player in &players {
+            queries::upsert_match_session_player(conn, player)?;
+        }
+        Ok(())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        models::steamid::SteamID,
+        tf2::lobby::{Player, Team, new_lobby_id},
+        tf2bd::models::{PlayerAttribute, PlayerInfo},
+    };
+    use chrono::{Local, TimeZone};
+
+    #[test]
+    fn test_session_records() {
+        let me = SteamID::from_u64(76561198012345678);
+        let other = SteamID::from_u64(76561197960287930);
+        let when = Local.with_ymd_and_hms(2024, 5, 6, 17, 5, 50).unwrap();
+
+        let mut lobby = Lobby::new(me);
+        lobby.started = when;
+        lobby.server.map = Some("pl_badwater".to_string());
+        lobby.server.hostname =
+            Some("Valve Matchmaking Server (Frankfurt srcds101-fra2 #70)".to_string());
+        lobby.players.push(Player {
+            steamid: me,
+            ..Default::default()
+        });
+        lobby.players.push(Player {
+            steamid: other,
+            name: "Player1".to_string(),
+            team: Team::Blue,
+            kills: 7,
+            deaths: 2,
+            last_seen: when,
+            player_info: Some(PlayerInfo {
+                attributes: vec![PlayerAttribute::Cheater, PlayerAttribute::Bot],
+                ..Default::default()
+            }),
+            ..Default::default()
+        });
+
+        let (session, players) = session_records(&lobby, 1000);
+        assert_eq!(session.lobby_id, lobby.lobby_id);
+        assert_eq!(session.started, when.timestamp());
+        assert_eq!(session.map.as_deref(), Some("pl_badwater"));
+
+        // We are not in our own roster
+        assert_eq!(players.len(), 1);
+        let player = &players[0];
+        assert_eq!(player.steam_id, 76561197960287930);
+        assert_eq!(player.team, "Blue");
+        assert_eq!((player.kills, player.deaths), (7, 2));
+        assert_eq!(player.flags, "Cheater,Bot");
+        assert_eq!(
+            (player.first_seen, player.last_seen),
+            (1000, when.timestamp())
+        );
+    }
+
+    #[test]
+    fn test_lobby_ids_are_unique() {
+        let when = Local::now();
+        assert_ne!(new_lobby_id(when), new_lobby_id(when));
+    }
+}
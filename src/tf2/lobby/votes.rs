@@ -0,0 +1,246 @@
+use super::Lobby;
+use super::name_resolver::resolve_name;
+use crate::models::steamid::SteamID;
+use crate::tf2::logfile::LogLine;
+use chrono::{DateTime, Local};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum VoteResult {
+    Passed,
+    Failed(String),
+}
+
+/// A vote called in the lobby, tracked from the vote lines in the console log
+#[derive(Debug, Clone)]
+pub struct LobbyVote {
+    pub started: DateTime<Local>,
+    pub caller_name: String,
+    pub caller: Option<SteamID>,
+
+    /// e.g. "Kick Player1 (cheating)"
+    pub issue: String,
+
+    /// The player to kick, for kick votes
+    pub target: Option<SteamID>,
+
+    pub yes: u32,
+    pub no: u32,
+
+    /// How we voted, None until our vote shows up in the console
+    pub my_vote: Option<bool>,
+
+    /// None while the vote is running
+    pub result: Option<VoteResult>,
+    pub ended: Option<DateTime<Local>>,
+}
+
+impl LobbyVote {
+    pub fn is_active(&self) -> bool {
+        self.result.is_none()
+    }
+
+    fn end(&mut self, when: DateTime<Local>, result: VoteResult) {
+        self.result = Some(result);
+        self.ended = Some(when);
+    }
+}
+
+impl Lobby {
+    /// The vote that is running now, if any
+    pub fn active_vote(&self) -> Option<&LobbyVote> {
+        self.votes.last().filter(|vote| vote.is_active())
+    }
+
+    /// When the last kick vote on the player failed
+    pub fn last_failed_kick_vote(&self, steamid: SteamID) -> Option<DateTime<Local>> {
+        self.votes
+            .iter()
+            .rev()
+            .filter(|vote| vote.target == Some(steamid))
+            .find(|vote| matches!(vote.result, Some(VoteResult::Failed(_))))
+            .and_then(|vote| vote.ended)
+    }
+}
+
+/// Updates the votes of the lobby with a vote line from the console log.
+/// Other lines are ignored.
+pub fn update_votes(lobby: &mut Lobby, line: &LogLine) {
+    match line {
+        LogLine::VoteStarted {
+            when,
+            caller,
+            issue,
+            kick_target,
+        } => {
+            // Only one vote runs at a time, so a vote without a result line has ended
+            if let Some(vote) = lobby.votes.last_mut()
+                && vote.is_active()
+            {
+                vote.end(*when, VoteResult::Failed("No result seen".to_string()));
+            }
+
+            let target = kick_target
+                .as_deref()
+                .and_then(|target| resolve_name(&lobby.players, target, None))
+                .map(|(steamid, _)| steamid);
+
+            lobby.votes.push(LobbyVote {
+                started: *when,
+                caller_name: caller.clone(),
+                caller: resolve_name(&lobby.players, caller, None).map(|(steamid, _)| steamid),
+                issue: issue.clone(),
+                target,
+                yes: 0,
+                no: 0,
+                my_vote: None,
+                result: None,
+                ended: None,
+            });
+        }
+        LogLine::VoteCast { name, yes, .. } => {
+            let voter = resolve_name(&lobby.players, name, None).map(|(steamid, _)| steamid);
+            let is_me = voter == Some(lobby.self_steamid);
+
+            let Some(vote) = lobby.votes.last_mut().filter(|vote| vote.is_active()) else {
+                log::warn!("Vote cast by {} without a running vote", name);
+                return;
+            };
+
+            if *yes {
+                vote.yes += 1;
+            } else {
+                vote.no += 1;
+            }
+            if is_me {
+                vote.my_vote = Some(*yes);
+            }
+        }
+        LogLine::VotePassed { when, .. } => {
+            if let Some(vote) = lobby.votes.last_mut().filter(|vote| vote.is_active()) {
+                vote.end(*when, VoteResult::Passed);
+            }
+        }
+        LogLine::VoteFailed { when, reason } => {
+            if let Some(vote) = lobby.votes.last_mut().filter(|vote| vote.is_active()) {
+                vote.end(*when, VoteResult::Failed(reason.clone()));
+            }
+        }
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tf2::lobby::Player;
+    use chrono::TimeZone;
+
+    fn lobby() -> Lobby {
+        let me = SteamID::from_u64(76561198012345678);
+        let mut lobby = Lobby::new(me);
+        for (id, steamid, name) in [
+            (1, me, "Me"),
+            (2, SteamID::from_u64(76561198000000001), "Player1"),
+            (3, SteamID::from_u64(76561198000000002), "Bot"),
+        ] {
+            lobby.players.push(Player {
+                id,
+                steamid,
+                name: name.to_string(),
+                ..Default::default()
+            });
+        }
+        lobby
+    }
+
+    fn vote_started(when: DateTime<Local>, caller: &str, target: &str) -> LogLine {
+        LogLine::VoteStarted {
+            when,
+            caller: caller.to_string(),
+            issue: format!("Kick {} (cheating)", target),
+            kick_target: Some(target.to_string()),
+        }
+    }
+
+    fn vote_cast(when: DateTime<Local>, name: &str, yes: bool) -> LogLine {
+        LogLine::VoteCast {
+            when,
+            name: name.to_string(),
+            yes,
+        }
+    }
+
+    #[test]
+    fn test_vote_counts_and_result() {
+        let when = Local.with_ymd_and_hms(2024, 5, 6, 17, 2, 55).unwrap();
+        let mut lobby = lobby();
+        let bot = lobby.players[2].steamid;
+
+        update_votes(&mut lobby, &vote_started(when, "Player1", "Bot"));
+        update_votes(&mut lobby, &vote_cast(when, "Player1", true));
+        update_votes(&mut lobby, &vote_cast(when, "Me", true));
+        update_votes(&mut lobby, &vote_cast(when, "Bot", false));
+
+        let vote = lobby.active_vote().unwrap();
+        assert_eq!((vote.yes, vote.no, vote.my_vote), (2, 1, Some(true)));
+        assert_eq!(vote.caller, Some(lobby.players[1].steamid));
+        assert_eq!(vote.target, Some(bot));
+
+        update_votes(
+            &mut lobby,
+            &LogLine::VotePassed {
+                when,
+                issue: "Kick Bot (cheating)".to_string(),
+                kick_target: Some("Bot".to_string()),
+            },
+        );
+        assert!(lobby.active_vote().is_none());
+        assert_eq!(lobby.votes[0].result, Some(VoteResult::Passed));
+        assert_eq!(lobby.last_failed_kick_vote(bot), None);
+    }
+
+    #[test]
+    fn test_failed_kick_vote() {
+        let when = Local.with_ymd_and_hms(2024, 5, 6, 17, 2, 55).unwrap();
+        let later = Local.with_ymd_and_hms(2024, 5, 6, 17, 3, 30).unwrap();
+        let mut lobby = lobby();
+        let bot = lobby.players[2].steamid;
+
+        update_votes(&mut lobby, &vote_started(when, "Me", "Bot"));
+        update_votes(
+            &mut lobby,
+            &LogLine::VoteFailed {
+                when: later,
+                reason: "Not enough players voted".to_string(),
+            },
+        );
+
+        assert_eq!(lobby.last_failed_kick_vote(bot), Some(later));
+        assert_eq!(
+            lobby.votes[0].result,
+            Some(VoteResult::Failed("Not enough players voted".to_string()))
+        );
+
+        // A vote cast after the vote ended is not counted
+        update_votes(&mut lobby, &vote_cast(later, "Player1", true));
+        assert_eq!(lobby.votes[0].yes, 0);
+    }
+
+    #[test]
+    fn test_vote_without_result() {
+        let when = Local.with_ymd_and_hms(2024, 5, 6, 17, 2, 55).unwrap();
+        let later = Local.with_ymd_and_hms(2024, 5, 6, 17, 5, 0).unwrap();
+        let mut lobby = lobby();
+
+        update_votes(&mut lobby, &vote_started(when, "Player1", "Bot"));
+        update_votes(&mut lobby, &vote_started(later, "Bot", "Player1"));
+
+        assert_eq!(lobby.votes.len(), 2);
+        assert_eq!(lobby.votes[0].ended, Some(later));
+        assert!(!lobby.votes[0].is_active());
+        assert_eq!(
+            lobby.active_vote().unwrap().target,
+            Some(lobby.players[1].steamid)
+        );
+    }
+}
@@ -8,6 +8,17 @@ pub struct LogLineParser {
     killed_rx: Regex,
     suicided_rx: Regex,
     chat_rx: Regex,
+    server_info_rx: Regex,
+    player_count_rx: Regex,
+    connected_rx: Regex,
+    disconnected_rx: Regex,
+    team_switch_rx: Regex,
+    vote_started_rx: Regex,
+    vote_cast_rx: Regex,
+    vote_result_rx: Regex,
+    kick_issue_rx: Regex,
+    captured_rx: Regex,
+    kicked_rx: Regex,
 }
 
 impl Default for LogLineParser {
@@ -22,6 +33,21 @@ impl LogLineParser {
             killed_rx: Regex::new(r"^(.+?) killed (.+) with (.+?)(\.|\. \(crit\))$").unwrap(),
             suicided_rx: Regex::new(r"^(.+?) suicided.$").unwrap(),
             chat_rx: Regex::new(r"^(.+?) :  (.+)$").unwrap(),
+            server_info_rx: Regex::new(r"^(hostname|udp/ip|map|tags|players) *: (.*)$").unwrap(),
+            player_count_rx: Regex::new(r"^(\d+) humans?, (\d+) bots? \((\d+) max\)").unwrap(),
+            connected_rx: Regex::new(r"^(.+) connected$").unwrap(),
+            disconnected_rx: Regex::new(r"^Dropped (.+) from server \((.*)\)$").unwrap(),
+            team_switch_rx: Regex::new(
+                r"^Player (.+) (?:joined team|was automatically assigned to team) (RED|BLU|Spectator)$",
+            )
+            .unwrap(),
+            vote_started_rx: Regex::new(r"^(.+?) called a vote: (.+)$").unwrap(),
+            vote_cast_rx: Regex::new(r"^(.+) voted (Yes|No)$").unwrap(),
+            vote_result_rx: Regex::new(r"^Vote (passed|failed): (.+)$").unwrap(),
+            kick_issue_rx: Regex::new(r"^Kick (.+?)(?: \((?:cheating|idle|scamming|other)\))?$")
+                .unwrap(),
+            captured_rx: Regex::new(r"^(.+) captured (.+) for team #(\d+)$").unwrap(),
+            kicked_rx: Regex::new(r"^Disconnect: (.*(?:[Kk]icked|voted off).*)$").unwrap(),
         }
     }
 
@@ -48,6 +74,17 @@ impl LogLineParser {
             if logobj.is_some() {
                 return logobj;
             }
+
+            // After chat, so a player can't fake the server info with their name
+            let logobj = self.parse_server_info_line(when, line);
+            if logobj.is_some() {
+                return logobj;
+            }
+
+            let logobj = self.parse_event_line(when, line);
+            if logobj.is_some() {
+                return logobj;
+            }
         }
 
         None
@@ -133,6 +170,141 @@ impl LogLineParser {
         }
     }
 
+    /// Parses the lines of the status command header that identify the server
+    pub fn parse_server_info_line(&self, when: DateTime<Local>, line: &str) -> Option<LogLine> {
+        let caps = self.server_info_rx.captures(line)?;
+        let value = caps.get(2).unwrap().as_str().trim();
+
+        match caps.get(1).unwrap().as_str() {
+            "hostname" => Some(LogLine::ServerHostname {
+                when,
+                hostname: value.to_string(),
+            }),
+            "udp/ip" => {
+                // 0.0.0.0:27015  (public ip: 1.2.3.4) on community servers
+                let address = value.split_whitespace().next()?.to_string();
+                Some(LogLine::ServerAddress { when, address })
+            }
+            "map" => {
+                let map = value.split(" at: ").next()?.trim().to_string();
+                Some(LogLine::ServerMap { when, map })
+            }
+            "tags" => Some(LogLine::ServerTags {
+                when,
+                tags: value
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|x| !x.is_empty())
+                    .map(str::to_string)
+                    .collect(),
+            }),
+            "players" => {
+                let caps = self.player_count_rx.captures(value)?;
+                Some(LogLine::ServerPlayerCount {
+                    when,
+                    humans: caps.get(1).unwrap().as_str().parse().ok()?,
+                    bots: caps.get(2).unwrap().as_str().parse().ok()?,
+                    max_players: caps.get(3).unwrap().as_str().parse().ok()?,
+                })
+            }
+            _ => None,
+        }
+    }
+
+    /// Parses the lines about players coming and going, votes, captures and us being kicked.
+    /// The lines that start with a player name are matched first, by their fixed ending,
+    /// so a name like "Vote passed: Kick Player1" can't fake the other lines.
+    pub fn parse_event_line(&self, when: DateTime<Local>, line: &str) -> Option<LogLine> {
+        if let Some(caps) = self.connected_rx.captures(line) {
+            return Some(LogLine::PlayerConnected {
+                when,
+                name: caps.get(1).unwrap().as_str().to_string(),
+            });
+        }
+
+        if let Some(caps) = self.vote_cast_rx.captures(line) {
+            return Some(LogLine::VoteCast {
+                when,
+                name: caps.get(1).unwrap().as_str().to_string(),
+                yes: caps.get(2).unwrap().as_str() == "Yes",
+            });
+        }
+
+        if let Some(caps) = self.captured_rx.captures(line) {
+            return Some(LogLine::PointCaptured {
+                when,
+                cappers: caps
+                    .get(1)
+                    .unwrap()
+                    .as_str()
+                    .split(", ")
+                    .map(str::to_string)
+                    .collect(),
+                point: caps.get(2).unwrap().as_str().to_string(),
+                team: caps.get(3).unwrap().as_str().parse().ok()?,
+            });
+        }
+
+        // Has no fixed ending, but still starts with the name of the caller
+        if let Some(caps) = self.vote_started_rx.captures(line) {
+            let issue = caps.get(2).unwrap().as_str().to_string();
+            return Some(LogLine::VoteStarted {
+                when,
+                caller: caps.get(1).unwrap().as_str().to_string(),
+                kick_target: self.kick_target(&issue),
+                issue,
+            });
+        }
+
+        if let Some(caps) = self.kicked_rx.captures(line) {
+            return Some(LogLine::Kicked {
+                when,
+                reason: caps.get(1).unwrap().as_str().to_string(),
+            });
+        }
+
+        if let Some(caps) = self.disconnected_rx.captures(line) {
+            return Some(LogLine::PlayerDisconnected {
+                when,
+                name: caps.get(1).unwrap().as_str().to_string(),
+                reason: caps.get(2).unwrap().as_str().to_string(),
+            });
+        }
+
+        if let Some(caps) = self.team_switch_rx.captures(line) {
+            return Some(LogLine::TeamSwitch {
+                when,
+                name: caps.get(1).unwrap().as_str().to_string(),
+                team: caps.get(2).unwrap().as_str().to_string(),
+            });
+        }
+
+        if let Some(caps) = self.vote_result_rx.captures(line) {
+            let result = caps.get(2).unwrap().as_str().to_string();
+            return if caps.get(1).unwrap().as_str() == "passed" {
+                Some(LogLine::VotePassed {
+                    when,
+                    kick_target: self.kick_target(&result),
+                    issue: result,
+                })
+            } else {
+                Some(LogLine::VoteFailed {
+                    when,
+                    reason: result,
+                })
+            };
+        }
+
+        None
+    }
+
+    /// The name of the player in a kick vote issue, e.g. "Kick Player1 (cheating)"
+    fn kick_target(&self, issue: &str) -> Option<String> {
+        self.kick_issue_rx
+            .captures(issue)
+            .map(|caps| caps.get(1).unwrap().as_str().to_string())
+    }
+
     pub fn try_get_when(&self, line: &str) -> Option<DateTime<Local>> {
         if line.len() < TIMESTAMP_LEN {
             return None;
@@ -345,6 +517,258 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_parse_server_info_lines() {
+        let parser = LogLineParser::default();
+
+        let when = Local.with_ymd_and_hms(2024, 5, 6, 17, 5, 50).unwrap();
+
+        let line = "05/06/2024 - 17:05:50: hostname: Valve Matchmaking Server (Frankfurt srcds101-fra2 #70)";
+        assert_eq!(
+            parser.parse_line(line),
+            Some(LogLine::ServerHostname {
+                when,
+                hostname: "Valve Matchmaking Server (Frankfurt srcds101-fra2 #70)".to_string(),
+            })
+        );
+
+        let line = "05/06/2024 - 17:05:50: udp/ip  : 169.254.171.56:28664";
+        assert_eq!(
+            parser.parse_line(line),
+            Some(LogLine::ServerAddress {
+                when,
+                address: "169.254.171.56:28664".to_string(),
+            })
+        );
+
+        let line = "05/06/2024 - 17:05:50: udp/ip  : 0.0.0.0:27015  (public ip: 1.2.3.4)";
+        assert_eq!(
+            parser.parse_line(line),
+            Some(LogLine::ServerAddress {
+                when,
+                address: "0.0.0.0:27015".to_string(),
+            })
+        );
+
+        let line = "05/06/2024 - 17:05:50: map     : pl_badwater at: 0 x, 0 y, 0 z";
+        assert_eq!(
+            parser.parse_line(line),
+            Some(LogLine::ServerMap {
+                when,
+                map: "pl_badwater".to_string(),
+            })
+        );
+
+        let line = "05/06/2024 - 17:05:50: tags    : hidden,increased_maxplayers,payload,valve";
+        assert_eq!(
+            parser.parse_line(line),
+            Some(LogLine::ServerTags {
+                when,
+                tags: vec![
+                    "hidden".to_string(),
+                    "increased_maxplayers".to_string(),
+                    "payload".to_string(),
+                    "valve".to_string(),
+                ],
+            })
+        );
+
+        let line = "05/06/2024 - 17:05:50: players : 24 humans, 0 bots (32 max)";
+        assert_eq!(
+            parser.parse_line(line),
+            Some(LogLine::ServerPlayerCount {
+                when,
+                humans: 24,
+                bots: 0,
+                max_players: 32,
+            })
+        );
+
+        let line = "05/06/2024 - 17:05:50: players : 1 human, 1 bot (24 max)";
+        assert_eq!(
+            parser.parse_line(line),
+            Some(LogLine::ServerPlayerCount {
+                when,
+                humans: 1,
+                bots: 1,
+                max_players: 24,
+            })
+        );
+
+        // Lines of the header that are not used
+        let line = "05/06/2024 - 17:05:50: version : 8835751/24 8835751 secure";
+        assert_eq!(parser.parse_line(line), None);
+        let line = "05/06/2024 - 17:05:50: edicts  : 959 used of 2048 max";
+        assert_eq!(parser.parse_line(line), None);
+
+        // A player named after a header field is still chat
+        let line = "05/06/2024 - 17:05:50: map :  cp_fake";
+        assert_eq!(
+            parser.parse_line(line),
+            Some(LogLine::Chat {
+                when,
+                name: "map".to_string(),
+                message: "cp_fake".to_string(),
+                dead: false,
+                team: false,
+            })
+        );
+    }
+
+    #[test]
+    fn test_parse_connect_lines() {
+        let parser = LogLineParser::default();
+
+        let when = Local.with_ymd_and_hms(2024, 5, 6, 17, 2, 55).unwrap();
+
+        let line = "05/06/2024 - 17:02:55: used to facetime with your mom connected";
+        let result = parser.parse_line(line).unwrap();
+        assert_eq!(
+            result,
+            LogLine::PlayerConnected {
+                when,
+                name: "used to facetime with your mom".to_string(),
+            }
+        );
+
+        let line = "05/06/2024 - 17:02:55: Dropped Player1 from server (Disconnect by user.)";
+        let result = parser.parse_line(line).unwrap();
+        assert_eq!(
+            result,
+            LogLine::PlayerDisconnected {
+                when,
+                name: "Player1".to_string(),
+                reason: "Disconnect by user.".to_string(),
+            }
+        );
+
+        let line =
+            "05/06/2024 - 17:02:55: Disconnect: Kicked by Console : You have been voted off.";
+        let result = parser.parse_line(line).unwrap();
+        assert_eq!(
+            result,
+            LogLine::Kicked {
+                when,
+                reason: "Kicked by Console : You have been voted off.".to_string(),
+            }
+        );
+    }
+
+    #[test]
+    fn test_parse_team_switch_line() {
+        let parser = LogLineParser::default();
+
+        let when = Local.with_ymd_and_hms(2024, 5, 6, 17, 2, 55).unwrap();
+
+        let line = "05/06/2024 - 17:02:55: Player 𝖁𝖆𝖘𝖎𝖑𝖎𝖘 joined team BLU";
+        let result = parser.parse_line(line).unwrap();
+        assert_eq!(
+            result,
+            LogLine::TeamSwitch {
+                when,
+                name: "𝖁𝖆𝖘𝖎𝖑𝖎𝖘".to_string(),
+                team: "BLU".to_string(),
+            }
+        );
+
+        let line = "05/06/2024 - 17:02:55: Player Player1 was automatically assigned to team RED";
+        let result = parser.parse_line(line).unwrap();
+        assert_eq!(
+            result,
+            LogLine::TeamSwitch {
+                when,
+                name: "Player1".to_string(),
+                team: "RED".to_string(),
+            }
+        );
+    }
+
+    #[test]
+    fn test_parse_vote_lines() {
+        let parser = LogLineParser::default();
+
+        let when = Local.with_ymd_and_hms(2024, 5, 6, 17, 2, 55).unwrap();
+
+        let line = "05/06/2024 - 17:02:55: Player1 called a vote: Kick Player2 (cheating)";
+        let result = parser.parse_line(line).unwrap();
+        assert_eq!(
+            result,
+            LogLine::VoteStarted {
+                when,
+                caller: "Player1".to_string(),
+                issue: "Kick Player2 (cheating)".to_string(),
+                kick_target: Some("Player2".to_string()),
+            }
+        );
+
+        let line = "05/06/2024 - 17:02:55: Player1 called a vote: Change level to pl_badwater";
+        let result = parser.parse_line(line).unwrap();
+        assert_eq!(
+            result,
+            LogLine::VoteStarted {
+                when,
+                caller: "Player1".to_string(),
+                issue: "Change level to pl_badwater".to_string(),
+                kick_target: None,
+            }
+        );
+
+        let line = "05/06/2024 - 17:02:55: Vote passed: Kick used to facetime with your mom (idle)";
+        let result = parser.parse_line(line).unwrap();
+        assert_eq!(
+            result,
+            LogLine::VotePassed {
+                when,
+                issue: "Kick used to facetime with your mom (idle)".to_string(),
+                kick_target: Some("used to facetime with your mom".to_string()),
+            }
+        );
+
+        let line = "05/06/2024 - 17:02:55: used to facetime with your mom voted No";
+        let result = parser.parse_line(line).unwrap();
+        assert_eq!(
+            result,
+            LogLine::VoteCast {
+                when,
+                name: "used to facetime with your mom".to_string(),
+                yes: false,
+            }
+        );
+
+        let line = "05/06/2024 - 17:02:55: Vote failed: Not enough players voted";
+        let result = parser.parse_line(line).unwrap();
+        assert_eq!(
+            result,
+            LogLine::VoteFailed {
+                when,
+                reason: "Not enough players voted".to_string(),
+            }
+        );
+    }
+
+    #[test]
+    fn test_parse_captured_line() {
+        let parser = LogLineParser::default();
+
+        let when = Local.with_ymd_and_hms(2024, 5, 6, 17, 5, 58).unwrap();
+
+        let line = "05/06/2024 - 17:05:58: Benito Tortellini (real), Slightly Destructive Justice, TopG_14 captured Third Capture point for team #3";
+        let result = parser.parse_line(line).unwrap();
+        assert_eq!(
+            result,
+            LogLine::PointCaptured {
+                when,
+                cappers: vec![
+                    "Benito Tortellini (real)".to_string(),
+                    "Slightly Destructive Justice".to_string(),
+                    "TopG_14".to_string(),
+                ],
+                point: "Third Capture point".to_string(),
+                team: 3,
+            }
+        );
+    }
+
     #[test]
     fn test_parse_order() {
         let parser = LogLineParser::default();
@@ -372,6 +796,61 @@ mod tests {
                 team: false
             }
         );
+
+        // A chat message can't pretend to be a vote
+        let line = r#"05/08/2024 - 13:30:42: Player1 :  Vote passed: Kick Player2"#;
+        let result = parser.parse_line(line).unwrap();
+        assert!(matches!(result, LogLine::Chat { .. }));
+    }
+
+    #[test]
+    fn test_parse_spoofed_event_lines() {
+        let parser = LogLineParser::default();
+        let parse = |line: &str| parser.parse_line(&format!("05/08/2024 - 13:30:42: {}", line));
+
+        // Names that look like the other event lines, joining, voting, calling a vote or capturing
+        let names = [
+            "Vote passed: Kick Player2 (cheating)",
+            "Vote failed: Not enough players voted",
+            "Disconnect: Kicked by Console : You have been voted off.",
+            "Dropped Player2 from server (Disconnect by user.)",
+            "Player Player2 joined team BLU",
+            "Player2 called a vote: Kick Player3 (cheating)",
+            "Player2 captured Point for team #3",
+            "Player2 voted Yes",
+            "Player2 connected",
+        ];
+
+        for name in names {
+            let line = format!("{} connected", name);
+            assert!(
+                matches!(parse(&line), Some(LogLine::PlayerConnected { name: ref x, .. }) if x == name),
+                "{}",
+                line
+            );
+
+            let line = format!("{} voted No", name);
+            assert!(
+                matches!(parse(&line), Some(LogLine::VoteCast { name: ref x, yes: false, .. }) if x == name),
+                "{}",
+                line
+            );
+
+            // Still a vote, only who called it can't be told apart from the issue
+            let line = format!("{} called a vote: Kick Player1 (other)", name);
+            assert!(
+                matches!(parse(&line), Some(LogLine::VoteStarted { caller: ref x, .. }) if x == name || name.contains(" called a vote: ")),
+                "{}",
+                line
+            );
+
+            let line = format!("{} captured Point for team #2", name);
+            assert!(
+                matches!(parse(&line), Some(LogLine::PointCaptured { ref cappers, team: 2, .. }) if cappers == &[name]),
+                "{}",
+                line
+            );
+        }
     }
 
     #[test]
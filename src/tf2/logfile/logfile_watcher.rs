@@ -1,8 +1,10 @@
 use super::line_parser::LogLineParser;
-use crate::appbus::AppBus;
-use crate::config::{LOGFILE_FILE_NOT_EXIST_DELAY, LOGFILE_LOOP_DELAY};
+use crate::appbus::{AppBus, AppEventMsg};
+use crate::config::{LOGFILE_FILE_NOT_EXIST_DELAY, LOGFILE_LOOP_DELAY, LOGFILE_MAX_PARTIAL_LINE};
 use crate::models::app_settings::AppSettings;
+use crate::tf2::replay::recorder::SessionRecorder;
 use crate::utils::BoxResult;
+use bus::BusReader;
 use fs_err as fs;
 use std::io::Read;
 use std::io::Seek;
@@ -11,27 +13,74 @@ use std::path::Path;
 use std::sync::Arc;
 use std::sync::Mutex;
 use std::thread;
+use std::time::SystemTime;
 use thread::sleep;
 
 /// Start the logfile watcher thread to run in the background
 pub fn start(settings: &AppSettings, bus: &Arc<Mutex<AppBus>>) -> thread::JoinHandle<()> {
-    let mut watcher = LogfileWatcher {
-        filename: settings.log_filename.clone(),
-        last_pos: 0,
-        bus: Arc::clone(bus),
-    };
+    let mut watcher = LogfileWatcher::new(settings.log_filename.clone(), bus);
 
     thread::spawn(move || watcher.run())
 }
 
+/// Tells files with the same name apart, so we notice when TF2 deletes
+/// and creates console.log again instead of only appending to it
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct FileIdentity {
+    created: Option<SystemTime>,
+
+    /// Always 0 on Windows
+    inode: u64,
+}
+
+impl FileIdentity {
+    fn of(metadata: &std::fs::Metadata) -> Self {
+        #[cfg(unix)]
+        let inode = std::os::unix::fs::MetadataExt::ino(metadata);
+        #[cfg(not(unix))]
+        let inode = 0;
+
+        Self {
+            created: metadata.created().ok(),
+            inode,
+        }
+    }
+}
+
 pub struct LogfileWatcher {
     pub filename: String,
     pub last_pos: u64,
 
+    /// The identity of the file when it was last read
+    identity: Option<FileIdentity>,
+
+    /// The start of a line that TF2 had not finished writing when we last read the file
+    partial_line: Vec<u8>,
+
     bus: Arc<Mutex<AppBus>>,
+    app_event_bus_rx: BusReader<AppEventMsg>,
+    session_recorder: Option<SessionRecorder>,
+
+    /// Set when the app exits
+    exit: bool,
 }
 
 impl LogfileWatcher {
+    pub fn new(filename: String, bus: &Arc<Mutex<AppBus>>) -> Self {
+        let app_event_bus_rx = bus.lock().unwrap().app_event_bus.add_rx();
+
+        Self {
+            filename,
+            last_pos: 0,
+            identity: None,
+            partial_line: Vec::new(),
+            bus: Arc::clone(bus),
+            app_event_bus_rx,
+            session_recorder: bus.lock().unwrap().session_recorder.clone(),
+            exit: false,
+        }
+    }
+
     pub fn run(&mut self) {
         let parser = LogLineParser::default();
         log::info!(
@@ -41,16 +90,27 @@ impl LogfileWatcher {
 
         self.goto_last_pos();
 
-        loop {
+        while !self.exit {
             sleep(LOGFILE_LOOP_DELAY);
 
             self.process_new_data(&parser);
+            self.process_app_event_bus();
+        }
+
+        log::info!("Logfile watcher stopped");
+    }
+
+    fn process_app_event_bus(&mut self) {
+        while let Ok(app_event) = self.app_event_bus_rx.try_recv() {
+            if let AppEventMsg::Exit = app_event {
+                self.exit = true;
+            }
         }
     }
 
     /// Go to the last position in the file
     fn goto_last_pos(&mut self) {
-        self.last_pos = 0;
+        self.restart();
 
         let file = fs::File::open(self.filename.as_str());
         if let Ok(file) = file {
@@ -58,10 +118,18 @@ impl LogfileWatcher {
                 log::info!("Moving to the end of TF2 console.log.");
 
                 self.last_pos = metadata.len();
+                self.identity = Some(FileIdentity::of(&metadata));
             }
         }
     }
 
+    /// Read the file from the beginning on the next read
+    fn restart(&mut self) {
+        self.last_pos = 0;
+        self.identity = None;
+        self.partial_line.clear();
+    }
+
     pub fn process_new_data(&mut self, parser: &LogLineParser) {
         if !Path::new(&self.filename).exists() {
             log::warn!(
@@ -73,12 +141,15 @@ impl LogfileWatcher {
         }
 
         // log::info!("Processing new data");
-        let new_data = self.read_new_data();
-        if let Ok(new_data) = new_data {
-            let lines: Vec<&str> = new_data.lines().collect();
+        let new_lines = self.read_new_lines();
+        if let Ok(lines) = new_lines {
             log::debug!("Got {} new lines in the logfile", lines.len());
             for line in lines {
-                let msg = parser.parse_line(line);
+                if let Some(recorder) = &self.session_recorder {
+                    recorder.record_log_line(&line);
+                }
+
+                let msg = parser.parse_line(&line);
                 if let Some(msg) = msg {
                     let mut bus = self.bus.lock().unwrap();
                     bus.send_logline(msg);
@@ -90,33 +161,181 @@ impl LogfileWatcher {
         }
     }
 
-    fn read_new_data(&mut self) -> BoxResult<String> {
+    /// Reads the lines added to the file since the last read.
+    /// A line without a line break at the end is kept until the rest of it is written.
+    fn read_new_lines(&mut self) -> BoxResult<Vec<String>> {
         let mut file = fs::File::open(self.filename.as_str())?;
+        let metadata = file.metadata()?;
+        let file_len = metadata.len();
+        let identity = FileIdentity::of(&metadata);
+
+        if self.identity.as_ref().is_some_and(|old| *old != identity) {
+            // Deleted and created again, e.g. when TF2 is restarted
+            log::info!("TF2 console.log file was replaced, starting from beginning.");
+            self.restart();
+        } else if file_len < self.last_pos {
+            // Cleared by -conclearlog when TF2 is restarted
+            log::info!("TF2 console.log file was truncated, starting from beginning.");
+            self.restart();
+        }
+        self.identity = Some(identity);
 
         // Get new file length, if same as old, we're done.
-        let file_len = file.metadata()?.len();
         if file_len == self.last_pos {
-            return Ok("".to_string());
+            return Ok(Vec::new());
         }
 
-        // File was truncated, start from beginning
-        if file_len < self.last_pos {
-            log::info!("TF2 console.log file was truncated, starting from beginning.");
-            self.last_pos = 0;
+        // Seek to the position we last read from and read what is new,
+        // including anything written since the length was checked
+        file.seek(SeekFrom::Start(self.last_pos))?;
+        let mut buf: Vec<u8> = Vec::new();
+        let read = file.read_to_end(&mut buf)?;
+        self.last_pos += read as u64;
+
+        self.partial_line.extend_from_slice(&buf);
+
+        let lines = self.take_complete_lines();
+        self.limit_partial_line();
+
+        Ok(lines)
+    }
+
+    /// Splits off the complete lines read so far, keeping a partial last line for the next read.
+    /// Lines are decoded after joining the parts, so a character split between reads stays whole.
+    fn take_complete_lines(&mut self) -> Vec<String> {
+        let Some(end) = self.partial_line.iter().rposition(|b| *b == b'\n') else {
+            return Vec::new();
+        };
+
+        let rest = self.partial_line.split_off(end + 1);
+        let complete = std::mem::replace(&mut self.partial_line, rest);
+
+        String::from_utf8_lossy(&complete)
+            .lines()
+            .map(str::to_string)
+            .collect()
+    }
+
+    /// Drops an unfinished line that keeps growing, e.g. a file that isn't a console log
+    fn limit_partial_line(&mut self) {
+        if self.partial_line.len() > LOGFILE_MAX_PARTIAL_LINE {
+            log::warn!(
+                "Skipping a line of more than {} bytes in the TF2 console.log file",
+                LOGFILE_MAX_PARTIAL_LINE
+            );
+            self.partial_line.clear();
         }
+    }
+}
 
-        // Seek to the position we last read from
-        file.seek(SeekFrom::Start(self.last_pos))?;
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    fn watcher(path: &Path) -> LogfileWatcher {
+        let bus = Arc::new(Mutex::new(AppBus::default()));
+        LogfileWatcher::new(path.to_str().unwrap().to_string(), &bus)
+    }
 
-        // Read the portion of the file that is new
-        let buf_size = (file_len - self.last_pos) as usize;
-        let mut buf: Vec<u8> = vec![0; buf_size];
-        file.read_exact(&mut buf)?;
+    fn append(path: &Path, data: &[u8]) {
+        let mut file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .unwrap();
+        file.write_all(data).unwrap();
+    }
+
+    #[test]
+    fn test_partial_lines() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("console.log");
+        std::fs::write(&path, "").unwrap();
+        let mut watcher = watcher(&path);
 
-        let s = String::from_utf8_lossy(&buf).to_string();
+        append(&path, b"line 1\r\nline ");
+        assert_eq!(watcher.read_new_lines().unwrap(), vec!["line 1"]);
 
-        self.last_pos += buf_size as u64;
+        // The rest of the line and a character split in the middle
+        append(&path, b"2\r\n\xc3");
+        assert_eq!(watcher.read_new_lines().unwrap(), vec!["line 2"]);
+        append(&path, b"\xa9t\xc3\xa9\n");
+        assert_eq!(watcher.read_new_lines().unwrap(), vec!["été"]);
+
+        assert!(watcher.read_new_lines().unwrap().is_empty());
+    }
 
-        Ok(s)
+    #[test]
+    fn test_long_partial_line() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("console.log");
+        std::fs::write(&path, "").unwrap();
+        let mut watcher = watcher(&path);
+
+        append(&path, &vec![b'x'; LOGFILE_MAX_PARTIAL_LINE + 1]);
+        assert!(watcher.read_new_lines().unwrap().is_empty());
+        assert!(watcher.partial_line.is_empty());
+
+        // Only the start is dropped, the end of the long line is read as a line of its own
+        append(&path, b"xxx\nline 1\n");
+        assert_eq!(watcher.read_new_lines().unwrap(), vec!["xxx", "line 1"]);
+    }
+
+    #[test]
+    fn test_invalid_utf8() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("console.log");
+        std::fs::write(&path, "").unwrap();
+        let mut watcher = watcher(&path);
+
+        append(&path, b"bad \xff name\nPlayer1 connected\n");
+        assert_eq!(
+            watcher.read_new_lines().unwrap(),
+            vec!["bad \u{FFFD} name", "Player1 connected"]
+        );
+    }
+
+    #[test]
+    fn test_truncated() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("console.log");
+        std::fs::write(&path, "old line 1\nold line 2\nold line 3\n").unwrap();
+        let mut watcher = watcher(&path);
+        watcher.goto_last_pos();
+        assert!(watcher.read_new_lines().unwrap().is_empty());
+
+        // -conclearlog empties the file, and the new session is read from the start
+        let file = std::fs::OpenOptions::new().write(true).open(&path).unwrap();
+        file.set_len(0).unwrap();
+        append(&path, b"new 1\nnew 2\n");
+
+        assert_eq!(watcher.read_new_lines().unwrap(), vec!["new 1", "new 2"]);
+        append(&path, b"new 3\n");
+        assert_eq!(watcher.read_new_lines().unwrap(), vec!["new 3"]);
+    }
+
+    #[test]
+    fn test_replaced() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("console.log");
+        std::fs::write(&path, "old\n").unwrap();
+        let mut watcher = watcher(&path);
+        watcher.goto_last_pos();
+        append(&path, b"partial");
+        assert!(watcher.read_new_lines().unwrap().is_empty());
+
+        // A new file that is already longer than the old one
+        let replacement = path.with_extension("new");
+        std::fs::write(&replacement, "a new session with long lines\nsecond\n").unwrap();
+        std::fs::rename(&replacement, &path).unwrap();
+
+        let lines = watcher.read_new_lines().unwrap();
+        if cfg!(unix) {
+            assert_eq!(lines, vec!["a new session with long lines", "second"]);
+        } else {
+            // Only the creation time tells the files apart
+            assert!(!lines.is_empty());
+        }
     }
 }
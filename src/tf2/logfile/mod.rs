@@ -24,18 +24,60 @@ pub enum LogLine {
         when: DateTime<Local>,
         name: String,
     },
-    /*
-    05/06/2024 - 17:05:50: hostname: Valve Matchmaking Server (Frankfurt srcds101-fra2 #70)
-    05/06/2024 - 17:05:50: version : 8835751/24 8835751 secure
-    05/06/2024 - 17:05:50: udp/ip  : 169.254.171.56:28664
-    05/06/2024 - 17:05:50: steamid : [A:1:509071377:29317] (90197908612698129)
-    05/06/2024 - 17:05:50: account : not logged in  (No account specified)
-    05/06/2024 - 17:05:50: map     : pl_badwater at: 0 x, 0 y, 0 z
-    05/06/2024 - 17:05:50: tags    : hidden,increased_maxplayers,payload,valve
-    05/06/2024 - 17:05:50: players : 24 humans, 0 bots (32 max)
-    05/06/2024 - 17:05:50: edicts  : 959 used of 2048 max
-         */
-    // 05/06/2024 - 17:05:58: Benito Tortellini (real), Slightly Destructive Justice, TopG_14 captured Third Capture point for team #3
+
+    // The header of the status command output is parsed into the Server* lines:
+    //
+    // 05/06/2024 - 17:05:50: hostname: Valve Matchmaking Server (Frankfurt srcds101-fra2 #70)
+    // 05/06/2024 - 17:05:50: version : 8835751/24 8835751 secure
+    // 05/06/2024 - 17:05:50: udp/ip  : 169.254.171.56:28664
+    // 05/06/2024 - 17:05:50: steamid : [A:1:509071377:29317] (90197908612698129)
+    // 05/06/2024 - 17:05:50: account : not logged in  (No account specified)
+    // 05/06/2024 - 17:05:50: map     : pl_badwater at: 0 x, 0 y, 0 z
+    // 05/06/2024 - 17:05:50: tags    : hidden,increased_maxplayers,payload,valve
+    // 05/06/2024 - 17:05:50: players : 24 humans, 0 bots (32 max)
+    // 05/06/2024 - 17:05:50: edicts  : 959 used of 2048 max
+    /// Example:
+    /// 05/06/2024 - 17:05:50: hostname: Valve Matchmaking Server (Frankfurt srcds101-fra2 #70)
+    ServerHostname {
+        /// Local time
+        when: DateTime<Local>,
+        hostname: String,
+    },
+
+    /// Example:
+    /// 05/06/2024 - 17:05:50: udp/ip  : 169.254.171.56:28664
+    ServerAddress {
+        /// Local time
+        when: DateTime<Local>,
+        address: String,
+    },
+
+    /// Example:
+    /// 05/06/2024 - 17:05:50: map     : pl_badwater at: 0 x, 0 y, 0 z
+    ServerMap {
+        /// Local time
+        when: DateTime<Local>,
+        map: String,
+    },
+
+    /// Example:
+    /// 05/06/2024 - 17:05:50: tags    : hidden,increased_maxplayers,payload,valve
+    ServerTags {
+        /// Local time
+        when: DateTime<Local>,
+        tags: Vec<String>,
+    },
+
+    /// Example:
+    /// 05/06/2024 - 17:05:50: players : 24 humans, 0 bots (32 max)
+    ServerPlayerCount {
+        /// Local time
+        when: DateTime<Local>,
+        humans: u32,
+        bots: u32,
+        max_players: u32,
+    },
+
     LobbyCreated {
         /// Local time
         when: DateTime<Local>,
@@ -54,4 +96,90 @@ pub enum LogLine {
         dead: bool,
         team: bool,
     },
+
+    /// Example:
+    /// 05/06/2024 - 17:02:55: Player1 connected
+    PlayerConnected {
+        /// Local time
+        when: DateTime<Local>,
+        name: String,
+    },
+
+    /// Example:
+    /// 05/06/2024 - 17:02:55: Dropped Player1 from server (Disconnect by user.)
+    PlayerDisconnected {
+        /// Local time
+        when: DateTime<Local>,
+        name: String,
+        reason: String,
+    },
+
+    /// A player picked a team, or was put in one by the server.
+    /// Example:
+    /// 05/06/2024 - 17:02:55: Player Player1 joined team BLU
+    /// 05/06/2024 - 17:02:55: Player Player1 was automatically assigned to team RED
+    TeamSwitch {
+        /// Local time
+        when: DateTime<Local>,
+        name: String,
+        /// RED, BLU or Spectator
+        team: String,
+    },
+
+    /// Example:
+    /// 05/06/2024 - 17:02:55: Player1 called a vote: Kick Player2 (cheating)
+    VoteStarted {
+        /// Local time
+        when: DateTime<Local>,
+        caller: String,
+        issue: String,
+        /// The name of the player to kick, for kick votes
+        kick_target: Option<String>,
+    },
+
+    /// Example:
+    /// 05/06/2024 - 17:02:58: Player3 voted Yes
+    VoteCast {
+        /// Local time
+        when: DateTime<Local>,
+        name: String,
+        yes: bool,
+    },
+
+    /// Example:
+    /// 05/06/2024 - 17:03:10: Vote passed: Kick Player2 (cheating)
+    VotePassed {
+        /// Local time
+        when: DateTime<Local>,
+        issue: String,
+        kick_target: Option<String>,
+    },
+
+    /// Example:
+    /// 05/06/2024 - 17:03:10: Vote failed: Not enough players voted
+    VoteFailed {
+        /// Local time
+        when: DateTime<Local>,
+        reason: String,
+    },
+
+    /// Example:
+    /// 05/06/2024 - 17:05:58: Benito Tortellini (real), Slightly Destructive Justice, TopG_14 captured Third Capture point for team #3
+    PointCaptured {
+        /// Local time
+        when: DateTime<Local>,
+        cappers: Vec<String>,
+        point: String,
+        /// 2 is RED and 3 is BLU
+        team: u32,
+    },
+
+    /// We were kicked from the server.
+    /// Example:
+    /// 05/06/2024 - 17:02:55: Disconnect: Kicked by Console : You have been voted off.
+    Kicked {
+        /// Local time
+        when: DateTime<Local>,
+        reason: String,
+    },
 }
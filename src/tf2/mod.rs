@@ -1,15 +1,31 @@
+#[cfg(test)]
+pub mod fake_tf2;
 pub mod lobby;
 pub mod logfile;
 pub mod rcon;
+pub mod replay;
 pub mod steamapi;
 
 use crate::{appbus::AppBus, db::db::DbPool, models::app_settings::AppSettings};
-use std::sync::{Arc, Mutex};
+use replay::{ArchiveEntry, SESSIONS_DIR, recorder::SessionRecorder};
+use std::{
+    path::Path,
+    sync::{Arc, Mutex},
+    thread::JoinHandle,
+};
+
+/// Start the background threads for the TF2 module.
+/// Returns the lobby thread, which stops after AppEventMsg::Exit.
+pub fn start(settings: &AppSettings, bus: &Arc<Mutex<AppBus>>, db: &DbPool) -> JoinHandle<()> {
+    if settings.record_sessions {
+        match SessionRecorder::create(Path::new(SESSIONS_DIR)) {
+            Ok(recorder) => bus.lock().unwrap().session_recorder = Some(recorder),
+            Err(e) => log::error!("Could not start recording the session: {}", e),
+        }
+    }
 
-/// Start the background threads for the TF2 module
-pub fn start(settings: &AppSettings, bus: &Arc<Mutex<AppBus>>, db: &DbPool) {
     let _rcon_thread_handle = rcon::rcon_thread::start(settings, bus);
-    let _lobby_thread_handle = lobby::lobby_thread::start(settings, bus);
+    let lobby_thread_handle = lobby::lobby_thread::start(settings, bus, db);
     let _steamapi_thread_handle = steamapi::steamapi_thread::start(settings, bus, db);
 
     let _logfile_watcher_thread_handle = logfile::logfile_watcher::start(settings, bus);
@@ -19,4 +35,25 @@ pub fn start(settings: &AppSettings, bus: &Arc<Mutex<AppBus>>, db: &DbPool) {
     // bus.lobby_thread_handle = Some(lobby_thread_handle);
     // bus.steamapi_thread_handle = Some(steamapi_thread_handle);
     // bus.logfile_watcher_thread_handle = Some(logfile_watcher_thread_handle);
+
+    lobby_thread_handle
+}
+
+/// Start the background threads for the TF2 module with a recorded session instead of TF2.
+/// The database should not be the real one, the replayed players would be recorded as met again.
+/// Returns the lobby thread, which stops after AppEventMsg::Exit.
+pub fn start_replay(
+    settings: &AppSettings,
+    bus: &Arc<Mutex<AppBus>>,
+    db: &DbPool,
+    archive: Vec<ArchiveEntry>,
+    speed: f64,
+) -> JoinHandle<()> {
+    bus.lock().unwrap().replaying = true;
+
+    let _replay_thread_handle = replay::replay_thread::start(archive, speed, bus);
+    let lobby_thread_handle = lobby::lobby_thread::start(settings, bus, db);
+    let _steamapi_thread_handle = steamapi::steamapi_thread::start(settings, bus, db);
+
+    lobby_thread_handle
 }
@@ -10,18 +10,37 @@ use std::collections::HashMap;
 pub struct G15DumpPlayerParser {
     current_players: HashMap<SteamID, PlayerEntry>,
     default_seen_counter: u32,
+    re_field: Regex,
 }
 
+/// The playerresource fields parsed from the dump.
+/// TF2 only dumps the fields of the base player resource, see g15_dumpplayer_output.txt,
+/// so there is no class, total score, damage or connection time.
+const PLAYER_FIELDS: &[&str] = &[
+    "m_szName",
+    "m_bConnected",
+    "m_bValid",
+    "m_bAlive",
+    "m_iHealth",
+    "m_iPing",
+    "m_iDeaths",
+    "m_iTeam",
+    "m_iAccountID",
+    "m_iUserID",
+    "m_iScore",
+];
+
 impl G15DumpPlayerParser {
     pub fn new() -> Self {
         Self {
             current_players: HashMap::new(),
             default_seen_counter: 8,
+            re_field: Regex::new(r"^(m_\w+)\[(\d+)\]\s+\w+\s+\((.*?)\)$").unwrap(),
         }
     }
 
     pub fn parse(&mut self, data: &str) -> G15DumpPlayerOutput {
-        let dump = Self::parse_dump(data, self.default_seen_counter);
+        let dump = self.parse_dump(data);
 
         // Add players from the current dump to the current_players map
         for (_, player) in dump.player_resource.players.iter() {
@@ -67,57 +86,65 @@ impl G15DumpPlayerParser {
                     _ => None,
                 },
                 health: player.health,
+                score: player.score,
             });
         }
 
+        // In the order of the scoreboard slots, the map above has no order
+        result.players.sort_by_key(|player| player.id);
+
         result
     }
 
-    fn parse_dump(data: &str, default_seen_counter: u32) -> DumpData {
+    fn parse_dump(&self, data: &str) -> DumpData {
         let mut dump = DumpData::default();
-        let mut current_section = "";
-
-        let re_field = Regex::new(r"(?m)^([a-zA-Z0-9_\[\]]+)\s+\w+\s+\((.*?)\)$").unwrap();
-        let re_indexed = Regex::new(r"([^\[]+)\[(\d+)\]").unwrap();
+        let mut in_player_resource = false;
 
         for line in data.lines() {
-            if line.starts_with("(playerresource)") {
-                current_section = "playerresource";
+            // Sections like (localplayer) and (playerresource) start with a line in parentheses
+            if line.starts_with('(') {
+                in_player_resource = line.starts_with("(playerresource)");
                 continue;
             }
 
-            if current_section == "playerresource" {
-                if let Some(caps) = re_field.captures(line) {
-                    let raw_key = &caps[1];
-                    let value_str = &caps[2];
-
-                    if let Some(cap_indexed) = re_indexed.captures(raw_key) {
-                        let key = cap_indexed[1].to_string();
-                        let index: usize = cap_indexed[2].parse().unwrap_or(999);
-
-                        let entry = dump.player_resource.players.entry(index).or_default();
-
-                        match key.as_str() {
-                            "m_szName" => entry.name = Some(value_str.to_string()),
-                            "m_bConnected" => entry.connected = Some(value_str == "true"),
-                            "m_bValid" => entry.valid = Some(value_str == "true"),
-                            "m_bAlive" => entry.alive = Some(value_str == "true"),
-                            "m_iHealth" => entry.health = value_str.parse().ok(),
-                            "m_iPing" => entry.ping_ms = value_str.parse().ok(),
-                            "m_iDeaths" => entry.deaths = value_str.parse().ok(),
-                            "m_iTeam" => entry.team = value_str.parse().ok(),
-                            "m_iAccountID" => entry.account_id = value_str.parse().ok(),
-                            "m_iUserID" => entry.user_id = value_str.parse().ok(),
-                            _ => {}
-                        }
-                    }
+            if !in_player_resource {
+                continue;
+            }
+
+            // Most of the fields are not used, skip them before running the slow regex
+            let Some((key, _)) = line.split_once('[') else {
+                continue;
+            };
+            if !PLAYER_FIELDS.contains(&key) {
+                continue;
+            }
+
+            if let Some(caps) = self.re_field.captures(line) {
+                let value_str = &caps[3];
+                let index: usize = caps[2].parse().unwrap_or(999);
+
+                let entry = dump.player_resource.players.entry(index).or_default();
+
+                match key {
+                    "m_szName" => entry.name = Some(value_str.to_string()),
+                    "m_bConnected" => entry.connected = Some(value_str == "true"),
+                    "m_bValid" => entry.valid = Some(value_str == "true"),
+                    "m_bAlive" => entry.alive = Some(value_str == "true"),
+                    "m_iHealth" => entry.health = value_str.parse().ok(),
+                    "m_iPing" => entry.ping_ms = value_str.parse().ok(),
+                    "m_iDeaths" => entry.deaths = value_str.parse().ok(),
+                    "m_iTeam" => entry.team = value_str.parse().ok(),
+                    "m_iAccountID" => entry.account_id = value_str.parse().ok(),
+                    "m_iUserID" => entry.user_id = value_str.parse().ok(),
+                    "m_iScore" => entry.score = value_str.parse().ok(),
+                    _ => {}
                 }
             }
         }
 
         // Set seen_counter for all players
         for (_, player) in dump.player_resource.players.iter_mut() {
-            player.seen_counter = default_seen_counter;
+            player.seen_counter = self.default_seen_counter;
             if let Some(account_id) = player.account_id {
                 player.steamid = Some(steamid::SteamID::from_u64(
                     account_id as u64 + steamid::MIN_STEAMID64,
@@ -141,6 +168,7 @@ struct PlayerEntry {
     pub team: Option<u32>,
     pub account_id: Option<u32>,
     pub user_id: Option<u32>,
+    pub score: Option<u32>,
     pub seen_counter: u32,
     pub steamid: Option<SteamID>,
 }
@@ -167,49 +195,166 @@ struct DumpData {
 
 #[cfg(test)]
 mod tests {
-    // use chrono::prelude::*;
-
-    // use steamid::SteamID;
-
-    // use super::*;
-
-    // fn get_dump_text() -> String {
-    //     let bytes = include_bytes!("g15_dumpplayer_output.txt");
-    //     let s = String::from_utf8_lossy(bytes);
-    //     s.to_string()
-    // }
-
-    // #[test]
-    // fn test_parse() {
-    //     let dump = get_dump_text();
-    //     let output = parse_g15_dump(&dump);
-
-    //     for (i, player) in output.players.iter().enumerate() {
-    //         println!("{}: {:?}", i, player);
-    //     }
-
-    //     assert_eq!(19, output.players.len());
-    //     let player = &output.players[15];
-    //     assert_eq!("aftershave".to_string(), player.name);
-    //     assert_eq!(SteamID::from_u64(76561197974228301), player.steamid);
-    //     assert_eq!(23, output.players[15].ping);
-    // }
-
-    // Without the filter in parse() this took 17 seconds.
-    // With the filter in parse() it takes 10 seconds.
-    // With a .start_with(prefix) it takes 5 seconds.
-    // #[test]
-    // fn test_bench_parse() {
-    //     let dump = get_dump_text();
-
-    //     let start_time = std::time::Instant::now();
-    //     for _ in 0..1000 {
-    //         let _output = parse_g15_dump(&dump);
-    //     }
-    //     let stop_time = std::time::Instant::now();
-    //     let elapsed = stop_time - start_time;
-    //     println!("Elapsed: {:?}", elapsed);
-
-    //     assert!(false);
-    // }
+    use super::*;
+    use crate::tf2::fake_tf2::{FakeTf2Script, g15_dump};
+
+    fn get_dump_text() -> String {
+        let bytes = include_bytes!("g15_dumpplayer_output.txt");
+        String::from_utf8_lossy(bytes).to_string()
+    }
+
+    #[test]
+    fn test_parse() {
+        let dump = get_dump_text();
+        let output = G15DumpPlayerParser::new().parse(&dump);
+
+        assert_eq!(19, output.players.len());
+
+        let player = output
+            .players
+            .iter()
+            .find(|player| player.steamid == SteamID::from_u64(76561197974228301))
+            .unwrap();
+        assert_eq!("aftershave", player.name);
+        assert_eq!(22, player.id);
+        assert_eq!(23, player.ping_ms);
+        assert_eq!(Some(Team::Red), player.team);
+        assert!(player.alive);
+        assert_eq!(Some(60), player.health);
+        assert_eq!(Some(1), player.score);
+
+        // Sorted by user ID
+        assert!(output.players.windows(2).all(|w| w[0].id <= w[1].id));
+    }
+
+    /// The fields TF2 dumps per player, a new TF2 version that dumps more shows up here
+    #[test]
+    fn test_real_dump_fields() {
+        let dump = get_dump_text();
+        let player_resource = dump
+            .split("(playerresource)")
+            .nth(1)
+            .and_then(|section| section.split("\n(").next())
+            .unwrap();
+
+        let mut fields: Vec<&str> = player_resource
+            .lines()
+            .filter_map(|line| line.split_once('[').map(|(key, _)| key))
+            .collect();
+        fields.sort();
+        fields.dedup();
+
+        assert_eq!(
+            vec![
+                "m_bAlive",
+                "m_bConnected",
+                "m_bValid",
+                "m_iAccountID",
+                "m_iDeaths",
+                "m_iHealth",
+                "m_iPing",
+                "m_iScore",
+                "m_iTeam",
+                "m_iUserID",
+                "m_szName",
+            ],
+            fields
+        );
+    }
+
+    /// The dump of the fake TF2 reads the same as the one of TF2
+    #[test]
+    fn test_parse_fake_tf2_dump() {
+        let script = FakeTf2Script::load("src/tf2/fake_tf2_fixture.json").unwrap();
+        let roster = script.steps[0].roster.clone().unwrap();
+        let output = G15DumpPlayerParser::new().parse(&g15_dump(&roster));
+
+        assert_eq!(output.players.len(), roster.len());
+        let bot = output
+            .players
+            .iter()
+            .find(|p| p.steamid == SteamID::from_u64(76561199179248685))
+            .unwrap();
+        assert_eq!((bot.id, bot.name.as_str()), (3, "OMEGATRONIC"));
+        assert_eq!(bot.score, Some(4));
+    }
+
+    /// Fields with the same name in other sections are not the player's
+    #[test]
+    fn test_parse_player_resource_only() {
+        let dump = "(playerresource)\n\
+            m_szName[1] string (Player1)\n\
+            m_bConnected[1] bool (true)\n\
+            m_bValid[1] bool (true)\n\
+            m_iAccountID[1] integer (52079950)\n\
+            m_iUserID[1] integer (7)\n\
+            m_iScore[1] integer (12)\n\
+            (localplayerweapon)\n\
+            m_iScore[1] integer (99)\n";
+
+        let output = G15DumpPlayerParser::new().parse(dump);
+
+        assert_eq!(1, output.players.len());
+        assert_eq!(Some(12), output.players[0].score);
+    }
+
+    #[test]
+    fn test_players_kept_for_a_while() {
+        let dump = get_dump_text();
+        let mut parser = G15DumpPlayerParser::new();
+        assert_eq!(19, parser.parse(&dump).players.len());
+
+        // Players missing from a few dumps in a row are still there
+        assert_eq!(19, parser.parse("(playerresource)\n").players.len());
+    }
+
+    /// How parse_dump found the fields before the regex was compiled once, for the benchmark
+    fn parse_dump_per_call_regex(data: &str) -> usize {
+        let re_field = Regex::new(r"(?m)^([a-zA-Z0-9_\[\]]+)\s+\w+\s+\((.*?)\)$").unwrap();
+        let re_indexed = Regex::new(r"([^\[]+)\[(\d+)\]").unwrap();
+
+        let mut fields = 0;
+        let mut in_player_resource = false;
+        for line in data.lines() {
+            if line.starts_with("(playerresource)") {
+                in_player_resource = true;
+                continue;
+            }
+
+            if in_player_resource
+                && let Some(caps) = re_field.captures(line)
+                && re_indexed.captures(&caps[1]).is_some()
+            {
+                fields += 1;
+            }
+        }
+
+        fields
+    }
+
+    // Run with: cargo test --release bench_parse -- --ignored --nocapture
+    #[test]
+    #[ignore]
+    fn test_bench_parse() {
+        let dump = get_dump_text();
+        let parser = G15DumpPlayerParser::new();
+
+        let start_time = std::time::Instant::now();
+        for _ in 0..1000 {
+            let _dump = parser.parse_dump(&dump);
+        }
+        let elapsed = start_time.elapsed();
+
+        let start_time = std::time::Instant::now();
+        for _ in 0..1000 {
+            let _fields = parse_dump_per_call_regex(&dump);
+        }
+        let elapsed_per_call_regex = start_time.elapsed();
+
+        // The dump is read every RCON_LOOP_DELAY of 2 seconds
+        println!(
+            "Parsing 1000 dumps took {:?}, {:?} with the regexes compiled per call",
+            elapsed, elapsed_per_call_regex
+        );
+    }
 }
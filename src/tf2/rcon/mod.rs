@@ -1,6 +1,8 @@
 use crate::models::steamid::SteamID;
 
 use super::lobby::Team;
+use rcon_connection::RconError;
+use std::io::ErrorKind;
 
 pub mod g15_dumpplayer_parser;
 pub mod rcon_connection;
@@ -20,4 +22,77 @@ pub struct G15PlayerData {
     pub alive: bool,
     pub team: Option<Team>,
     pub health: Option<u32>,
+    pub score: Option<u32>,
+}
+
+/// How the RCON connection to TF2 is doing, shown in the status row
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum RconState {
+    #[default]
+    Connecting,
+    Connected,
+
+    /// Nothing listens on the RCON port, or TF2 closed the connection
+    Tf2NotRunning,
+    WrongPassword,
+    Timeout,
+    Failed(String),
+}
+
+impl RconState {
+    /// Sorts an error from the RCON connection into the states the user can do something about
+    pub fn from_error(error: &RconError) -> Self {
+        match error {
+            RconError::Connect(e) | RconError::Io(e) => match e.kind() {
+                ErrorKind::ConnectionRefused
+                | ErrorKind::ConnectionReset
+                | ErrorKind::ConnectionAborted
+                | ErrorKind::BrokenPipe
+                | ErrorKind::UnexpectedEof => RconState::Tf2NotRunning,
+                ErrorKind::TimedOut => RconState::Timeout,
+                _ => RconState::Failed(error.to_string()),
+            },
+            RconError::Auth => RconState::WrongPassword,
+            RconError::Timeout => RconState::Timeout,
+            RconError::Protocol { .. } => RconState::Failed(error.to_string()),
+        }
+    }
+
+    pub fn description(&self) -> String {
+        match self {
+            RconState::Connecting => "connecting".to_string(),
+            RconState::Connected => "connected".to_string(),
+            RconState::Tf2NotRunning => "TF2 not running".to_string(),
+            RconState::WrongPassword => "wrong RCON password".to_string(),
+            RconState::Timeout => "TF2 is not answering".to_string(),
+            RconState::Failed(error) => format!("error: {}", error),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Error;
+
+    #[test]
+    fn test_rcon_state_from_error() {
+        let state = |error: RconError| RconState::from_error(&error);
+        let io = |kind: ErrorKind| Error::new(kind, "test");
+
+        assert_eq!(
+            state(RconError::Connect(io(ErrorKind::ConnectionRefused))),
+            RconState::Tf2NotRunning
+        );
+        assert_eq!(
+            state(RconError::Io(io(ErrorKind::UnexpectedEof))),
+            RconState::Tf2NotRunning
+        );
+        assert_eq!(state(RconError::Auth), RconState::WrongPassword);
+        assert_eq!(state(io(ErrorKind::WouldBlock).into()), RconState::Timeout);
+        assert_eq!(
+            state(RconError::Io(io(ErrorKind::InvalidData))),
+            RconState::Failed("test".to_string())
+        );
+    }
 }
@@ -1,14 +1,17 @@
+use std::fmt;
+use std::io::ErrorKind;
 use std::io::prelude::*;
 use std::net::TcpStream;
 use std::time::Duration;
 
-use crate::utils::BoxResult;
-
 #[derive(Debug, Clone)]
 pub struct RConArgs {
     pub ip: String,
     pub port: u16,
     pub password: String,
+
+    /// How long to wait for TF2 to accept a package or reply
+    pub timeout: Duration,
 }
 
 impl RConArgs {
@@ -17,6 +20,65 @@ impl RConArgs {
             ip: "127.0.0.1".to_string(),
             port: 40434,
             password: "rconpwd".to_string(),
+            timeout: Duration::from_secs(8),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum RconError {
+    /// Could not connect, usually because TF2 isn't running or RCON is not enabled
+    Connect(std::io::Error),
+
+    /// TF2 rejected the password
+    Auth,
+
+    /// TF2 didn't answer in time
+    Timeout,
+
+    /// TF2 sent a package we don't understand
+    Protocol {
+        id: u32,
+        typ: u32,
+        size: u32,
+        message: String,
+    },
+
+    /// The connection failed after it was opened, e.g. TF2 was closed
+    Io(std::io::Error),
+}
+
+pub type RconResult<T> = Result<T, RconError>;
+
+impl fmt::Display for RconError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RconError::Connect(e) => write!(f, "Could not connect: {}", e),
+            RconError::Auth => write!(f, "Wrong RCON password"),
+            RconError::Timeout => write!(f, "Timed out"),
+            RconError::Protocol {
+                id,
+                typ,
+                size,
+                message,
+            } => write!(
+                f,
+                "{} (package id: {}, type: {}, size: {})",
+                message, id, typ, size
+            ),
+            RconError::Io(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for RconError {}
+
+impl From<std::io::Error> for RconError {
+    fn from(e: std::io::Error) -> Self {
+        match e.kind() {
+            // Read timeouts are WouldBlock on Unix
+            ErrorKind::TimedOut | ErrorKind::WouldBlock => RconError::Timeout,
+            _ => RconError::Io(e),
         }
     }
 }
@@ -27,15 +89,20 @@ pub struct RConConnection {
     id: u32,
 }
 
-#[allow(dead_code)]
 const PKT_TYPE_RESPONSE_VALUE: u32 = 0;
 const PKT_TYPE_EXEC_COMMAND: u32 = 2;
 const PKT_TYPE_AUTH_RESPONSE: u32 = 2;
 const PKT_TYPE_AUTH: u32 = 3;
 
+/// The id of the auth response when the password is wrong, -1 as a u32
+const PKT_ID_AUTH_FAILED: u32 = u32::MAX;
+
 // 4 + 4 + 2 = 10. The size of the two u32s and 2 nul bytes.
 const PKT_HEADER_SIZE: usize = 4 + 4 + 2;
 
+/// TF2 splits long replies into packages of 4096 bytes, anything much bigger is garbage
+const PKT_MAX_SIZE: usize = 64 * 1024;
+
 #[derive(Debug)]
 struct Pkt {
     id: u32,
@@ -53,11 +120,11 @@ struct Pkt {
 ///    println!("{}", client.exec_command(&"cvarlist".to_string()).unwrap());
 ///
 impl RConConnection {
-    pub fn new(args: &RConArgs) -> BoxResult<Self> {
+    pub fn new(args: &RConArgs) -> RconResult<Self> {
         let addr = format!("{}:{}", args.ip, args.port);
-        let stream = TcpStream::connect(addr)?;
-        stream.set_read_timeout(Some(Duration::from_secs(8)))?;
-        stream.set_write_timeout(Some(Duration::from_secs(8)))?;
+        let stream = TcpStream::connect(addr).map_err(RconError::Connect)?;
+        stream.set_read_timeout(Some(args.timeout))?;
+        stream.set_write_timeout(Some(args.timeout))?;
 
         Ok(RConConnection {
             args: args.clone(),
@@ -66,7 +133,7 @@ impl RConConnection {
         })
     }
 
-    pub fn authorize(&mut self) -> BoxResult<()> {
+    pub fn authorize(&mut self) -> RconResult<()> {
         let pkt = Pkt {
             id: self.next_id(),
             typ: PKT_TYPE_AUTH,
@@ -76,16 +143,19 @@ impl RConConnection {
         self.write_pkt(&pkt)?;
         loop {
             let reply = self.read_pkt()?;
-            if reply.typ == PKT_TYPE_AUTH_RESPONSE {
-                // TODO: Check for bad auth
-                break;
+            match reply.typ {
+                // The server sends an empty response before the auth response
+                PKT_TYPE_RESPONSE_VALUE => continue,
+                PKT_TYPE_AUTH_RESPONSE if reply.id == PKT_ID_AUTH_FAILED => {
+                    return Err(RconError::Auth);
+                }
+                PKT_TYPE_AUTH_RESPONSE if reply.id == pkt.id => return Ok(()),
+                _ => return Err(protocol_error(&reply, "Unexpected reply to auth")),
             }
         }
-
-        Ok(())
     }
 
-    pub fn exec_command(&mut self, cmd: &str) -> BoxResult<String> {
+    pub fn exec_command(&mut self, cmd: &str) -> RconResult<String> {
         let request_pkt = Pkt {
             id: self.next_id(),
             typ: PKT_TYPE_EXEC_COMMAND,
@@ -107,6 +177,9 @@ impl RConConnection {
             if reply.id == halt_id {
                 break;
             }
+            if reply.id != request_pkt.id || reply.typ != PKT_TYPE_RESPONSE_VALUE {
+                return Err(protocol_error(&reply, "Unexpected reply to command"));
+            }
             result.push_str(reply.body.as_str());
         }
 
@@ -124,9 +197,9 @@ impl RConConnection {
     /// A halt package is an empty EXEC_COMMAND whose
     /// only purpose is to allow us to read reply packages
     /// until a reply package has the ID of the sent halt package.
-    fn send_halt_pkt(&mut self) -> BoxResult<u32> {
+    fn send_halt_pkt(&mut self) -> RconResult<u32> {
         let halt_pkt = Pkt {
-            id: self.id.wrapping_add(1) & 0xffff,
+            id: self.next_id(),
             typ: PKT_TYPE_EXEC_COMMAND,
             body: "".to_string(),
         };
@@ -136,11 +209,20 @@ impl RConConnection {
         Ok(halt_pkt.id)
     }
 
-    fn read_pkt(&mut self) -> BoxResult<Pkt> {
+    fn read_pkt(&mut self) -> RconResult<Pkt> {
         let size: u32 = self.read_u32()?;
         let id: u32 = self.read_u32()?;
         let typ: u32 = self.read_u32()?;
 
+        if (size as usize) < PKT_HEADER_SIZE || (size as usize) > PKT_MAX_SIZE {
+            return Err(RconError::Protocol {
+                id,
+                typ,
+                size,
+                message: "Invalid package size".to_string(),
+            });
+        }
+
         let body_len = size as usize - PKT_HEADER_SIZE;
         let body = self.read_string(body_len)?;
 
@@ -153,7 +235,7 @@ impl RConConnection {
         Ok(Pkt { id, typ, body })
     }
 
-    fn write_pkt(&mut self, pkt: &Pkt) -> BoxResult<()> {
+    fn write_pkt(&mut self, pkt: &Pkt) -> RconResult<()> {
         let size = PKT_HEADER_SIZE as u32 + pkt.body.len() as u32;
         let typ = pkt.typ;
 
@@ -171,42 +253,252 @@ impl RConConnection {
     // Helper function to read/write u32, u8 and String
     //
 
-    fn write_u32(&mut self, value: u32) -> BoxResult<()> {
+    fn write_u32(&mut self, value: u32) -> RconResult<()> {
         self.stream.write_all(value.to_le_bytes().as_ref())?;
 
         Ok(())
     }
 
-    fn write_u8(&mut self, value: u8) -> BoxResult<()> {
+    fn write_u8(&mut self, value: u8) -> RconResult<()> {
         self.stream.write_all(value.to_le_bytes().as_ref())?;
 
         Ok(())
     }
 
-    fn write_string(&mut self, value: &str) -> BoxResult<()> {
+    fn write_string(&mut self, value: &str) -> RconResult<()> {
         self.stream.write_all(value.as_ref())?;
 
         Ok(())
     }
 
-    fn read_u32(&mut self) -> BoxResult<u32> {
+    fn read_u32(&mut self) -> RconResult<u32> {
         let mut buf = [0u8; 4];
         self.stream.read_exact(&mut buf)?;
 
         Ok(u32::from_le_bytes(buf))
     }
 
-    fn read_u8(&mut self) -> BoxResult<u8> {
+    fn read_u8(&mut self) -> RconResult<u8> {
         let mut buf = [0u8; 1];
         self.stream.read_exact(&mut buf)?;
 
         Ok(buf[0])
     }
 
-    fn read_string(&mut self, len: usize) -> BoxResult<String> {
+    fn read_string(&mut self, len: usize) -> RconResult<String> {
         let mut buf: Vec<u8> = vec![0; len];
         self.stream.read_exact(&mut buf)?;
 
         Ok(String::from_utf8_lossy(&buf).to_string())
     }
 }
+
+fn protocol_error(pkt: &Pkt, message: &str) -> RconError {
+    RconError::Protocol {
+        id: pkt.id,
+        typ: pkt.typ,
+        size: (PKT_HEADER_SIZE + pkt.body.len()) as u32,
+        message: message.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tf2::fake_tf2::{read_packet as server_read, write_packet as server_write};
+    use std::net::TcpListener;
+    use std::thread::{self, JoinHandle};
+
+    const PASSWORD: &str = "rconpwd";
+
+    /// A Source RCON server that replies to commands from the given function,
+    /// splitting the replies into packages of max_body bytes like TF2 does with 4096.
+    /// Returns the commands it received.
+    fn fake_server(
+        reply: fn(&str) -> String,
+        max_body: usize,
+    ) -> (RConArgs, JoinHandle<Vec<String>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let args = args(listener.local_addr().unwrap().port());
+
+        let handle = thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut commands = Vec::new();
+
+            while let Some(pkt) = server_read(&mut stream) {
+                match pkt.typ {
+                    PKT_TYPE_AUTH => {
+                        let id = if pkt.body == PASSWORD {
+                            pkt.id
+                        } else {
+                            PKT_ID_AUTH_FAILED
+                        };
+                        server_write(&mut stream, pkt.id, PKT_TYPE_RESPONSE_VALUE, "");
+                        server_write(&mut stream, id, PKT_TYPE_AUTH_RESPONSE, "");
+                    }
+                    PKT_TYPE_EXEC_COMMAND if pkt.body.is_empty() => {
+                        // The reply to the halt package
+                        server_write(&mut stream, pkt.id, PKT_TYPE_RESPONSE_VALUE, "");
+                    }
+                    PKT_TYPE_EXEC_COMMAND => {
+                        let text = reply(&pkt.body);
+                        let bytes = text.as_bytes();
+                        for chunk in bytes.chunks(max_body.max(1)) {
+                            let chunk = std::str::from_utf8(chunk).unwrap();
+                            server_write(&mut stream, pkt.id, PKT_TYPE_RESPONSE_VALUE, chunk);
+                        }
+                        commands.push(pkt.body);
+                    }
+                    _ => panic!("Unexpected package {:?}", pkt),
+                }
+            }
+
+            commands
+        });
+
+        (args, handle)
+    }
+
+    fn args(port: u16) -> RConArgs {
+        RConArgs {
+            ip: "127.0.0.1".to_string(),
+            port,
+            password: PASSWORD.to_string(),
+            timeout: Duration::from_secs(2),
+        }
+    }
+
+    fn echo(cmd: &str) -> String {
+        format!("You said: {}", cmd)
+    }
+
+    #[test]
+    fn test_exec_command() {
+        let (args, server) = fake_server(echo, 4096);
+
+        let mut client = RConConnection::new(&args).unwrap();
+        client.authorize().unwrap();
+        assert_eq!(client.exec_command("status").unwrap(), "You said: status");
+
+        // The same connection is used for the next command
+        assert_eq!(
+            client.exec_command("g15_dumpplayer").unwrap(),
+            "You said: g15_dumpplayer"
+        );
+
+        drop(client);
+        assert_eq!(server.join().unwrap(), vec!["status", "g15_dumpplayer"]);
+    }
+
+    #[test]
+    fn test_multi_package_reply() {
+        fn long_reply(_cmd: &str) -> String {
+            (0..100).map(|i| format!("line {}\n", i)).collect()
+        }
+        let (args, server) = fake_server(long_reply, 16);
+
+        let mut client = RConConnection::new(&args).unwrap();
+        client.authorize().unwrap();
+        assert_eq!(client.exec_command("cvarlist").unwrap(), long_reply(""));
+        assert_eq!(client.exec_command("status").unwrap(), long_reply(""));
+
+        drop(client);
+        assert_eq!(server.join().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_wrong_password() {
+        let (mut args, server) = fake_server(echo, 4096);
+        args.password = "wrong".to_string();
+
+        let mut client = RConConnection::new(&args).unwrap();
+        assert!(matches!(client.authorize(), Err(RconError::Auth)));
+
+        drop(client);
+        assert!(server.join().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_connection_refused() {
+        let port = TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+
+        assert!(matches!(
+            RConConnection::new(&args(port)),
+            Err(RconError::Connect(_))
+        ));
+    }
+
+    #[test]
+    fn test_timeout() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let mut args = args(listener.local_addr().unwrap().port());
+        args.timeout = Duration::from_millis(100);
+
+        // Accepts the connection but never replies
+        let server = thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            while server_read(&mut stream).is_some() {}
+        });
+
+        let mut client = RConConnection::new(&args).unwrap();
+        assert!(matches!(client.authorize(), Err(RconError::Timeout)));
+
+        drop(client);
+        server.join().unwrap();
+    }
+
+    #[test]
+    fn test_invalid_package() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let args = args(listener.local_addr().unwrap().port());
+
+        let server = thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            server_read(&mut stream).unwrap();
+
+            // A size smaller than the header
+            let mut data = Vec::new();
+            for value in [4u32, 7, PKT_TYPE_AUTH_RESPONSE] {
+                data.extend_from_slice(&value.to_le_bytes());
+            }
+            stream.write_all(&data).unwrap();
+        });
+
+        let mut client = RConConnection::new(&args).unwrap();
+        match client.authorize() {
+            Err(RconError::Protocol { id, typ, size, .. }) => {
+                assert_eq!((id, typ, size), (7, PKT_TYPE_AUTH_RESPONSE, 4));
+            }
+            other => panic!("Expected a protocol error, got {:?}", other),
+        }
+
+        server.join().unwrap();
+    }
+
+    #[test]
+    fn test_closed_connection() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let args = args(listener.local_addr().unwrap().port());
+
+        // TF2 is closed right after the auth
+        let server = thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let pkt = server_read(&mut stream).unwrap();
+            server_write(&mut stream, pkt.id, PKT_TYPE_RESPONSE_VALUE, "");
+            server_write(&mut stream, pkt.id, PKT_TYPE_AUTH_RESPONSE, "");
+        });
+
+        let mut client = RConConnection::new(&args).unwrap();
+        client.authorize().unwrap();
+        server.join().unwrap();
+
+        assert!(matches!(
+            client.exec_command("status"),
+            Err(RconError::Io(_))
+        ));
+    }
+}
@@ -1,13 +1,19 @@
-use super::rcon_connection::{RConArgs, RConConnection};
-use crate::config::{RCON_DELAY, RCON_LOOP_DELAY};
-use crate::db::db::DbPool;
+use super::RconState;
+use super::rcon_connection::{RConArgs, RConConnection, RconResult};
+use crate::config::{
+    RCON_DELAY, RCON_LOOP_DELAY, RCON_RECONNECT_MAX_DELAY, RCON_RECONNECT_MIN_DELAY,
+};
 use crate::models::app_settings::AppSettings;
-use crate::utils::BoxResult;
-use crate::{appbus::AppBus, tf2::rcon::g15_dumpplayer_parser::G15DumpPlayerParser};
+use crate::tf2::replay::recorder::SessionRecorder;
+use crate::{
+    appbus::{AppBus, AppEventMsg},
+    tf2::rcon::g15_dumpplayer_parser::G15DumpPlayerParser,
+};
 use bus::BusReader;
 use std::{
     sync::{Arc, Mutex},
     thread::{self, sleep},
+    time::{Duration, Instant},
 };
 
 /// Start the background thread for the rcon module
@@ -21,6 +27,19 @@ pub struct RconThread {
     bus: Arc<Mutex<AppBus>>,
     rcon_args: RConArgs,
     rcon_bus_rx: BusReader<String>,
+    app_event_bus_rx: BusReader<AppEventMsg>,
+    session_recorder: Option<SessionRecorder>,
+
+    /// Kept open between commands, None until connected and authorized
+    connection: Option<RConConnection>,
+    state: RconState,
+
+    /// When to try connecting again, and how long to wait after the next failure
+    next_connect: Instant,
+    reconnect_delay: Duration,
+
+    /// Set when the app exits
+    exit: bool,
 }
 
 impl RconThread {
@@ -31,11 +50,20 @@ impl RconThread {
         rcon_args.password.clone_from(&settings.rcon_password);
 
         let rcon_bus_rx = bus.lock().unwrap().rcon_bus.add_rx();
+        let app_event_bus_rx = bus.lock().unwrap().app_event_bus.add_rx();
+        let session_recorder = bus.lock().unwrap().session_recorder.clone();
 
         Self {
             bus: Arc::clone(bus),
             rcon_args,
             rcon_bus_rx,
+            app_event_bus_rx,
+            session_recorder,
+            connection: None,
+            state: RconState::Connecting,
+            next_connect: Instant::now(),
+            reconnect_delay: RCON_RECONNECT_MIN_DELAY,
+            exit: false,
         }
     }
 
@@ -44,8 +72,12 @@ impl RconThread {
 
         let mut g15_dumpplayer_parser = G15DumpPlayerParser::new();
 
-        loop {
+        while !self.exit {
             if let Some(reply) = self.send_rcon_command("g15_dumpplayer") {
+                if let Some(recorder) = &self.session_recorder {
+                    recorder.record_g15_dump(&reply);
+                }
+
                 // let start_time = std::time::Instant::now();
 
                 let parsed_data = g15_dumpplayer_parser.parse(&reply);
@@ -71,41 +103,107 @@ impl RconThread {
 
             sleep(RCON_LOOP_DELAY);
         }
+
+        log::info!("Rcon background thread stopped");
     }
 
-    fn send_rcon_command(&self, cmd: &str) -> Option<String> {
+    fn send_rcon_command(&mut self, cmd: &str) -> Option<String> {
+        if self.connection.is_none() && Instant::now() < self.next_connect {
+            log::debug!("RCON: not connected, skipping '{}'", cmd);
+            return None;
+        }
+
         match self.send_command_internal(cmd) {
             Ok(reply) => {
                 // log::info!("RCON '{}' replied: start'{}'end", cmd, reply);
                 // log::info!("RCON command '{}' replied with {} chars", cmd, reply.len());
 
+                self.reconnect_delay = RCON_RECONNECT_MIN_DELAY;
+                self.set_state(RconState::Connected);
+
                 Some(reply)
             }
             Err(error) => {
-                log::info!("RCON: '{}' failed: '{:?}'", cmd, error);
-                log::warn!("Could not talk to TF2 using RCON.");
+                log::debug!("RCON: '{}' failed: '{:?}'", cmd, error);
+
+                // Start over with a new connection, after a longer wait each time it fails
+                self.connection = None;
+                self.next_connect = Instant::now() + self.reconnect_delay;
+                self.reconnect_delay = next_reconnect_delay(self.reconnect_delay);
+                self.set_state(RconState::from_error(&error));
 
                 None
             }
         }
     }
 
+    /// Logs and broadcasts the state when it changes, so a missing TF2 doesn't fill the log
+    fn set_state(&mut self, state: RconState) {
+        if self.state == state {
+            return;
+        }
+
+        if state == RconState::Connected {
+            log::info!("RCON connected to TF2.");
+        } else {
+            log::warn!("Could not talk to TF2 using RCON: {}", state.description());
+        }
+
+        self.state = state.clone();
+        self.bus.lock().unwrap().rcon_state_bus.broadcast(state);
+    }
+
     fn process_bus(&mut self) {
         while let Ok(cmd) = self.rcon_bus_rx.try_recv() {
             self.send_rcon_command(&cmd);
         }
+
+        while let Ok(app_event) = self.app_event_bus_rx.try_recv() {
+            if let AppEventMsg::Exit = app_event {
+                self.exit = true;
+            }
+        }
     }
 
-    fn send_command_internal(&self, cmd: &str) -> BoxResult<String> {
+    fn send_command_internal(&mut self, cmd: &str) -> RconResult<String> {
         sleep(RCON_DELAY);
 
-        let mut rcon_client = RConConnection::new(&self.rcon_args)?;
+        let rcon_client = match &mut self.connection {
+            Some(rcon_client) => rcon_client,
+            None => {
+                let mut rcon_client = RConConnection::new(&self.rcon_args)?;
+
+                log::debug!("Sending RCON authorize");
+                rcon_client.authorize()?;
 
-        log::debug!("Sending RCON authorize: {}", cmd);
-        rcon_client.authorize()?;
+                self.connection.insert(rcon_client)
+            }
+        };
 
         log::debug!("Sending RCON command: {}", cmd);
         let reply = rcon_client.exec_command(cmd)?;
         Ok(reply)
     }
 }
+
+/// Doubles the wait between connection attempts, up to the maximum
+fn next_reconnect_delay(delay: Duration) -> Duration {
+    (delay * 2).min(RCON_RECONNECT_MAX_DELAY)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_next_reconnect_delay() {
+        let mut delay = RCON_RECONNECT_MIN_DELAY;
+        delay = next_reconnect_delay(delay);
+        assert_eq!(delay, RCON_RECONNECT_MIN_DELAY * 2);
+
+        for _ in 0..20 {
+            delay = next_reconnect_delay(delay);
+        }
+        assert_eq!(delay, RCON_RECONNECT_MAX_DELAY);
+    }
+}
@@ -0,0 +1,54 @@
+pub mod recorder;
+pub mod replay_thread;
+
+use crate::utils::BoxResult;
+use chrono::{DateTime, Local};
+use fs_err as fs;
+use serde::{Deserialize, Serialize};
+
+/// The folder where recorded sessions are saved
+pub const SESSIONS_DIR: &str = "sessions";
+
+/// One line of a session archive.
+/// The archive is a JSON lines file with the raw data from TF2 in the order it was read.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ArchiveEntry {
+    /// When the data was read, in milliseconds since the epoch
+    pub timestamp_ms: i64,
+
+    #[serde(flatten)]
+    pub data: ArchiveData,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ArchiveData {
+    /// A line from console.log, with its timestamp
+    LogLine { line: String },
+
+    /// The reply to g15_dumpplayer
+    G15Dump { reply: String },
+}
+
+impl ArchiveEntry {
+    pub fn new(when: DateTime<Local>, data: ArchiveData) -> Self {
+        Self {
+            timestamp_ms: when.timestamp_millis(),
+            data,
+        }
+    }
+}
+
+/// Reads all entries of a session archive
+pub fn load_archive(filename: &str) -> BoxResult<Vec<ArchiveEntry>> {
+    let text = fs::read_to_string(filename)?;
+
+    text.lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(i, line)| {
+            serde_json::from_str(line)
+                .map_err(|e| format!("{} line {}: {}", filename, i + 1, e).into())
+        })
+        .collect()
+}
@@ -0,0 +1,99 @@
+use super::{ArchiveData, ArchiveEntry};
+use crate::utils::BoxResult;
+use chrono::Local;
+use fs_err as fs;
+use std::{
+    io::Write,
+    path::Path,
+    sync::{Arc, Mutex},
+};
+
+/// Saves the raw console.log lines and g15_dumpplayer replies of a session to an archive,
+/// so it can be replayed later. Clones write to the same archive.
+#[derive(Clone)]
+pub struct SessionRecorder {
+    filename: String,
+    file: Arc<Mutex<fs::File>>,
+}
+
+impl SessionRecorder {
+    /// Creates a new archive in the folder, named after the current time
+    pub fn create(dir: &Path) -> BoxResult<Self> {
+        fs::create_dir_all(dir)?;
+
+        let filename = dir
+            .join(format!(
+                "session-{}.jsonl",
+                Local::now().format("%Y%m%d-%H%M%S")
+            ))
+            .to_string_lossy()
+            .to_string();
+        let file = fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&filename)?;
+
+        log::info!("Recording the session to {}", filename);
+
+        Ok(Self {
+            filename,
+            file: Arc::new(Mutex::new(file)),
+        })
+    }
+
+    pub fn record_log_line(&self, line: &str) {
+        self.record(ArchiveData::LogLine {
+            line: line.to_string(),
+        });
+    }
+
+    pub fn record_g15_dump(&self, reply: &str) {
+        self.record(ArchiveData::G15Dump {
+            reply: reply.to_string(),
+        });
+    }
+
+    fn record(&self, data: ArchiveData) {
+        let entry = ArchiveEntry::new(Local::now(), data);
+
+        let result = serde_json::to_string(&entry)
+            .map_err(|e| e.to_string())
+            .and_then(|json| {
+                let mut file = self.file.lock().unwrap();
+                writeln!(file, "{}", json).map_err(|e| e.to_string())
+            });
+        if let Err(e) = result {
+            log::error!("Failed to record the session to {}: {}", self.filename, e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tf2::replay::load_archive;
+
+    #[test]
+    fn test_record_and_load() {
+        let dir = tempfile::tempdir().unwrap();
+        let recorder = SessionRecorder::create(dir.path()).unwrap();
+
+        recorder.record_log_line("05/06/2024 - 17:02:55: Player1 connected");
+        recorder
+            .clone()
+            .record_g15_dump("(playerresource)\nm_szName[1] string (Player1)\n");
+
+        let entries = load_archive(&recorder.filename).unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(
+            entries[0].data,
+            ArchiveData::LogLine {
+                line: "05/06/2024 - 17:02:55: Player1 connected".to_string()
+            }
+        );
+        assert!(
+            matches!(&entries[1].data, ArchiveData::G15Dump { reply } if reply.contains("Player1"))
+        );
+        assert!(entries[0].timestamp_ms <= entries[1].timestamp_ms);
+    }
+}
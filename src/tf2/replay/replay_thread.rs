@@ -0,0 +1,183 @@
+use super::{ArchiveData, ArchiveEntry};
+use crate::appbus::{AppBus, AppEventMsg};
+use crate::config::REPLAY_LOOP_DELAY;
+use crate::tf2::logfile::line_parser::LogLineParser;
+use crate::tf2::rcon::g15_dumpplayer_parser::G15DumpPlayerParser;
+use bus::BusReader;
+use std::{
+    sync::{Arc, Mutex},
+    thread::{self, sleep},
+    time::{Duration, Instant},
+};
+
+/// Start the thread that replays a recorded session instead of the logfile watcher and RCON threads.
+/// A speed of 2.0 replays twice as fast as it was recorded.
+pub fn start(
+    archive: Vec<ArchiveEntry>,
+    speed: f64,
+    bus: &Arc<Mutex<AppBus>>,
+) -> thread::JoinHandle<()> {
+    let mut replay_thread = ReplayThread::new(archive, speed, bus);
+
+    thread::spawn(move || replay_thread.run())
+}
+
+pub struct ReplayThread {
+    bus: Arc<Mutex<AppBus>>,
+    rcon_bus_rx: BusReader<String>,
+    app_event_bus_rx: BusReader<AppEventMsg>,
+    archive: Vec<ArchiveEntry>,
+    speed: f64,
+
+    /// Set when the app exits
+    exit: bool,
+}
+
+impl ReplayThread {
+    pub fn new(archive: Vec<ArchiveEntry>, speed: f64, bus: &Arc<Mutex<AppBus>>) -> Self {
+        let rcon_bus_rx = bus.lock().unwrap().rcon_bus.add_rx();
+        let app_event_bus_rx = bus.lock().unwrap().app_event_bus.add_rx();
+
+        Self {
+            bus: Arc::clone(bus),
+            rcon_bus_rx,
+            app_event_bus_rx,
+            archive,
+            speed,
+            exit: false,
+        }
+    }
+
+    pub fn run(&mut self) {
+        log::info!(
+            "Replay background thread started, replaying {} entries at {}x speed",
+            self.archive.len(),
+            self.speed
+        );
+
+        let parser = LogLineParser::default();
+        let mut g15_dumpplayer_parser = G15DumpPlayerParser::new();
+
+        let archive = std::mem::take(&mut self.archive);
+        let first = archive.first().map(|entry| entry.timestamp_ms).unwrap_or(0);
+        let started = Instant::now();
+
+        for entry in archive {
+            let due = replay_offset(entry.timestamp_ms - first, self.speed);
+            while !self.exit && started.elapsed() < due {
+                self.process_bus();
+                sleep(REPLAY_LOOP_DELAY.min(due.saturating_sub(started.elapsed())));
+            }
+            if self.exit {
+                break;
+            }
+
+            match entry.data {
+                ArchiveData::LogLine { line } => {
+                    if let Some(msg) = parser.parse_line(&line) {
+                        self.bus.lock().unwrap().send_logline(msg);
+                    }
+                }
+                ArchiveData::G15Dump { reply } => {
+                    let parsed_data = g15_dumpplayer_parser.parse(&reply);
+                    if !parsed_data.players.is_empty() {
+                        self.bus
+                            .lock()
+                            .unwrap()
+                            .g15_report_bus
+                            .broadcast(parsed_data);
+                    }
+                }
+            }
+        }
+
+        if !self.exit {
+            log::info!("Replay finished");
+        }
+
+        while !self.exit {
+            self.process_bus();
+            sleep(REPLAY_LOOP_DELAY);
+        }
+
+        log::info!("Replay background thread stopped");
+    }
+
+    fn process_bus(&mut self) {
+        // There is no TF2 to send commands to, but the bus must not fill up
+        while let Ok(cmd) = self.rcon_bus_rx.try_recv() {
+            log::info!("Replay: not sending RCON command '{}'", cmd);
+        }
+
+        while let Ok(app_event) = self.app_event_bus_rx.try_recv() {
+            if let AppEventMsg::Exit = app_event {
+                self.exit = true;
+            }
+        }
+    }
+}
+
+/// How long after the start of the replay an entry is replayed
+fn replay_offset(offset_ms: i64, speed: f64) -> Duration {
+    Duration::from_secs_f64(offset_ms.max(0) as f64 / 1000.0 / speed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tf2::logfile::LogLine;
+    use chrono::{Local, TimeZone};
+
+    #[test]
+    fn test_replay_offset() {
+        assert_eq!(replay_offset(2000, 1.0), Duration::from_secs(2));
+        assert_eq!(replay_offset(2000, 4.0), Duration::from_millis(500));
+        assert_eq!(replay_offset(-10, 1.0), Duration::ZERO);
+    }
+
+    #[test]
+    fn test_replay() {
+        let when = Local.with_ymd_and_hms(2024, 5, 6, 17, 2, 55).unwrap();
+        let archive = vec![
+            ArchiveEntry::new(
+                when,
+                ArchiveData::G15Dump {
+                    reply: "(playerresource)\n\
+                        m_szName[1] string (Player1)\n\
+                        m_bConnected[1] bool (true)\n\
+                        m_bValid[1] bool (true)\n\
+                        m_iAccountID[1] integer (52079950)\n"
+                        .to_string(),
+                },
+            ),
+            ArchiveEntry::new(
+                when + Duration::from_secs(10),
+                ArchiveData::LogLine {
+                    line: "05/06/2024 - 17:03:05: Player1 connected".to_string(),
+                },
+            ),
+        ];
+
+        let bus = Arc::new(Mutex::new(AppBus::default()));
+        let mut logfile_rx = bus.lock().unwrap().logfile_bus.add_rx();
+        let mut g15_rx = bus.lock().unwrap().g15_report_bus.add_rx();
+
+        // 10 seconds in 10 milliseconds
+        let replay_thread = start(archive, 1000.0, &bus);
+        bus.lock()
+            .unwrap()
+            .send_rcon_cmd("callvote kick \"2 cheating\"");
+
+        let g15 = g15_rx.recv_timeout(Duration::from_secs(5)).unwrap();
+        assert_eq!(g15.players[0].name, "Player1");
+
+        let line = logfile_rx.recv_timeout(Duration::from_secs(5)).unwrap();
+        assert!(matches!(line, LogLine::PlayerConnected { name, .. } if name == "Player1"));
+
+        bus.lock()
+            .unwrap()
+            .app_event_bus
+            .broadcast(AppEventMsg::Exit);
+        replay_thread.join().unwrap();
+    }
+}
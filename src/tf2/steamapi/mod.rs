@@ -43,13 +43,14 @@ pub struct SteamApi {
 #[derive(Debug, Clone, Deserialize)]
 pub struct SteamApiPlayer {
     pub steamid: String,
-    // pub personaname: String,
+    #[serde(default)]
+    pub personaname: String,
     pub communityvisibilitystate: u8,
     // profileurl: String,
     pub avatar: String,
     // pub avatarmedium: String,
     pub avatarfull: String,
-    // avatarhash: String,
+    pub avatarhash: Option<String>,
     pub timecreated: Option<u64>,
 }
 
@@ -105,6 +106,14 @@ impl SteamApi {
 }
 
 impl SteamApiPlayer {
+    /// The avatar hash from the API, or from the avatar URL if the API didn't send one
+    pub fn get_avatar_hash(&self) -> Option<String> {
+        match &self.avatarhash {
+            Some(avatarhash) if is_avatar_hash(avatarhash) => Some(avatarhash.to_ascii_lowercase()),
+            _ => avatar_hash_from_url(&self.avatarfull),
+        }
+    }
+
     pub fn get_account_age(&self) -> Option<DateTime<Local>> {
         self.timecreated?;
 
@@ -115,3 +124,73 @@ impl SteamApiPlayer {
         }
     }
 }
+
+/// Gets the avatar hash from a Steam avatar URL such as
+/// https://avatars.steamstatic.com/fef49e7fa7e1997310d705b2a6158ff8dc1cdfeb_full.jpg
+/// The hash is the same for the thumb, medium and full size images.
+pub fn avatar_hash_from_url(url: &str) -> Option<String> {
+    let filename = url.rsplit('/').next()?;
+    let name = filename.split('.').next()?;
+    let hash = name.trim_end_matches("_full").trim_end_matches("_medium");
+
+    if is_avatar_hash(hash) {
+        Some(hash.to_ascii_lowercase())
+    } else {
+        None
+    }
+}
+
+/// Avatar hashes are SHA-1 hashes, 40 hex digits
+fn is_avatar_hash(hash: &str) -> bool {
+    hash.len() == 40 && hash.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_avatar_hash_from_url() {
+        let hash = "fef49e7fa7e1997310d705b2a6158ff8dc1cdfeb";
+        let urls = [
+            "https://avatars.steamstatic.com/fef49e7fa7e1997310d705b2a6158ff8dc1cdfeb.jpg",
+            "https://avatars.steamstatic.com/fef49e7fa7e1997310d705b2a6158ff8dc1cdfeb_medium.jpg",
+            "https://avatars.steamstatic.com/fef49e7fa7e1997310d705b2a6158ff8dc1cdfeb_full.jpg",
+            "https://steamcdn-a.akamaihd.net/steamcommunity/public/images/avatars/fe/fef49e7fa7e1997310d705b2a6158ff8dc1cdfeb_full.jpg",
+        ];
+        for url in urls {
+            assert_eq!(avatar_hash_from_url(url).as_deref(), Some(hash), "{}", url);
+        }
+
+        assert_eq!(avatar_hash_from_url(""), None);
+        assert_eq!(
+            avatar_hash_from_url("https://example.com/avatar_full.jpg"),
+            None
+        );
+    }
+
+    #[test]
+    fn test_get_avatar_hash() {
+        let mut player = SteamApiPlayer {
+            steamid: "76561198398458549".to_string(),
+            personaname: String::new(),
+            communityvisibilitystate: 3,
+            avatar: String::new(),
+            avatarfull:
+                "https://avatars.steamstatic.com/fef49e7fa7e1997310d705b2a6158ff8dc1cdfeb_full.jpg"
+                    .to_string(),
+            avatarhash: Some("0123456789ABCDEF0123456789ABCDEF01234567".to_string()),
+            timecreated: None,
+        };
+        assert_eq!(
+            player.get_avatar_hash().as_deref(),
+            Some("0123456789abcdef0123456789abcdef01234567")
+        );
+
+        player.avatarhash = None;
+        assert_eq!(
+            player.get_avatar_hash().as_deref(),
+            Some("fef49e7fa7e1997310d705b2a6158ff8dc1cdfeb")
+        );
+    }
+}
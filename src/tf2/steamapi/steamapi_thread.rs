@@ -6,14 +6,16 @@ use crate::config::{
     STEAMAPI_RETRY_DELAY,
 };
 use crate::db::db::DbPool;
-use crate::db::entities::{Game, NewAccount, NewComment, NewFriendship, NewPlaytime};
+use crate::db::entities::{
+    Game, NAME_SOURCE_STEAM, NewAccount, NewComment, NewFriendship, NewNameHistory, NewPlaytime,
+};
 use crate::db::queries;
 use crate::{
     appbus::AppBus,
     models::{app_settings::AppSettings, steamid::SteamID},
     tf2::{
         lobby::{AccountAge, Lobby, Player, PlayerSteamInfo, Tf2PlayMinutes},
-        steamapi::SteamApiMsg,
+        steamapi::{SteamApiMsg, avatar_hash_from_url},
     },
 };
 use chrono::Utc;
@@ -110,6 +112,10 @@ impl SteamApiThread {
                             public_profile: account.public_profile,
                             avatar_thumb: account.avatar_thumb_url.clone(),
                             avatar_full: account.avatar_full_url.clone(),
+                            avatar_hash: account
+                                .avatar_hash
+                                .clone()
+                                .or_else(|| avatar_hash_from_url(&account.avatar_full_url)),
                             account_age,
                         };
 
@@ -142,6 +148,8 @@ impl SteamApiThread {
                     if let Some(steamid) = SteamID::from_u64_string(&info.steamid) {
                         let public_profile = matches!(info.communityvisibilitystate, 3);
                         let account_age = info.get_account_age();
+                        let avatar_hash = info.get_avatar_hash();
+                        let steam_name = info.personaname.clone();
 
                         let info = PlayerSteamInfo {
                             steamid,
@@ -150,6 +158,7 @@ impl SteamApiThread {
                             avatar_thumb: info.avatar.clone(),
                             // avatarmedium: info.avatarmedium.clone(),
                             avatar_full: info.avatarfull.clone(),
+                            avatar_hash,
                             account_age: account_age.clone(),
                         };
 
@@ -179,6 +188,7 @@ impl SteamApiThread {
                                 playtimes_fetched: None,
                                 reputation_fetched: None,
                                 steam_bans_last_fetched: None,
+                                avatar_hash: info.avatar_hash.clone(),
                             };
 
                             if let Err(e) = queries::upsert_account(&mut conn, new_account) {
@@ -188,6 +198,24 @@ impl SteamApiThread {
                                     e
                                 );
                             }
+
+                            if !steam_name.is_empty() {
+                                let new_name = NewNameHistory {
+                                    steam_id: steamid.to_u64() as i64,
+                                    name: steam_name,
+                                    source: NAME_SOURCE_STEAM.to_string(),
+                                    seen: Utc::now().timestamp(),
+                                    lobby_id: None,
+                                    mid_match: false,
+                                };
+                                if let Err(e) = queries::record_name(&mut conn, &new_name) {
+                                    log::error!(
+                                        "Failed to record Steam name of {}: {}",
+                                        steamid.to_u64(),
+                                        e
+                                    );
+                                }
+                            }
                         }
                     }
                 }
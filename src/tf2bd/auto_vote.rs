@@ -0,0 +1,144 @@
+use crate::{
+    models::app_settings::AppSettings,
+    tf2::lobby::{Lobby, votes::LobbyVote},
+};
+
+/// How to answer a kick vote called by someone else: Some(true) for F1, Some(false) for F2,
+/// and None to leave it to the user. Only trusted flags make us vote yes.
+pub fn auto_vote_choice(lobby: &Lobby, vote: &LobbyVote, settings: &AppSettings) -> Option<bool> {
+    if !settings.auto_vote || !vote.is_active() || vote.my_vote.is_some() {
+        return None;
+    }
+
+    if vote.caller == Some(lobby.self_steamid) {
+        return None;
+    }
+
+    // Only kick votes
+    let target = vote.target?;
+
+    if target == lobby.self_steamid {
+        return settings.auto_vote_no_on_self.then_some(false);
+    }
+
+    if settings.auto_vote_no_on_friends && lobby.friendships.are_friends(lobby.self_steamid, target)
+    {
+        return Some(false);
+    }
+
+    let player_info = lobby.get_player(None, Some(target))?.player_info.as_ref()?;
+    player_info
+        .attributes
+        .iter()
+        .any(|attribute| settings.auto_vote_yes_on.contains(attribute))
+        .then_some(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        models::steamid::SteamID,
+        tf2::lobby::Player,
+        tf2bd::models::{PlayerAttribute, PlayerInfo},
+    };
+    use chrono::Local;
+
+    const ME: u64 = 76561198012345678;
+    const PLAYER: u64 = 76561198000000001;
+    const CHEATER: u64 = 76561198000000002;
+
+    fn lobby() -> Lobby {
+        let mut lobby = Lobby::new(SteamID::from_u64(ME));
+        lobby.players.push(Player {
+            steamid: SteamID::from_u64(PLAYER),
+            name: "Player1".to_string(),
+            ..Default::default()
+        });
+        lobby.players.push(Player {
+            steamid: SteamID::from_u64(CHEATER),
+            name: "Cheater".to_string(),
+            player_info: Some(PlayerInfo {
+                attributes: vec![PlayerAttribute::Cheater],
+                ..Default::default()
+            }),
+            ..Default::default()
+        });
+        lobby
+    }
+
+    fn kick_vote(caller: u64, target: u64) -> LobbyVote {
+        LobbyVote {
+            started: Local::now(),
+            caller_name: String::new(),
+            caller: Some(SteamID::from_u64(caller)),
+            issue: String::new(),
+            target: Some(SteamID::from_u64(target)),
+            yes: 0,
+            no: 0,
+            my_vote: None,
+            result: None,
+            ended: None,
+        }
+    }
+
+    fn settings() -> AppSettings {
+        AppSettings {
+            auto_vote: true,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_auto_vote_choice() {
+        let lobby = lobby();
+        let settings = settings();
+
+        assert_eq!(
+            auto_vote_choice(&lobby, &kick_vote(PLAYER, CHEATER), &settings),
+            Some(true)
+        );
+        assert_eq!(
+            auto_vote_choice(&lobby, &kick_vote(CHEATER, ME), &settings),
+            Some(false)
+        );
+        assert_eq!(
+            auto_vote_choice(&lobby, &kick_vote(CHEATER, PLAYER), &settings),
+            None
+        );
+
+        // Our own votes and votes we already answered
+        assert_eq!(
+            auto_vote_choice(&lobby, &kick_vote(ME, CHEATER), &settings),
+            None
+        );
+        let mut answered = kick_vote(PLAYER, CHEATER);
+        answered.my_vote = Some(false);
+        assert_eq!(auto_vote_choice(&lobby, &answered, &settings), None);
+    }
+
+    #[test]
+    fn test_auto_vote_policies() {
+        let lobby = lobby();
+
+        let off = AppSettings::default();
+        assert_eq!(
+            auto_vote_choice(&lobby, &kick_vote(PLAYER, CHEATER), &off),
+            None
+        );
+
+        let mut only_bots = settings();
+        only_bots.auto_vote_yes_on = vec![PlayerAttribute::Bot];
+        assert_eq!(
+            auto_vote_choice(&lobby, &kick_vote(PLAYER, CHEATER), &only_bots),
+            None
+        );
+
+        let mut no_self = settings();
+        no_self.auto_vote_no_on_self = false;
+        assert_eq!(
+            auto_vote_choice(&lobby, &kick_vote(CHEATER, ME), &no_self),
+            None
+        );
+    }
+}
@@ -0,0 +1,734 @@
+//
+// Exports the player_flags table to a TF2BD v3 playerlist and imports
+// TF2BD v3 playerlists into it, so curated lists can be shared with others.
+//
+
+use super::{
+    flag_proof::proof_text,
+    formats::{self, AttributeMapping, ListFormat},
+    models::{FileInfo, PlayerAttribute, PlayerInfo, PlayerLastSeen, TF2BDPlayerList},
+};
+use crate::{
+    db::{
+        entities::{NewPlayerFlag, NewPlayerFlagProof, PlayerFlag, PlayerFlagProof},
+        queries,
+    },
+    models::steamid::SteamID,
+    utils::BoxResult,
+};
+use chrono::Utc;
+use diesel::{Connection, SqliteConnection};
+use fs_err as fs;
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    fmt,
+    path::Path,
+};
+
+/// Every attribute that is stored in player_flags. The flag_type is the Debug name of the attribute.
+const FLAG_ATTRIBUTES: [PlayerAttribute; 6] = [
+    PlayerAttribute::Cheater,
+    PlayerAttribute::Bot,
+    PlayerAttribute::Suspicious,
+    PlayerAttribute::Toxic,
+    PlayerAttribute::Exploiter,
+    PlayerAttribute::Cool,
+];
+
+/// Which flags to export
+#[derive(Debug, Default, Clone)]
+pub struct ExportFilter {
+    /// Only flags with these attributes, all attributes when empty
+    pub attributes: HashSet<PlayerAttribute>,
+
+    /// Only flags whose source contains this text, all sources when empty
+    pub source: String,
+
+    /// Only flags last seen at or after this UnixTime
+    pub seen_since: Option<i64>,
+
+    /// Also flags from suggestion lists and rules, which are not confirmed
+    pub include_suggestions: bool,
+}
+
+impl ExportFilter {
+    fn matches(&self, flag: &PlayerFlag, attribute: PlayerAttribute) -> bool {
+        if flag.suggestion && !self.include_suggestions {
+            return false;
+        }
+
+        if !self.attributes.is_empty() && !self.attributes.contains(&attribute) {
+            return false;
+        }
+
+        let source = self.source.trim();
+        if !source.is_empty() && !flag.source.to_lowercase().contains(&source.to_lowercase()) {
+            return false;
+        }
+
+        self.seen_since
+            .is_none_or(|seen_since| flag.last_seen >= seen_since)
+    }
+}
+
+/// What to do when a player already has flags from the source that is imported
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum ConflictPolicy {
+    /// Leave the player's existing flags as they are
+    #[default]
+    Keep,
+
+    /// Replace the player's existing flags with the ones in the list
+    Overwrite,
+
+    /// Add the flags in the list to the existing ones
+    Merge,
+}
+
+impl ConflictPolicy {
+    pub const ALL: [ConflictPolicy; 3] = [
+        ConflictPolicy::Keep,
+        ConflictPolicy::Overwrite,
+        ConflictPolicy::Merge,
+    ];
+
+    pub fn parse(name: &str) -> Option<Self> {
+        ConflictPolicy::ALL
+            .into_iter()
+            .find(|policy| policy.to_string().eq_ignore_ascii_case(name.trim()))
+    }
+}
+
+impl fmt::Display for ConflictPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConflictPolicy::Keep => write!(f, "keep"),
+            ConflictPolicy::Overwrite => write!(f, "overwrite"),
+            ConflictPolicy::Merge => write!(f, "merge"),
+        }
+    }
+}
+
+/// What an import did
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct ImportSummary {
+    /// Players that got at least one flag written
+    pub players: usize,
+    pub flags_written: usize,
+    pub flags_removed: usize,
+    pub proofs_written: usize,
+
+    /// Players that were skipped because they already had flags from the source
+    pub kept: usize,
+
+    /// Entries with a SteamID that could not be parsed
+    pub invalid: usize,
+}
+
+impl fmt::Display for ImportSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} players imported ({} flags written, {} removed, {} proofs), {} kept, {} invalid",
+            self.players,
+            self.flags_written,
+            self.flags_removed,
+            self.proofs_written,
+            self.kept,
+            self.invalid
+        )
+    }
+}
+
+/// Changes to player_flags that an import makes
+#[derive(Debug, Default)]
+struct ImportPlan {
+    writes: Vec<NewPlayerFlag>,
+
+    /// steam_id and flag_type of flags to remove from the source
+    removals: Vec<(i64, String)>,
+
+    /// Proofs in the list that are not stored yet
+    proofs: Vec<NewPlayerFlagProof>,
+
+    summary: ImportSummary,
+}
+
+/// The attribute of a player_flags flag_type, None for flag types TF2Monitor doesn't know
+pub fn flag_attribute(flag_type: &str) -> Option<PlayerAttribute> {
+    FLAG_ATTRIBUTES
+        .into_iter()
+        .find(|attribute| format!("{:?}", attribute).eq_ignore_ascii_case(flag_type))
+}
+
+/// Exports the matching flags of all sources as a TF2BD v3 playerlist
+pub fn export_player_flags(
+    conn: &mut SqliteConnection,
+    filter: &ExportFilter,
+    file_info: FileInfo,
+) -> Result<TF2BDPlayerList, diesel::result::Error> {
+    let flags = queries::get_all_player_flags(conn)?;
+    let proofs = queries::get_all_player_flag_proofs(conn)?;
+    let names = queries::get_all_accounts(conn)?
+        .into_iter()
+        .map(|account| (account.steam_id, account.name))
+        .collect();
+
+    Ok(build_playerlist(&flags, &proofs, &names, filter, file_info))
+}
+
+/// Exports the matching flags to a file. Returns the number of exported players.
+pub fn export_to_file(
+    conn: &mut SqliteConnection,
+    filter: &ExportFilter,
+    file_info: FileInfo,
+    filename: &str,
+    format: ListFormat,
+) -> BoxResult<usize> {
+    let playerlist = export_player_flags(conn, filter, file_info)?;
+    let json = formats::to_json_string(&playerlist, format)?;
+    fs::write(filename, json)?;
+
+    log::info!(
+        "Exported {} players to {}",
+        playerlist.players.len(),
+        filename
+    );
+    Ok(playerlist.players.len())
+}
+
+/// The title of the list, or the filename when the list has no title
+pub fn default_import_source(filename: &str) -> String {
+    let title = formats::load(filename, None, &AttributeMapping::new())
+        .ok()
+        .and_then(|(playerlist, _)| playerlist.file_info)
+        .map(|file_info| file_info.title)
+        .filter(|title| !title.trim().is_empty());
+
+    title.unwrap_or_else(|| {
+        Path::new(filename)
+            .file_name()
+            .map(|x| x.to_string_lossy().to_string())
+            .unwrap_or_else(|| filename.to_string())
+    })
+}
+
+/// Imports the players in a playerlist file as flags from `source`.
+/// The format is detected from the content when it is not given.
+pub fn import_from_file(
+    conn: &mut SqliteConnection,
+    filename: &str,
+    source: &str,
+    policy: ConflictPolicy,
+    format: Option<ListFormat>,
+    mapping: &AttributeMapping,
+) -> BoxResult<ImportSummary> {
+    if source.trim().is_empty() {
+        return Err("The source name is empty".into());
+    }
+
+    // formats::load gives an empty list for a missing file
+    if !Path::new(filename).exists() {
+        return Err(format!("File {} does not exist", filename).into());
+    }
+
+    let (playerlist, _format) = formats::load(filename, format, mapping)?;
+    let summary = import_playerlist(conn, &playerlist, source.trim(), policy)?;
+
+    log::info!("Imported {} as {}: {}", filename, source, summary);
+    Ok(summary)
+}
+
+/// Imports the players in the list as flags from `source`, in one transaction
+pub fn import_playerlist(
+    conn: &mut SqliteConnection,
+    playerlist: &TF2BDPlayerList,
+    source: &str,
+    policy: ConflictPolicy,
+) -> Result<ImportSummary, diesel::result::Error> {
+    conn.transaction(|conn| {
+        let existing = queries::get_player_flags_by_source(conn, source)?;
+        let existing_proofs = queries::get_player_flag_proofs_by_source(conn, source)?;
+        let plan = plan_import(
+            playerlist,
+            &existing,
+            &existing_proofs,
+            source,
+            policy,
+            Utc::now().timestamp(),
+        );
+
+        for (steam_id, flag_type) in &plan.removals {
+            queries::remove_player_flag(conn, *steam_id, flag_type, source)?;
+        }
+
+        for new_flag in plan.writes {
+            queries::replace_player_flag(conn, new_flag)?;
+        }
+
+        for new_proof in &plan.proofs {
+            queries::insert_player_flag_proof(conn, new_proof)?;
+        }
+
+        Ok(plan.summary)
+    })
+}
+
+fn build_playerlist(
+    flags: &[PlayerFlag],
+    proofs: &[PlayerFlagProof],
+    names: &HashMap<i64, String>,
+    filter: &ExportFilter,
+    file_info: FileInfo,
+) -> TF2BDPlayerList {
+    // steam_id -> (attributes, last_seen), ordered by steam_id so exports are stable
+    let mut players: BTreeMap<i64, (Vec<PlayerAttribute>, i64)> = BTreeMap::new();
+
+    // Only the proofs of exported flags are exported
+    let mut exported_flags: HashSet<(i64, &str, &str)> = HashSet::new();
+
+    for flag in flags {
+        let Some(attribute) = flag_attribute(&flag.flag_type) else {
+            continue;
+        };
+
+        if !filter.matches(flag, attribute) {
+            continue;
+        }
+
+        exported_flags.insert((flag.steam_id, &flag.flag_type, &flag.source));
+
+        let (attributes, last_seen) = players.entry(flag.steam_id).or_insert((Vec::new(), 0));
+        if !attributes.contains(&attribute) {
+            attributes.push(attribute);
+        }
+        *last_seen = (*last_seen).max(flag.last_seen);
+    }
+
+    let players = players
+        .into_iter()
+        .map(|(steam_id, (mut attributes, time))| {
+            attributes.sort_by_key(|attribute| FLAG_ATTRIBUTES.iter().position(|x| x == attribute));
+
+            let mut proof: Vec<String> = Vec::new();
+            for flag_proof in proofs.iter().filter(|flag_proof| {
+                flag_proof.steam_id == steam_id
+                    && exported_flags.contains(&(
+                        steam_id,
+                        flag_proof.flag_type.as_str(),
+                        flag_proof.source.as_str(),
+                    ))
+            }) {
+                let text = proof_text(flag_proof);
+                if !text.is_empty() && !proof.contains(&text) {
+                    proof.push(text);
+                }
+            }
+
+            PlayerInfo {
+                attributes,
+                unknown_attributes: Vec::new(),
+                proof,
+                last_seen: Some(PlayerLastSeen {
+                    player_name: names
+                        .get(&steam_id)
+                        .filter(|name| !name.is_empty())
+                        .cloned(),
+                    time,
+                }),
+                steamid32: SteamID::from_u64(steam_id as u64).to_steam_id32(),
+            }
+        })
+        .collect();
+
+    TF2BDPlayerList {
+        file_info: Some(file_info),
+        players,
+        ..TF2BDPlayerList::new()
+    }
+}
+
+fn plan_import(
+    playerlist: &TF2BDPlayerList,
+    existing: &[PlayerFlag],
+    existing_proofs: &[PlayerFlagProof],
+    source: &str,
+    policy: ConflictPolicy,
+    now: i64,
+) -> ImportPlan {
+    let mut existing_by_steamid: HashMap<i64, Vec<&PlayerFlag>> = HashMap::new();
+    for flag in existing {
+        existing_by_steamid
+            .entry(flag.steam_id)
+            .or_default()
+            .push(flag);
+    }
+
+    let mut plan = ImportPlan::default();
+
+    for player in &playerlist.players {
+        let Some(steamid) = SteamID::parse(&player.steamid32) else {
+            log::warn!("Skipping invalid SteamID '{}'", player.steamid32);
+            plan.summary.invalid += 1;
+            continue;
+        };
+        let steam_id = steamid.to_u64() as i64;
+
+        let mut flag_types: Vec<String> = Vec::new();
+        for attribute in &player.attributes {
+            let flag_type = format!("{:?}", attribute);
+            if !flag_types.contains(&flag_type) {
+                flag_types.push(flag_type);
+            }
+        }
+
+        if flag_types.is_empty() {
+            continue;
+        }
+
+        let current = existing_by_steamid
+            .get(&steam_id)
+            .map(Vec::as_slice)
+            .unwrap_or_default();
+
+        match policy {
+            ConflictPolicy::Keep if !current.is_empty() => {
+                plan.summary.kept += 1;
+                continue;
+            }
+            ConflictPolicy::Overwrite => {
+                for flag in current {
+                    if !flag_types.contains(&flag.flag_type) {
+                        plan.removals.push((steam_id, flag.flag_type.clone()));
+                        plan.summary.flags_removed += 1;
+                    }
+                }
+            }
+            _ => {}
+        }
+
+        let time = player
+            .last_seen
+            .as_ref()
+            .map_or(now, |last_seen| last_seen.time);
+
+        // Proofs in a list are not tied to an attribute, they are stored with the first flag
+        for text in player
+            .proof
+            .iter()
+            .map(|x| x.trim())
+            .filter(|x| !x.is_empty())
+        {
+            let stored = existing_proofs
+                .iter()
+                .any(|proof| proof.steam_id == steam_id && proof.reason.as_deref() == Some(text))
+                || plan.proofs.iter().any(|proof| {
+                    proof.steam_id == steam_id && proof.reason.as_deref() == Some(text)
+                });
+            if stored {
+                continue;
+            }
+
+            plan.proofs.push(NewPlayerFlagProof {
+                steam_id,
+                flag_type: flag_types[0].clone(),
+                source: source.to_string(),
+                created: now,
+                reason: Some(text.to_string()),
+                ..Default::default()
+            });
+            plan.summary.proofs_written += 1;
+        }
+
+        for flag_type in flag_types {
+            let previous = current.iter().find(|flag| flag.flag_type == flag_type);
+
+            let (first_seen, last_seen) = match previous {
+                Some(previous) if policy == ConflictPolicy::Merge => {
+                    (previous.first_seen.min(time), previous.last_seen.max(time))
+                }
+                _ => (time, time),
+            };
+
+            plan.writes.push(NewPlayerFlag {
+                steam_id,
+                flag_type,
+                source: source.to_string(),
+                first_seen,
+                last_seen,
+                // Don't notify again about flags the user has already been told about
+                notified: previous.is_some_and(|previous| previous.notified),
+                suggestion: false,
+                from_marking: false,
+            });
+            plan.summary.flags_written += 1;
+        }
+
+        plan.summary.players += 1;
+    }
+
+    plan
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Reads a v3 list without the format adapters, which drop players with invalid SteamIDs
+    fn from_json_str(json: &str) -> TF2BDPlayerList {
+        serde_json::from_str(json).unwrap()
+    }
+
+    fn flag(
+        steam_id: i64,
+        flag_type: &str,
+        source: &str,
+        first_seen: i64,
+        last_seen: i64,
+    ) -> PlayerFlag {
+        PlayerFlag {
+            steam_id,
+            flag_type: flag_type.to_string(),
+            source: source.to_string(),
+            first_seen,
+            last_seen,
+            notified: true,
+            suggestion: false,
+            from_marking: false,
+        }
+    }
+
+    fn proof(steam_id: i64, flag_type: &str, source: &str, reason: &str) -> PlayerFlagProof {
+        PlayerFlagProof {
+            id: 0,
+            steam_id,
+            flag_type: flag_type.to_string(),
+            source: source.to_string(),
+            created: 100,
+            reason: Some(reason.to_string()),
+            lobby_id: None,
+            map: None,
+            server: None,
+            chat: String::new(),
+            kills: 0,
+            deaths: 0,
+            crit_kills: 0,
+            kills_with: String::new(),
+        }
+    }
+
+    fn file_info() -> FileInfo {
+        FileInfo {
+            authors: vec!["Team".to_string()],
+            description: "Curated list".to_string(),
+            title: "Team list".to_string(),
+            update_url: String::new(),
+        }
+    }
+
+    const PLAYER_1: i64 = 76561197960287930;
+    const PLAYER_2: i64 = 76561198012345678;
+
+    #[test]
+    fn test_export_filter_and_names() {
+        let flags = vec![
+            flag(PLAYER_1, "Cheater", "playerlist.json", 100, 200),
+            flag(PLAYER_1, "Bot", "bot rule", 100, 300),
+            flag(PLAYER_1, "Cheater", "manual", 100, 250),
+            flag(PLAYER_2, "Toxic", "manual", 100, 150),
+            flag(PLAYER_2, "Unknown", "manual", 100, 150),
+        ];
+        let names = HashMap::from([(PLAYER_1, "Cheater Name".to_string())]);
+        let proofs = vec![
+            proof(PLAYER_1, "Cheater", "manual", "aimbot"),
+            proof(PLAYER_1, "Bot", "bot rule", "bot name"),
+            proof(PLAYER_1, "Cheater", "playerlist.json", "aimbot"),
+            proof(PLAYER_2, "Unknown", "manual", "not exported"),
+        ];
+
+        let playerlist = build_playerlist(
+            &flags,
+            &proofs,
+            &names,
+            &ExportFilter::default(),
+            file_info(),
+        );
+        assert_eq!(playerlist.players.len(), 2);
+        assert_eq!(playerlist.file_info.as_ref().unwrap().title, "Team list");
+        assert!(playerlist.schema.contains("/v3/"));
+
+        let player = &playerlist.players[0];
+        assert_eq!(player.steamid32, "[U:1:22202]");
+        assert_eq!(
+            player.attributes,
+            vec![PlayerAttribute::Cheater, PlayerAttribute::Bot]
+        );
+        assert_eq!(
+            player.last_seen,
+            Some(PlayerLastSeen {
+                player_name: Some("Cheater Name".to_string()),
+                time: 300,
+            })
+        );
+        assert_eq!(player.proof, vec!["aimbot", "bot name"]);
+        assert_eq!(
+            playerlist.players[1].attributes,
+            vec![PlayerAttribute::Toxic]
+        );
+        assert!(playerlist.players[1].proof.is_empty());
+
+        let filter = ExportFilter {
+            attributes: HashSet::from([PlayerAttribute::Cheater, PlayerAttribute::Toxic]),
+            source: "MANUAL".to_string(),
+            seen_since: Some(200),
+            include_suggestions: false,
+        };
+        let playerlist = build_playerlist(&flags, &proofs, &names, &filter, file_info());
+        assert_eq!(playerlist.players.len(), 1);
+        assert_eq!(
+            playerlist.players[0].attributes,
+            vec![PlayerAttribute::Cheater]
+        );
+        assert_eq!(playerlist.players[0].proof, vec!["aimbot"]);
+        assert_eq!(playerlist.players[0].last_seen.as_ref().unwrap().time, 250);
+
+        // The export can be read back as a TF2BD list
+        let json = serde_json::to_string_pretty(&playerlist).unwrap();
+        let reloaded = from_json_str(&json);
+        assert_eq!(reloaded.players, playerlist.players);
+    }
+
+    #[test]
+    fn test_export_suggestions() {
+        let flags = vec![
+            flag(PLAYER_1, "Cheater", "manual", 100, 200),
+            PlayerFlag {
+                suggestion: true,
+                ..flag(PLAYER_1, "Bot", "suggestions.json", 100, 200)
+            },
+            PlayerFlag {
+                suggestion: true,
+                ..flag(PLAYER_2, "Cheater", "suggestions.json", 100, 200)
+            },
+        ];
+
+        // Only confirmed flags by default
+        let filter = ExportFilter::default();
+        let playerlist = build_playerlist(&flags, &[], &HashMap::new(), &filter, file_info());
+        assert_eq!(playerlist.players.len(), 1);
+        assert_eq!(
+            playerlist.players[0].attributes,
+            vec![PlayerAttribute::Cheater]
+        );
+
+        let filter = ExportFilter {
+            include_suggestions: true,
+            ..Default::default()
+        };
+        let playerlist = build_playerlist(&flags, &[], &HashMap::new(), &filter, file_info());
+        assert_eq!(playerlist.players.len(), 2);
+        assert_eq!(
+            playerlist.players[0].attributes,
+            vec![PlayerAttribute::Cheater, PlayerAttribute::Bot]
+        );
+    }
+
+    #[test]
+    fn test_import_conflict_policies() {
+        let playerlist = from_json_str(
+            r#"{
+                "$schema": "https://raw.githubusercontent.com/PazerOP/tf2_bot_detector/master/schemas/v3/playerlist.schema.json",
+                "players": [
+                    { "attributes": ["cheater", "racist"], "last_seen": { "time": 500 }, "steamid": "[U:1:22202]", "proof": ["aimbot", "spinbot"] },
+                    { "attributes": ["bot"], "steamid": "76561198012345678", "proof": ["copies names"] },
+                    { "attributes": ["cheater"], "steamid": "not a steamid" }
+                ]
+            }"#,
+        );
+        let existing = vec![
+            flag(PLAYER_1, "Cheater", "team", 100, 1000),
+            flag(PLAYER_1, "Bot", "team", 100, 100),
+        ];
+        let existing_proofs = vec![proof(PLAYER_1, "Cheater", "team", "aimbot")];
+
+        // Player 1 already has flags from the source
+        let keep = plan_import(
+            &playerlist,
+            &existing,
+            &existing_proofs,
+            "team",
+            ConflictPolicy::Keep,
+            900,
+        );
+        assert_eq!(keep.summary.kept, 1);
+        assert_eq!(keep.summary.players, 1);
+        assert_eq!(keep.summary.invalid, 1);
+        assert!(keep.removals.is_empty());
+        assert_eq!(keep.writes.len(), 1);
+        assert_eq!(keep.writes[0].steam_id, PLAYER_2);
+        assert_eq!(keep.writes[0].flag_type, "Bot");
+        assert_eq!(
+            (keep.writes[0].first_seen, keep.writes[0].last_seen),
+            (900, 900)
+        );
+        assert!(!keep.writes[0].notified);
+        assert_eq!(keep.proofs.len(), 1);
+        assert_eq!(keep.proofs[0].flag_type, "Bot");
+        assert_eq!(keep.proofs[0].reason.as_deref(), Some("copies names"));
+
+        let overwrite = plan_import(
+            &playerlist,
+            &existing,
+            &existing_proofs,
+            "team",
+            ConflictPolicy::Overwrite,
+            900,
+        );
+        assert_eq!(overwrite.removals, vec![(PLAYER_1, "Bot".to_string())]);
+        assert_eq!(overwrite.summary.players, 2);
+        let cheater = &overwrite.writes[0];
+        assert_eq!(cheater.flag_type, "Cheater");
+        assert_eq!((cheater.first_seen, cheater.last_seen), (500, 500));
+        assert!(cheater.notified);
+        assert_eq!(overwrite.writes[1].flag_type, "Toxic");
+
+        // Proofs that are already stored are not added again
+        let reasons: Vec<_> = overwrite
+            .proofs
+            .iter()
+            .map(|proof| (proof.flag_type.as_str(), proof.reason.as_deref().unwrap()))
+            .collect();
+        assert_eq!(
+            reasons,
+            vec![("Cheater", "spinbot"), ("Bot", "copies names")]
+        );
+        assert_eq!(overwrite.summary.proofs_written, 2);
+
+        let merge = plan_import(
+            &playerlist,
+            &existing,
+            &existing_proofs,
+            "team",
+            ConflictPolicy::Merge,
+            900,
+        );
+        assert!(merge.removals.is_empty());
+        let cheater = &merge.writes[0];
+        assert_eq!((cheater.first_seen, cheater.last_seen), (100, 1000));
+        let toxic = &merge.writes[1];
+        assert_eq!((toxic.first_seen, toxic.last_seen), (500, 500));
+        assert!(merge.writes.iter().all(|x| x.source == "team"));
+    }
+
+    #[test]
+    fn test_conflict_policy_parse() {
+        for policy in ConflictPolicy::ALL {
+            assert_eq!(ConflictPolicy::parse(&policy.to_string()), Some(policy));
+        }
+        assert_eq!(
+            ConflictPolicy::parse(" Merge "),
+            Some(ConflictPolicy::Merge)
+        );
+        assert_eq!(ConflictPolicy::parse("replace"), None);
+    }
+}
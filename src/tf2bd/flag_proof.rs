@@ -0,0 +1,264 @@
+//
+// Proofs stored with player flags, so it can later be seen why a player was flagged.
+// A proof has the reason given by the user and what was going on in the lobby at the time.
+//
+
+use crate::{
+    config::FLAG_PROOF_CHAT_LINES,
+    db::entities::{NewPlayerFlagProof, PlayerFlagProof},
+    models::steamid::SteamID,
+    tf2::lobby::{Lobby, PlayerKill},
+};
+use chrono::DateTime;
+use std::cmp::Reverse;
+
+/// Captures the player's stats and latest chat lines from the lobby.
+/// Returns None when there is no reason and the player is not in the lobby.
+pub fn capture_flag_proof(
+    lobby: &Lobby,
+    steamid: SteamID,
+    flag_type: &str,
+    source: &str,
+    reason: Option<String>,
+    now: i64,
+) -> Option<NewPlayerFlagProof> {
+    let reason = reason
+        .map(|reason| reason.trim().to_string())
+        .filter(|reason| !reason.is_empty());
+
+    let player = lobby
+        .players
+        .iter()
+        .chain(lobby.recently_left_players.iter())
+        .find(|player| player.steamid == steamid);
+
+    let chat: Vec<String> = lobby
+        .chat
+        .iter()
+        .filter(|chat| chat.steamid == steamid && !chat.system)
+        .map(|chat| format!("[{}] {}", chat.when.format("%H:%M"), chat.message))
+        .collect();
+    let chat = chat[chat.len().saturating_sub(FLAG_PROOF_CHAT_LINES)..].join("\n");
+
+    if reason.is_none() && player.is_none() && chat.is_empty() {
+        return None;
+    }
+
+    let mut proof = NewPlayerFlagProof {
+        steam_id: steamid.to_u64() as i64,
+        flag_type: flag_type.to_string(),
+        source: source.to_string(),
+        created: now,
+        reason,
+        lobby_id: Some(lobby.lobby_id.clone()).filter(|lobby_id| !lobby_id.is_empty()),
+        map: lobby.server.map.clone(),
+        server: lobby.server.name().map(str::to_string),
+        chat,
+        ..Default::default()
+    };
+
+    if let Some(player) = player {
+        proof.kills = player.kills as i32;
+        proof.deaths = player.deaths as i32;
+        proof.crit_kills = player.crit_kills as i32;
+        proof.kills_with = kills_with_summary(&player.kills_with);
+    }
+
+    Some(proof)
+}
+
+/// The weapons used for kills, most used first, e.g. "sniperrifle x5 (2 crits), smg x1"
+pub fn kills_with_summary(kills_with: &[PlayerKill]) -> String {
+    // weapon -> (kills, crits), in order of first use
+    let mut weapons: Vec<(&str, u32, u32)> = Vec::new();
+    for kill in kills_with {
+        match weapons
+            .iter_mut()
+            .find(|(weapon, _, _)| *weapon == kill.weapon)
+        {
+            Some((_, kills, crits)) => {
+                *kills += 1;
+                *crits += u32::from(kill.crit);
+            }
+            None => weapons.push((&kill.weapon, 1, u32::from(kill.crit))),
+        }
+    }
+    weapons.sort_by_key(|(_, kills, _)| Reverse(*kills));
+
+    weapons
+        .into_iter()
+        .map(|(weapon, kills, crits)| match crits {
+            0 => format!("{} x{}", weapon, kills),
+            1 => format!("{} x{} (1 crit)", weapon, kills),
+            _ => format!("{} x{} ({} crits)", weapon, kills, crits),
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Percentage of kills that were crits
+pub fn crit_ratio(crit_kills: i32, kills: i32) -> Option<f32> {
+    (kills > 0).then(|| crit_kills as f32 * 100.0 / kills as f32)
+}
+
+/// The captured context of a proof as one line, None when only a reason was stored
+pub fn proof_context(proof: &PlayerFlagProof) -> Option<String> {
+    let has_context = proof.lobby_id.is_some()
+        || proof.map.is_some()
+        || proof.server.is_some()
+        || !proof.chat.is_empty()
+        || proof.kills > 0
+        || proof.deaths > 0;
+    if !has_context {
+        return None;
+    }
+
+    let mut context = match DateTime::from_timestamp(proof.created, 0) {
+        Some(created) => format!("{} {}", proof.flag_type, created.format("%Y-%m-%d")),
+        None => proof.flag_type.clone(),
+    };
+    if let Some(map) = &proof.map {
+        context += &format!(" on {}", map);
+    }
+    if let Some(server) = &proof.server {
+        context += &format!(" ({})", server);
+    }
+
+    context += &format!(": {}/{} K/D", proof.kills, proof.deaths);
+    if let Some(crit_ratio) = crit_ratio(proof.crit_kills, proof.kills) {
+        context += &format!(", {:.0}% crits", crit_ratio);
+    }
+    if !proof.kills_with.is_empty() {
+        context += &format!(", kills with {}", proof.kills_with);
+    }
+    if !proof.chat.is_empty() {
+        context += &format!(", chat: {}", proof.chat.replace('\n', " | "));
+    }
+
+    Some(context)
+}
+
+/// The proof as one line for the proof array of a TF2BD playerlist.
+/// A proof with only a reason, e.g. one imported from a list, is exported as it was.
+pub fn proof_text(proof: &PlayerFlagProof) -> String {
+    match (&proof.reason, proof_context(proof)) {
+        (Some(reason), Some(context)) => format!("{} - {}", reason, context),
+        (Some(reason), None) => reason.clone(),
+        (None, Some(context)) => context,
+        (None, None) => String::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tf2::lobby::{LobbyChat, Player};
+    use chrono::Local;
+
+    fn kill(weapon: &str, crit: bool) -> PlayerKill {
+        PlayerKill {
+            weapon: weapon.to_string(),
+            crit,
+        }
+    }
+
+    fn stored(proof: NewPlayerFlagProof) -> PlayerFlagProof {
+        PlayerFlagProof {
+            id: 1,
+            steam_id: proof.steam_id,
+            flag_type: proof.flag_type,
+            source: proof.source,
+            created: proof.created,
+            reason: proof.reason,
+            lobby_id: proof.lobby_id,
+            map: proof.map,
+            server: proof.server,
+            chat: proof.chat,
+            kills: proof.kills,
+            deaths: proof.deaths,
+            crit_kills: proof.crit_kills,
+            kills_with: proof.kills_with,
+        }
+    }
+
+    #[test]
+    fn test_capture_flag_proof() {
+        let steamid = SteamID::from_u64(76561197960287930);
+        let other = SteamID::from_u64(76561198012345678);
+
+        let mut lobby = Lobby::new(other);
+        lobby.lobby_id = "[A:1:123:456]".to_string();
+        lobby.server.map = Some("pl_badwater".to_string());
+        lobby.server.address = Some("169.254.171.56:28664".to_string());
+        lobby.players.push(Player {
+            steamid,
+            kills: 4,
+            deaths: 1,
+            crit_kills: 3,
+            kills_with: vec![
+                kill("smg", false),
+                kill("sniperrifle", true),
+                kill("sniperrifle", true),
+                kill("sniperrifle", true),
+            ],
+            ..Default::default()
+        });
+        for i in 0..FLAG_PROOF_CHAT_LINES + 2 {
+            for sender in [steamid, other] {
+                lobby.chat.push(LobbyChat {
+                    when: Local::now(),
+                    steamid: sender,
+                    message: format!("message {}", i),
+                    ..Default::default()
+                });
+            }
+        }
+
+        let proof = capture_flag_proof(
+            &lobby,
+            steamid,
+            "Cheater",
+            "manual",
+            Some(" aimbot ".to_string()),
+            1700000000,
+        )
+        .unwrap();
+        assert_eq!(proof.reason.as_deref(), Some("aimbot"));
+        assert_eq!(proof.lobby_id.as_deref(), Some("[A:1:123:456]"));
+        assert_eq!((proof.kills, proof.deaths, proof.crit_kills), (4, 1, 3));
+        assert_eq!(proof.kills_with, "sniperrifle x3 (3 crits), smg x1");
+
+        let chat: Vec<&str> = proof.chat.lines().collect();
+        assert_eq!(chat.len(), FLAG_PROOF_CHAT_LINES);
+        assert!(chat[0].ends_with("message 2"));
+        assert!(
+            chat.last()
+                .unwrap()
+                .ends_with(&format!("message {}", FLAG_PROOF_CHAT_LINES + 1))
+        );
+
+        let text = proof_text(&stored(proof));
+        assert!(text.starts_with(
+            "aimbot - Cheater 2023-11-14 on pl_badwater (169.254.171.56:28664): 4/1 K/D, 75% crits, kills with sniperrifle x3"
+        ));
+
+        // Nothing to store for a player that is not in the lobby and no reason
+        let absent = SteamID::from_u64(76561198000000000);
+        assert_eq!(
+            capture_flag_proof(&lobby, absent, "Bot", "manual", Some(" ".to_string()), 0),
+            None
+        );
+
+        // Only the reason is exported for a proof without context
+        let proof = capture_flag_proof(
+            &Lobby::default(),
+            absent,
+            "Bot",
+            "manual",
+            Some("spams chat".to_string()),
+            0,
+        )
+        .unwrap();
+        assert_eq!(proof_text(&stored(proof)), "spams chat");
+    }
+}
@@ -0,0 +1,728 @@
+//
+// Adapters for the playerlist formats of other tools. Every format is
+// converted to and from TF2BDPlayerList, which is what the rest of the
+// app works with.
+//
+// TF2BD: the PazerOP v3 schema and the variants other tools write, with
+//        SteamID64s, a single string instead of an array, proof strings
+//        and attribute names of their own.
+// MAC:   the player records of Mega Anti Cheat, one verdict per player.
+//
+
+use super::models::{
+    PlayerAttribute, PlayerInfo, PlayerLastSeen, PlayerListError, TF2BDPlayerList,
+    write_with_backups,
+};
+use crate::models::steamid::SteamID;
+use chrono::{DateTime, SecondsFormat, TimeZone, Utc};
+use fs_err as fs;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value, json};
+use std::{cell::Cell, collections::BTreeMap, fmt, path::Path};
+
+/// Maps attribute names of other tools, compared case-insensitively, to TF2Monitor's attributes
+pub type AttributeMapping = BTreeMap<String, PlayerAttribute>;
+
+pub fn default_attribute_mapping() -> AttributeMapping {
+    [
+        ("cheating", PlayerAttribute::Cheater),
+        ("hacker", PlayerAttribute::Cheater),
+        ("bots", PlayerAttribute::Bot),
+        ("sus", PlayerAttribute::Suspicious),
+        ("exploit", PlayerAttribute::Exploiter),
+        ("exploiting", PlayerAttribute::Exploiter),
+        ("racism", PlayerAttribute::Toxic),
+        ("toxic", PlayerAttribute::Toxic),
+        ("harassment", PlayerAttribute::Toxic),
+        ("trusted", PlayerAttribute::Cool),
+        ("friend", PlayerAttribute::Cool),
+    ]
+    .into_iter()
+    .map(|(name, attribute)| (name.to_string(), attribute))
+    .collect()
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "lowercase")]
+pub enum ListFormat {
+    Tf2bd,
+    Mac,
+}
+
+impl ListFormat {
+    pub const ALL: [ListFormat; 2] = [ListFormat::Tf2bd, ListFormat::Mac];
+
+    pub fn parse(name: &str) -> Option<Self> {
+        ListFormat::ALL
+            .into_iter()
+            .find(|format| format!("{:?}", format).eq_ignore_ascii_case(name.trim()))
+    }
+}
+
+impl fmt::Display for ListFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ListFormat::Tf2bd => write!(f, "TF2BD"),
+            ListFormat::Mac => write!(f, "MAC"),
+        }
+    }
+}
+
+/// Converts between a playerlist format and TF2BDPlayerList
+pub trait PlayerListAdapter: Sync {
+    fn format(&self) -> ListFormat;
+
+    /// True if the JSON has the shape of this format
+    fn detect(&self, json: &Value) -> bool;
+
+    /// Attributes that are not known to TF2Monitor are looked up in the mapping,
+    /// and kept as unknown attributes when they are not in it either
+    fn import(
+        &self,
+        json: Value,
+        mapping: &AttributeMapping,
+    ) -> Result<TF2BDPlayerList, PlayerListError>;
+
+    fn export(&self, playerlist: &TF2BDPlayerList) -> Result<String, PlayerListError>;
+
+    /// False if the rules section is left out when exporting
+    fn supports_rules(&self) -> bool {
+        true
+    }
+}
+
+static ADAPTERS: [&dyn PlayerListAdapter; 2] = [&Tf2bdAdapter, &MacAdapter];
+
+pub fn adapter(format: ListFormat) -> &'static dyn PlayerListAdapter {
+    match format {
+        ListFormat::Tf2bd => &Tf2bdAdapter,
+        ListFormat::Mac => &MacAdapter,
+    }
+}
+
+/// Parses a playerlist in the given format, or in the format detected from the JSON
+pub fn from_json_str(
+    json: &str,
+    format: Option<ListFormat>,
+    mapping: &AttributeMapping,
+) -> Result<(TF2BDPlayerList, ListFormat), PlayerListError> {
+    let json: Value = serde_json::from_str(json)?;
+
+    let adapter = match format {
+        Some(format) => adapter(format),
+        None => ADAPTERS
+            .into_iter()
+            .find(|adapter| adapter.detect(&json))
+            .ok_or_else(|| {
+                PlayerListError::Format(
+                    "Unknown playerlist format, expected a players array (TF2BD) or a records object (MAC)"
+                        .to_string(),
+                )
+            })?,
+    };
+
+    let playerlist = adapter.import(json, mapping)?;
+    Ok((playerlist, adapter.format()))
+}
+
+pub fn to_json_string(
+    playerlist: &TF2BDPlayerList,
+    format: ListFormat,
+) -> Result<String, PlayerListError> {
+    let adapter = adapter(format);
+
+    let rules = playerlist.rules.as_ref().map_or(0, Vec::len);
+    if rules > 0 && !adapter.supports_rules() {
+        log::warn!(
+            "The {} format has no rules, {} rules are left out",
+            format,
+            rules
+        );
+    }
+
+    adapter.export(playerlist)
+}
+
+/// Loads a playerlist in any format. A missing file gives an empty list.
+pub fn load(
+    filename: &str,
+    format: Option<ListFormat>,
+    mapping: &AttributeMapping,
+) -> Result<(TF2BDPlayerList, ListFormat), PlayerListError> {
+    if !Path::new(filename).exists() {
+        log::info!("File {} does not exist. Using an empty list", filename);
+        return Ok((TF2BDPlayerList::new(), format.unwrap_or(ListFormat::Tf2bd)));
+    }
+
+    log::info!("Loading player list: {}", filename);
+    let json = fs::read_to_string(filename)?;
+    let (playerlist, format) = from_json_str(&json, format, mapping)?;
+    log::info!("Stats: {:?}", playerlist.get_stats());
+
+    Ok((playerlist, format))
+}
+
+/// Loads a playerlist in any format, or the newest backup that can be loaded if the file is broken
+pub fn load_or_backup(
+    filename: &str,
+    format: Option<ListFormat>,
+    mapping: &AttributeMapping,
+) -> (TF2BDPlayerList, ListFormat, Option<PlayerListError>) {
+    let detected = Cell::new(format.unwrap_or(ListFormat::Tf2bd));
+
+    let (playerlist, error) = TF2BDPlayerList::load_or_backup(filename, |filename| {
+        let (playerlist, format) = load(filename, format, mapping)?;
+        detected.set(format);
+        Ok(playerlist)
+    });
+
+    (playerlist, detected.get(), error)
+}
+
+/// Saves a playerlist in the given format, keeping backups of the previous versions
+pub fn save(
+    playerlist: &TF2BDPlayerList,
+    filename: &str,
+    format: ListFormat,
+) -> Result<(), PlayerListError> {
+    log::info!("Saving {} player list: {}", format, filename);
+    write_with_backups(filename, &to_json_string(playerlist, format)?, |json| {
+        from_json_str(json, Some(format), &AttributeMapping::new()).is_ok()
+    })
+}
+
+/// Looks up an attribute name, first as a TF2BD name and then in the mapping
+fn map_attribute(name: &str, mapping: &AttributeMapping) -> Option<PlayerAttribute> {
+    PlayerAttribute::from_tf2bd_name(&name.to_lowercase()).or_else(|| {
+        mapping
+            .iter()
+            .find(|(mapped_name, _)| mapped_name.eq_ignore_ascii_case(name.trim()))
+            .map(|(_, attribute)| *attribute)
+    })
+}
+
+fn apply_attribute_mapping(player_info: &mut PlayerInfo, mapping: &AttributeMapping) {
+    for name in std::mem::take(&mut player_info.unknown_attributes) {
+        match map_attribute(&name, mapping) {
+            Some(attribute) => {
+                if !player_info.attributes.contains(&attribute) {
+                    player_info.attributes.push(attribute);
+                }
+            }
+            None => player_info.unknown_attributes.push(name),
+        }
+    }
+}
+
+/// The first of the fields that is present
+fn get_field<'a>(object: &'a Value, names: &[&str]) -> Option<&'a Value> {
+    names.iter().find_map(|name| object.get(*name))
+}
+
+fn parse_steamid_value(value: &Value) -> Option<SteamID> {
+    match value {
+        Value::String(steamid) => SteamID::parse(steamid),
+        Value::Number(steamid) => {
+            let steamid = SteamID::from_u64(steamid.as_u64()?);
+            steamid.is_valid().then_some(steamid)
+        }
+        _ => None,
+    }
+}
+
+/// The PazerOP v3 schema and the variants of it other tools write
+struct Tf2bdAdapter;
+
+const TF2BD_STEAMID_FIELDS: [&str; 4] = ["steamid", "steamid64", "steamid32", "steam_id"];
+
+impl PlayerListAdapter for Tf2bdAdapter {
+    fn format(&self) -> ListFormat {
+        ListFormat::Tf2bd
+    }
+
+    fn detect(&self, json: &Value) -> bool {
+        json.get("players").is_some_and(Value::is_array)
+    }
+
+    fn import(
+        &self,
+        mut json: Value,
+        mapping: &AttributeMapping,
+    ) -> Result<TF2BDPlayerList, PlayerListError> {
+        let Some(object) = json.as_object_mut() else {
+            return Err(PlayerListError::Format(
+                "A TF2BD list must be a JSON object".to_string(),
+            ));
+        };
+
+        object
+            .entry("$schema")
+            .or_insert_with(|| Value::String(String::new()));
+
+        if let Some(Value::Array(players)) = object.get_mut("players") {
+            let normalized = std::mem::take(players)
+                .into_iter()
+                .filter_map(normalize_tf2bd_player)
+                .collect();
+            *players = normalized;
+        }
+
+        let mut playerlist: TF2BDPlayerList = serde_json::from_value(json)
+            .map_err(|e| PlayerListError::Format(format!("Invalid TF2BD list: {}", e)))?;
+
+        for player_info in &mut playerlist.players {
+            apply_attribute_mapping(player_info, mapping);
+        }
+
+        Ok(playerlist)
+    }
+
+    fn export(&self, playerlist: &TF2BDPlayerList) -> Result<String, PlayerListError> {
+        let mut playerlist = playerlist.clone();
+        if playerlist.schema.is_empty() {
+            playerlist.schema = TF2BDPlayerList::new().schema;
+        }
+
+        Ok(serde_json::to_string_pretty(&playerlist)?)
+    }
+}
+
+/// Rewrites a player of a TF2BD variant into the v3 shape.
+/// Returns None for players without a valid SteamID.
+fn normalize_tf2bd_player(mut player: Value) -> Option<Value> {
+    let object = player.as_object_mut()?;
+
+    let Some(steamid) = TF2BD_STEAMID_FIELDS
+        .iter()
+        .find_map(|field| object.remove(*field))
+    else {
+        log::warn!(
+            "Skipping player without a SteamID: {}",
+            Value::Object(object.clone())
+        );
+        return None;
+    };
+    let Some(steamid) = parse_steamid_value(&steamid) else {
+        log::warn!("Skipping player with invalid SteamID {}", steamid);
+        return None;
+    };
+    object.insert("steamid".to_string(), steamid.to_steam_id32().into());
+
+    // Some tools write a single string instead of an array
+    for field in ["attributes", "proof"] {
+        match object.get(field) {
+            Some(Value::String(value)) => {
+                let value = json!([value]);
+                object.insert(field.to_string(), value);
+            }
+            None if field == "attributes" => {
+                object.insert(field.to_string(), json!([]));
+            }
+            _ => {}
+        }
+    }
+
+    let name = object
+        .remove("player_name")
+        .or_else(|| object.remove("name"));
+
+    match object.get_mut("last_seen").and_then(Value::as_object_mut) {
+        Some(last_seen) => {
+            if let Some(name) = last_seen.remove("name") {
+                last_seen.entry("player_name").or_insert(name);
+            }
+
+            let time = match last_seen.get("time") {
+                Some(Value::String(time)) => time.trim().parse::<i64>().unwrap_or_default(),
+                Some(time) => time.as_i64().unwrap_or_default(),
+                None => 0,
+            };
+            last_seen.insert("time".to_string(), time.into());
+        }
+        None => {
+            if let Some(name) = name {
+                object.insert(
+                    "last_seen".to_string(),
+                    json!({ "player_name": name, "time": 0 }),
+                );
+            }
+        }
+    }
+
+    Some(player)
+}
+
+/// The player records of Mega Anti Cheat, keyed by SteamID64
+struct MacAdapter;
+
+/// MAC's verdict for players that are not marked
+const MAC_NO_VERDICT: &str = "Player";
+
+/// Field in MAC's custom data with all the attributes of players that one verdict can't describe
+const MAC_ATTRIBUTES_FIELD: &str = "tf2monitorAttributes";
+
+/// MAC verdicts for TF2Monitor's attributes, the first attribute the player has is used
+const MAC_VERDICTS: [(PlayerAttribute, &str); 4] = [
+    (PlayerAttribute::Cheater, "Cheater"),
+    (PlayerAttribute::Bot, "Bot"),
+    (PlayerAttribute::Suspicious, "Suspicious"),
+    (PlayerAttribute::Cool, "Trusted"),
+];
+
+impl PlayerListAdapter for MacAdapter {
+    fn format(&self) -> ListFormat {
+        ListFormat::Mac
+    }
+
+    fn detect(&self, json: &Value) -> bool {
+        json.get("records").is_some_and(Value::is_object)
+    }
+
+    fn import(
+        &self,
+        json: Value,
+        mapping: &AttributeMapping,
+    ) -> Result<TF2BDPlayerList, PlayerListError> {
+        let Some(records) = json.get("records").and_then(Value::as_object) else {
+            return Err(PlayerListError::Format(
+                "A MAC list must have a records object".to_string(),
+            ));
+        };
+
+        let mut players = Vec::new();
+        for (steamid, record) in records {
+            let Some(steamid) = SteamID::parse(steamid) else {
+                log::warn!("Skipping MAC record with invalid SteamID {}", steamid);
+                continue;
+            };
+
+            let mut player_info = PlayerInfo {
+                steamid32: steamid.to_steam_id32(),
+                ..Default::default()
+            };
+
+            let verdict = get_field(record, &["verdict", "localVerdict"])
+                .and_then(Value::as_str)
+                .unwrap_or(MAC_NO_VERDICT);
+            let custom_data = get_field(record, &["custom_data", "customData"]);
+            let mut names: Vec<&str> = custom_data
+                .and_then(|custom_data| custom_data.get(MAC_ATTRIBUTES_FIELD))
+                .and_then(Value::as_array)
+                .map(|names| names.iter().filter_map(Value::as_str).collect())
+                .unwrap_or_default();
+            if !verdict.eq_ignore_ascii_case(MAC_NO_VERDICT) && names.is_empty() {
+                names.push(verdict);
+            }
+            for name in names {
+                match map_attribute(name, mapping) {
+                    Some(attribute) if !player_info.attributes.contains(&attribute) => {
+                        player_info.attributes.push(attribute)
+                    }
+                    Some(_) => {}
+                    None => player_info.unknown_attributes.push(name.to_string()),
+                }
+            }
+
+            let player_name = get_field(record, &["previous_names", "previousNames"])
+                .and_then(Value::as_array)
+                .and_then(|names| names.first())
+                .and_then(Value::as_str)
+                .map(str::to_string);
+            let time = get_field(record, &["modified", "created"])
+                .and_then(Value::as_str)
+                .and_then(|time| DateTime::parse_from_rfc3339(time).ok())
+                .map(|time| time.timestamp());
+            if player_name.is_some() || time.is_some() {
+                player_info.last_seen = Some(PlayerLastSeen {
+                    player_name,
+                    time: time.unwrap_or_default(),
+                });
+            }
+
+            if let Some(note) = custom_data
+                .and_then(|custom_data| custom_data.get("playerNote"))
+                .and_then(Value::as_str)
+                .filter(|note| !note.trim().is_empty())
+            {
+                player_info.proof.push(note.to_string());
+            }
+
+            players.push(player_info);
+        }
+
+        Ok(TF2BDPlayerList {
+            players,
+            ..TF2BDPlayerList::new()
+        })
+    }
+
+    /// MAC has one verdict per player, so players with other or more attributes
+    /// also get all of them in their custom data
+    fn export(&self, playerlist: &TF2BDPlayerList) -> Result<String, PlayerListError> {
+        let mut records = Map::new();
+
+        for player_info in &playerlist.players {
+            let Some(steamid) = SteamID::parse(&player_info.steamid32) else {
+                log::warn!("Skipping invalid SteamID '{}'", player_info.steamid32);
+                continue;
+            };
+
+            let verdict = MAC_VERDICTS
+                .iter()
+                .find(|(attribute, _)| player_info.attributes.contains(attribute))
+                .map(|(_, verdict)| verdict.to_string())
+                .or_else(|| player_info.unknown_attributes.first().cloned())
+                .unwrap_or_else(|| MAC_NO_VERDICT.to_string());
+
+            let (previous_names, time) = match &player_info.last_seen {
+                Some(last_seen) => (
+                    last_seen.player_name.iter().cloned().collect::<Vec<_>>(),
+                    last_seen.time,
+                ),
+                None => (Vec::new(), 0),
+            };
+            let time = Utc
+                .timestamp_opt(time, 0)
+                .single()
+                .unwrap_or_default()
+                .to_rfc3339_opts(SecondsFormat::Secs, true);
+
+            let mut custom_data = Map::new();
+            if !player_info.proof.is_empty() {
+                custom_data.insert(
+                    "playerNote".to_string(),
+                    player_info.proof.join("\n").into(),
+                );
+            }
+
+            let has_verdict = player_info.unknown_attributes.is_empty()
+                && match player_info.attributes.as_slice() {
+                    [] => true,
+                    [attribute] => MAC_VERDICTS.iter().any(|(x, _)| x == attribute),
+                    _ => false,
+                };
+            if !has_verdict {
+                let names: Vec<String> = player_info
+                    .attributes
+                    .iter()
+                    .map(PlayerAttribute::tf2bd_name)
+                    .chain(player_info.unknown_attributes.iter().cloned())
+                    .collect();
+                custom_data.insert(MAC_ATTRIBUTES_FIELD.to_string(), names.into());
+            }
+
+            records.insert(
+                steamid.to_u64().to_string(),
+                json!({
+                    "custom_data": custom_data,
+                    "verdict": verdict,
+                    "previous_names": previous_names,
+                    "modified": time,
+                    "created": time,
+                }),
+            );
+        }
+
+        Ok(serde_json::to_string_pretty(
+            &json!({ "records": records }),
+        )?)
+    }
+
+    fn supports_rules(&self) -> bool {
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TF2BD_FIXTURE: &str = include_str!("playerlist_fixture.json");
+    const TF2BD_VARIANT_FIXTURE: &str = include_str!("playerlist_variant_fixture.json");
+    const MAC_FIXTURE: &str = include_str!("playerlist_mac_fixture.json");
+
+    /// Imports the fixture, exports it in the same format and imports the export again
+    fn round_trip(fixture: &str) -> (TF2BDPlayerList, TF2BDPlayerList, ListFormat) {
+        let mapping = default_attribute_mapping();
+        let (imported, format) = from_json_str(fixture, None, &mapping).unwrap();
+        let exported = to_json_string(&imported, format).unwrap();
+        let (reimported, reformat) = from_json_str(&exported, None, &mapping).unwrap();
+        assert_eq!(format, reformat);
+
+        (imported, reimported, format)
+    }
+
+    #[test]
+    fn test_tf2bd_round_trip() {
+        let (imported, reimported, format) = round_trip(TF2BD_FIXTURE);
+        assert_eq!(format, ListFormat::Tf2bd);
+        assert_eq!(imported.players, reimported.players);
+        assert_eq!(imported.file_info, reimported.file_info);
+        assert_eq!(reimported.rules.unwrap().len(), 1);
+
+        // The players of a v3 list are written back the way they were
+        let exported: Value =
+            serde_json::from_str(&to_json_string(&imported, ListFormat::Tf2bd).unwrap()).unwrap();
+        let fixture: Value = serde_json::from_str(TF2BD_FIXTURE).unwrap();
+        assert_eq!(exported["players"], fixture["players"]);
+        assert_eq!(exported["file_info"], fixture["file_info"]);
+        assert_eq!(exported["$schema"], fixture["$schema"]);
+    }
+
+    #[test]
+    fn test_tf2bd_variant_round_trip() {
+        let (imported, reimported, format) = round_trip(TF2BD_VARIANT_FIXTURE);
+        assert_eq!(format, ListFormat::Tf2bd);
+        assert_eq!(imported.players, reimported.players);
+        assert_eq!(imported.file_info.as_ref().unwrap().title, "Community list");
+
+        // The player with an invalid SteamID is skipped
+        let players = &imported.players;
+        assert_eq!(players.len(), 3);
+
+        assert_eq!(players[0].steamid32, "[U:1:132301509]");
+        assert_eq!(players[0].attributes, vec![PlayerAttribute::Cheater]);
+        assert_eq!(players[0].proof.len(), 2);
+        assert_eq!(
+            players[0].last_seen,
+            Some(PlayerLastSeen {
+                player_name: Some("aimer".to_string()),
+                time: 1700000000,
+            })
+        );
+
+        assert_eq!(players[1].steamid32, "[U:1:52079950]");
+        assert_eq!(players[1].attributes, vec![PlayerAttribute::Toxic]);
+        assert_eq!(players[1].proof, vec!["said slurs in chat".to_string()]);
+
+        assert_eq!(players[2].steamid32, "[U:1:15]");
+        assert_eq!(players[2].attributes, vec![PlayerAttribute::Bot]);
+        assert_eq!(players[2].unknown_attributes, vec!["sniper".to_string()]);
+        assert_eq!(
+            players[2]
+                .last_seen
+                .as_ref()
+                .unwrap()
+                .player_name
+                .as_deref(),
+            Some("botty")
+        );
+    }
+
+    #[test]
+    fn test_mac_round_trip() {
+        let (imported, reimported, format) = round_trip(MAC_FIXTURE);
+        assert_eq!(format, ListFormat::Mac);
+        assert_eq!(imported.players, reimported.players);
+        assert!(imported.file_info.is_none());
+
+        let player = |steamid32: &str| {
+            imported
+                .players
+                .iter()
+                .find(|player| player.steamid32 == steamid32)
+                .unwrap()
+        };
+
+        let cheater = player("[U:1:132301509]");
+        assert_eq!(cheater.attributes, vec![PlayerAttribute::Cheater]);
+        assert_eq!(cheater.proof, vec!["Spinbot on koth_viaduct".to_string()]);
+        assert_eq!(
+            cheater.last_seen,
+            Some(PlayerLastSeen {
+                player_name: Some("aimer".to_string()),
+                time: 1709294400,
+            })
+        );
+
+        assert_eq!(
+            player("[U:1:52079950]").attributes,
+            vec![PlayerAttribute::Cool]
+        );
+        assert_eq!(player("[U:1:15]").attributes, vec![PlayerAttribute::Bot]);
+
+        let unmarked = player("[U:1:22202]");
+        assert!(unmarked.attributes.is_empty());
+        assert!(unmarked.unknown_attributes.is_empty());
+    }
+
+    #[test]
+    fn test_mac_export_keeps_all_attributes() {
+        let player =
+            |steamid32: &str, attributes: Vec<PlayerAttribute>, unknown: &[&str]| PlayerInfo {
+                steamid32: steamid32.to_string(),
+                attributes,
+                unknown_attributes: unknown.iter().map(|x| x.to_string()).collect(),
+                ..Default::default()
+            };
+        let playerlist = TF2BDPlayerList {
+            players: vec![
+                player("[U:1:1]", vec![PlayerAttribute::Cheater], &[]),
+                player(
+                    "[U:1:2]",
+                    vec![PlayerAttribute::Cheater, PlayerAttribute::Toxic],
+                    &[],
+                ),
+                player("[U:1:3]", vec![PlayerAttribute::Exploiter], &["griefer"]),
+            ],
+            ..TF2BDPlayerList::new()
+        };
+
+        let exported = to_json_string(&playerlist, ListFormat::Mac).unwrap();
+        let json: Value = serde_json::from_str(&exported).unwrap();
+        let records = &json["records"];
+        assert_eq!(records["76561197960265729"]["verdict"], "Cheater");
+        assert!(records["76561197960265729"]["custom_data"][MAC_ATTRIBUTES_FIELD].is_null());
+        assert_eq!(records["76561197960265730"]["verdict"], "Cheater");
+        assert_eq!(
+            records["76561197960265730"]["custom_data"][MAC_ATTRIBUTES_FIELD],
+            json!(["cheater", "racist"])
+        );
+
+        let (reimported, _) = from_json_str(&exported, None, &AttributeMapping::new()).unwrap();
+        for player_info in &playerlist.players {
+            let reimported = reimported
+                .players
+                .iter()
+                .find(|x| x.steamid32 == player_info.steamid32)
+                .unwrap();
+            assert_eq!(reimported.attributes, player_info.attributes);
+            assert_eq!(
+                reimported.unknown_attributes,
+                player_info.unknown_attributes
+            );
+        }
+    }
+
+    #[test]
+    fn test_attribute_mapping() {
+        let json = r#"{ "players": [ { "attributes": ["Griefer", "CHEATING"], "steamid": "[U:1:15]" } ] }"#;
+
+        let (playerlist, _) = from_json_str(json, None, &default_attribute_mapping()).unwrap();
+        assert_eq!(
+            playerlist.players[0].attributes,
+            vec![PlayerAttribute::Cheater]
+        );
+        assert_eq!(
+            playerlist.players[0].unknown_attributes,
+            vec!["Griefer".to_string()]
+        );
+
+        let mapping = AttributeMapping::from([("griefer".to_string(), PlayerAttribute::Toxic)]);
+        let (playerlist, _) = from_json_str(json, None, &mapping).unwrap();
+        assert_eq!(
+            playerlist.players[0].attributes,
+            vec![PlayerAttribute::Toxic]
+        );
+        assert_eq!(
+            playerlist.players[0].unknown_attributes,
+            vec!["CHEATING".to_string()]
+        );
+    }
+
+    #[test]
+    fn test_unknown_format() {
+        let result = from_json_str(r#"{ "something": [] }"#, None, &default_attribute_mapping());
+        assert!(matches!(result, Err(PlayerListError::Format(_))));
+    }
+}
@@ -0,0 +1,378 @@
+use super::models::PlayerAttribute;
+use crate::{
+    config::{FAILED_KICK_VOTE_RETRY_DELAY, PARTY_VOTE_BACKOFF, VOTE_CREATION_COOLDOWN},
+    models::{app_settings::AppSettings, steamid::SteamID},
+    tf2::lobby::{Lobby, Player},
+};
+use chrono::{DateTime, Local, TimeDelta};
+use std::time::Duration;
+
+/// How long the kicker waits after starting before it calls the first vote
+const VOTE_PERIOD_SECONDS: i64 = 10;
+
+/// A player in our team that we want to kick
+#[derive(Debug, Clone, PartialEq)]
+pub struct KickTarget {
+    pub steamid: SteamID,
+
+    /// The player's ID in the lobby, used in the callvote command
+    pub user_id: i64,
+    pub name: String,
+    pub attribute: PlayerAttribute,
+
+    /// A kick vote on the player failed recently, so TF2 won't allow another one until then
+    pub not_before: Option<DateTime<Local>>,
+}
+
+/// The players to kick in priority order and when the next vote is called
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct KickSchedule {
+    pub queue: Vec<KickTarget>,
+
+    /// When the next kick vote is called, None when there is nobody to kick or a vote is running
+    pub next_attempt: Option<DateTime<Local>>,
+
+    /// Why the next kick vote has to wait, shown in the GUI
+    pub waiting_for: Option<String>,
+}
+
+impl KickSchedule {
+    /// The player to call a kick vote on now, if it is time for one
+    pub fn due(&self, now: DateTime<Local>) -> Option<&KickTarget> {
+        if self.next_attempt? > now {
+            return None;
+        }
+
+        self.queue
+            .iter()
+            .find(|target| target.not_before.is_none_or(|not_before| not_before <= now))
+    }
+}
+
+/// Picks who to kick and when, so our votes aren't wasted on TF2's vote cooldowns
+#[derive(Debug)]
+pub struct KickScheduler {
+    started: DateTime<Local>,
+
+    /// When we last called a kick vote
+    last_call: Option<DateTime<Local>>,
+}
+
+impl KickScheduler {
+    pub fn new(now: DateTime<Local>) -> Self {
+        Self {
+            started: now,
+            last_call: None,
+        }
+    }
+
+    pub fn called(&mut self, now: DateTime<Local>) {
+        self.last_call = Some(now);
+    }
+
+    pub fn schedule(
+        &self,
+        lobby: &Lobby,
+        settings: &AppSettings,
+        now: DateTime<Local>,
+    ) -> KickSchedule {
+        let Some(me) = lobby.get_me() else {
+            return KickSchedule::default();
+        };
+
+        let mut queue: Vec<(&Player, KickTarget)> = lobby
+            .players
+            .iter()
+            .filter(|player| player.team == me.team && player.steamid != me.steamid)
+            // Friends are never kicked, they are probably flagged by mistake
+            .filter(|player| !lobby.friendships.are_friends(me.steamid, player.steamid))
+            .filter_map(|player| {
+                let attribute = kick_attribute(player, settings)?;
+                let not_before = lobby
+                    .last_failed_kick_vote(player.steamid)
+                    .map(|ended| ended + FAILED_KICK_VOTE_RETRY_DELAY)
+                    .filter(|not_before| *not_before > now);
+
+                Some((
+                    player,
+                    KickTarget {
+                        steamid: player.steamid,
+                        user_id: player.id,
+                        name: player.name.clone(),
+                        attribute,
+                        not_before,
+                    },
+                ))
+            })
+            .collect();
+
+        // Bots before cheaters, alive before dead,
+        // and then the one that has been in the server the longest
+        queue.sort_by_key(|(player, target)| {
+            (
+                target.attribute != PlayerAttribute::Bot,
+                !player.alive,
+                player.id,
+            )
+        });
+        let queue: Vec<KickTarget> = queue.into_iter().map(|(_, target)| target).collect();
+
+        if queue.is_empty() {
+            return KickSchedule::default();
+        }
+
+        if let Some(vote) = lobby.active_vote() {
+            return KickSchedule {
+                queue,
+                next_attempt: None,
+                waiting_for: Some(format!("Vote running: {}", vote.issue)),
+            };
+        }
+
+        let mut next_attempt = now;
+        let mut waiting_for = None;
+        let mut wait_until = |until: DateTime<Local>, reason: &str| {
+            if until > next_attempt {
+                next_attempt = until;
+                waiting_for = Some(reason.to_string());
+            }
+        };
+
+        wait_until(
+            self.started + TimeDelta::seconds(VOTE_PERIOD_SECONDS),
+            "Starting up",
+        );
+
+        // Our own votes show up in the console, the last call covers votes that didn't
+        let my_last_vote = lobby
+            .votes
+            .iter()
+            .filter(|vote| vote.caller == Some(me.steamid))
+            .map(|vote| vote.started)
+            .chain(self.last_call)
+            .max();
+        if let Some(called) = my_last_vote {
+            wait_until(called + VOTE_CREATION_COOLDOWN, "Our vote cooldown");
+        }
+
+        // We don't know the party, friends in our team are the closest thing
+        let party_vote = lobby
+            .votes
+            .iter()
+            .filter(|vote| {
+                vote.caller
+                    .is_some_and(|caller| lobby.friendships.are_friends(me.steamid, caller))
+            })
+            .map(|vote| vote.started)
+            .max();
+        if let Some(called) = party_vote {
+            wait_until(
+                called + PARTY_VOTE_BACKOFF,
+                "A party member just called a vote",
+            );
+        }
+
+        // Every target has a failed vote on them
+        if let Some(first_allowed) = queue
+            .iter()
+            .map(|target| target.not_before.unwrap_or(now))
+            .min()
+        {
+            wait_until(first_allowed, "Failed vote cooldown");
+        }
+
+        KickSchedule {
+            queue,
+            next_attempt: Some(next_attempt),
+            waiting_for,
+        }
+    }
+}
+
+/// Which of the kick settings the player matches, bots first.
+/// player_info only has the trusted attributes, suggestions never trigger votekicks.
+fn kick_attribute(player: &Player, settings: &AppSettings) -> Option<PlayerAttribute> {
+    let trusted = &player.player_info.as_ref()?.attributes;
+
+    let mut attributes = Vec::new();
+    if settings.kick_bots {
+        attributes.push(PlayerAttribute::Bot);
+    }
+    if settings.kick_cheaters {
+        attributes.push(PlayerAttribute::Cheater);
+    }
+
+    attributes
+        .into_iter()
+        .find(|attribute| trusted.contains(attribute))
+}
+
+/// Seconds until the next kick vote, for the GUI
+pub fn seconds_until(next_attempt: DateTime<Local>, now: DateTime<Local>) -> u64 {
+    (next_attempt - now)
+        .to_std()
+        .unwrap_or(Duration::ZERO)
+        .as_secs()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tf2::{
+        lobby::{Team, votes::update_votes},
+        logfile::LogLine,
+    };
+    use crate::tf2bd::{MarkingSource, models::PlayerInfo};
+    use chrono::TimeZone;
+
+    const ME: u64 = 76561198012345678;
+
+    fn player(id: i64, name: &str, attribute: Option<PlayerAttribute>, alive: bool) -> Player {
+        Player {
+            id,
+            steamid: SteamID::from_u64(ME - 100 + id as u64),
+            name: name.to_string(),
+            team: Team::Red,
+            alive,
+            player_info: attribute.map(|attribute| PlayerInfo {
+                attributes: vec![attribute],
+                ..Default::default()
+            }),
+            ..Default::default()
+        }
+    }
+
+    fn lobby(players: Vec<Player>) -> Lobby {
+        let mut lobby = Lobby::new(SteamID::from_u64(ME));
+        lobby.players.push(Player {
+            id: 1,
+            steamid: SteamID::from_u64(ME),
+            name: "Me".to_string(),
+            team: Team::Red,
+            ..Default::default()
+        });
+        lobby.players.extend(players);
+        lobby
+    }
+
+    fn settings() -> AppSettings {
+        AppSettings {
+            kick_bots: true,
+            kick_cheaters: true,
+            ..Default::default()
+        }
+    }
+
+    fn names(schedule: &KickSchedule) -> Vec<&str> {
+        schedule.queue.iter().map(|t| t.name.as_str()).collect()
+    }
+
+    #[test]
+    fn test_priority() {
+        let now = Local.with_ymd_and_hms(2024, 5, 6, 17, 0, 0).unwrap();
+
+        let mut suggested_bot = player(2, "Suggested bot", None, true);
+        suggested_bot.marking_sources.insert(
+            PlayerAttribute::Bot,
+            MarkingSource {
+                source: "rules".to_string(),
+                suggestion: true,
+            },
+        );
+        let mut blue_bot = player(3, "Blue bot", Some(PlayerAttribute::Bot), true);
+        blue_bot.team = Team::Blue;
+
+        let lobby = lobby(vec![
+            suggested_bot,
+            blue_bot,
+            player(4, "Cheater", Some(PlayerAttribute::Cheater), true),
+            player(5, "Dead bot", Some(PlayerAttribute::Bot), false),
+            player(6, "Old bot", Some(PlayerAttribute::Bot), true),
+            player(7, "New bot", Some(PlayerAttribute::Bot), true),
+            player(8, "Nobody", None, true),
+        ]);
+
+        // Suggestions are never kicked
+        let scheduler = KickScheduler::new(now - Duration::from_secs(60));
+        let schedule = scheduler.schedule(&lobby, &settings(), now);
+        assert_eq!(
+            names(&schedule),
+            vec!["Old bot", "New bot", "Dead bot", "Cheater"]
+        );
+        assert_eq!(schedule.due(now).unwrap().name, "Old bot");
+
+        let mut bots_only = settings();
+        bots_only.kick_cheaters = false;
+        let schedule = scheduler.schedule(&lobby, &bots_only, now);
+        assert_eq!(names(&schedule), vec!["Old bot", "New bot", "Dead bot"]);
+    }
+
+    #[test]
+    fn test_cooldowns() {
+        let now = Local.with_ymd_and_hms(2024, 5, 6, 17, 0, 0).unwrap();
+        let mut lobby = lobby(vec![
+            player(2, "Bot", Some(PlayerAttribute::Bot), true),
+            player(3, "Cheater", Some(PlayerAttribute::Cheater), true),
+        ]);
+
+        // A vote is running
+        update_votes(
+            &mut lobby,
+            &LogLine::VoteStarted {
+                when: now,
+                caller: "Me".to_string(),
+                issue: "Kick Bot (cheating)".to_string(),
+                kick_target: Some("Bot".to_string()),
+            },
+        );
+        let mut scheduler = KickScheduler::new(now - Duration::from_secs(60));
+        scheduler.called(now);
+        let schedule = scheduler.schedule(&lobby, &settings(), now);
+        assert_eq!(schedule.next_attempt, None);
+        assert!(schedule.due(now).is_none());
+
+        // The vote failed, the cheater is next once our cooldown is over
+        let ended = now + Duration::from_secs(20);
+        update_votes(
+            &mut lobby,
+            &LogLine::VoteFailed {
+                when: ended,
+                reason: "Not enough players voted".to_string(),
+            },
+        );
+        let schedule = scheduler.schedule(&lobby, &settings(), ended);
+        assert_eq!(schedule.next_attempt, Some(now + VOTE_CREATION_COOLDOWN));
+        assert_eq!(schedule.waiting_for.as_deref(), Some("Our vote cooldown"));
+        assert!(schedule.due(ended).is_none());
+
+        let later = now + VOTE_CREATION_COOLDOWN;
+        let schedule = scheduler.schedule(&lobby, &settings(), later);
+        assert_eq!(schedule.due(later).unwrap().name, "Cheater");
+        assert_eq!(
+            schedule.queue[0].not_before,
+            Some(ended + FAILED_KICK_VOTE_RETRY_DELAY)
+        );
+    }
+
+    #[test]
+    fn test_start_delay() {
+        let now = Local.with_ymd_and_hms(2024, 5, 6, 17, 0, 0).unwrap();
+        let lobby = lobby(vec![player(2, "Bot", Some(PlayerAttribute::Bot), true)]);
+
+        let scheduler = KickScheduler::new(now);
+        let schedule = scheduler.schedule(&lobby, &settings(), now);
+        assert!(schedule.due(now).is_none());
+        assert_eq!(schedule.waiting_for.as_deref(), Some("Starting up"));
+
+        let later = now + Duration::from_secs(VOTE_PERIOD_SECONDS as u64);
+        let schedule = scheduler.schedule(&lobby, &settings(), later);
+        assert_eq!(schedule.due(later).unwrap().name, "Bot");
+    }
+
+    #[test]
+    fn test_seconds_until() {
+        let now = Local.with_ymd_and_hms(2024, 5, 6, 17, 0, 0).unwrap();
+        assert_eq!(seconds_until(now + Duration::from_secs(42), now), 42);
+        assert_eq!(seconds_until(now - Duration::from_secs(42), now), 0);
+    }
+}
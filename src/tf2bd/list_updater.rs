@@ -0,0 +1,406 @@
+//
+// Keeps subscribed TF2BD lists up to date by downloading them from
+// the update_url in their file_info section.
+//
+
+use super::{
+    formats::{self, AttributeMapping, ListFormat},
+    models::TF2BDPlayerList,
+};
+use crate::{
+    appbus::{AppBus, AppEventMsg},
+    config::{TF2BD_LIST_UPDATE_LOOP_DELAY, TF2BD_LIST_UPDATE_PERIOD},
+    http_cache::{
+        ConditionalResponse, Validators, fetch_if_modified, load_validators, save_validators,
+    },
+    models::app_settings::{AppSettings, PlayerListSettings},
+    utils::BoxResult,
+};
+use bus::BusReader;
+use fs_err as fs;
+use std::{
+    collections::HashMap,
+    path::Path,
+    sync::{Arc, Mutex},
+    thread::{self, sleep},
+    time::Instant,
+};
+
+/// Start the background thread that updates remote TF2BD lists
+pub fn start(settings: &AppSettings, bus: &Arc<Mutex<AppBus>>) -> thread::JoinHandle<()> {
+    let mut list_updater = ListUpdaterThread::new(settings, bus);
+
+    thread::spawn(move || list_updater.run())
+}
+
+/// The http_cache domain the validators of the update URLs are stored under
+const HTTP_CACHE_DOMAIN: &str = "TF2BD Lists";
+
+struct ListUpdaterThread {
+    bus: Arc<Mutex<AppBus>>,
+    app_event_bus_rx: BusReader<AppEventMsg>,
+    app_settings: AppSettings,
+
+    /// Validators per update_url, loaded from the http_cache the first time the URL is used
+    validators: HashMap<String, Validators>,
+    last_update: Option<Instant>,
+
+    /// Set when the app exits
+    exit: bool,
+}
+
+impl ListUpdaterThread {
+    fn new(settings: &AppSettings, bus: &Arc<Mutex<AppBus>>) -> Self {
+        let app_event_bus_rx = bus.lock().unwrap().app_event_bus.add_rx();
+
+        Self {
+            bus: Arc::clone(bus),
+            app_event_bus_rx,
+            app_settings: settings.clone(),
+            validators: HashMap::new(),
+            last_update: None,
+            exit: false,
+        }
+    }
+
+    fn run(&mut self) {
+        log::info!("TF2BD list updater background thread started");
+
+        while !self.exit {
+            self.process_app_event_bus();
+            if self.exit {
+                break;
+            }
+
+            let time_to_update = match self.last_update {
+                Some(last_update) => last_update.elapsed() >= TF2BD_LIST_UPDATE_PERIOD,
+                None => true,
+            };
+
+            if time_to_update {
+                self.update_lists();
+                self.last_update = Some(Instant::now());
+            }
+
+            sleep(TF2BD_LIST_UPDATE_LOOP_DELAY);
+        }
+
+        log::info!("TF2BD list updater background thread stopped");
+    }
+
+    fn process_app_event_bus(&mut self) {
+        while let Ok(app_event) = self.app_event_bus_rx.try_recv() {
+            match app_event {
+                AppEventMsg::UpdatedSettings(settings) => {
+                    if settings.player_lists != self.app_settings.player_lists {
+                        // Check new lists right away
+                        self.last_update = None;
+                    }
+                    self.app_settings = settings;
+                }
+                AppEventMsg::Exit => self.exit = true,
+                _ => {}
+            }
+        }
+    }
+
+    fn update_lists(&mut self) {
+        // Never overwrite the list where the user's own flags are saved
+        let own_list = self.app_settings.own_player_list().cloned();
+
+        let player_lists: Vec<PlayerListSettings> = self
+            .app_settings
+            .player_lists
+            .iter()
+            .filter(|player_list| player_list.auto_update)
+            .filter(|player_list| Some(*player_list) != own_list.as_ref())
+            .cloned()
+            .collect();
+
+        let mapping = &self.app_settings.attribute_mapping;
+        for player_list in player_lists {
+            let Some(update_url) = get_update_url(&player_list, mapping) else {
+                continue;
+            };
+
+            let validators = self
+                .validators
+                .entry(update_url.clone())
+                .or_insert_with(|| load_validators(HTTP_CACHE_DOMAIN, &update_url));
+            let result = update_list(
+                &player_list.filename,
+                &update_url,
+                player_list.format,
+                mapping,
+                validators,
+            );
+            if result.is_ok() {
+                save_validators(HTTP_CACHE_DOMAIN, &update_url, validators);
+            }
+
+            match result {
+                Ok(true) => {
+                    log::info!(
+                        "Updated TF2BD list {} from {}",
+                        player_list.filename,
+                        update_url
+                    );
+                    self.bus.lock().unwrap().app_event_bus.broadcast(
+                        AppEventMsg::PlayerListUpdated {
+                            filename: player_list.filename.clone(),
+                        },
+                    );
+                }
+                Ok(false) => {
+                    log::info!("TF2BD list {} is up to date", player_list.filename);
+                }
+                Err(e) => {
+                    log::error!(
+                        "Failed to update TF2BD list {} from {}: {}",
+                        player_list.filename,
+                        update_url,
+                        e
+                    );
+                }
+            }
+        }
+    }
+}
+
+/// The URL the list is downloaded from, the update_url in the settings or else the one
+/// in the file_info section of the list. MAC lists have no file_info, so theirs is in the settings.
+pub fn update_url(
+    player_list: &PlayerListSettings,
+    playerlist: &TF2BDPlayerList,
+) -> Option<String> {
+    player_list
+        .update_url
+        .clone()
+        .or_else(|| {
+            playerlist
+                .file_info
+                .as_ref()
+                .map(|file_info| file_info.update_url.clone())
+        })
+        .filter(|update_url| !update_url.trim().is_empty())
+}
+
+/// Returns the update_url of the list, read from the local copy when it is not in the settings.
+/// A list with the update_url in the settings is downloaded even when there is no local copy yet.
+fn get_update_url(player_list: &PlayerListSettings, mapping: &AttributeMapping) -> Option<String> {
+    let playerlist = if Path::new(&player_list.filename).exists() {
+        formats::load(&player_list.filename, player_list.format, mapping)
+            .map(|(playerlist, _)| playerlist)
+            .unwrap_or_else(|_| TF2BDPlayerList::new())
+    } else {
+        TF2BDPlayerList::new()
+    };
+
+    update_url(player_list, &playerlist)
+}
+
+/// Downloads the list from update_url and replaces the local copy if it has changed.
+/// Returns true if the local copy was replaced.
+pub fn update_list(
+    filename: &str,
+    update_url: &str,
+    format: Option<ListFormat>,
+    mapping: &AttributeMapping,
+    validators: &mut Validators,
+) -> BoxResult<bool> {
+    let response = fetch_if_modified(update_url, validators).ok_or("request failed")?;
+
+    let (body, etag, last_modified) = match response {
+        ConditionalResponse::NotModified => return Ok(false),
+        ConditionalResponse::Modified {
+            body,
+            etag,
+            last_modified,
+        } => (body, etag, last_modified),
+    };
+
+    validate_playerlist(&body, format, mapping)?;
+
+    validators.etag = etag;
+    validators.last_modified = last_modified;
+
+    // Servers without ETag support send the same content every time
+    if let Ok(current) = fs::read_to_string(filename)
+        && current == body
+    {
+        return Ok(false);
+    }
+
+    write_atomically(filename, &body)?;
+
+    Ok(true)
+}
+
+/// Checks that the JSON is a playerlist in the format of the list, or in any format
+/// when it is detected, and that a TF2BD list has the v3 schema
+fn validate_playerlist(
+    json: &str,
+    format: Option<ListFormat>,
+    mapping: &AttributeMapping,
+) -> BoxResult<()> {
+    let (playerlist, format) = formats::from_json_str(json, format, mapping)?;
+
+    if format == ListFormat::Tf2bd
+        && !playerlist.schema.is_empty()
+        && !playerlist.schema.contains("/v3/")
+    {
+        return Err(format!("Unsupported schema: {}", playerlist.schema).into());
+    }
+
+    Ok(())
+}
+
+/// Writes to a temporary file next to the target and then renames it,
+/// so a reader never sees a half written list
+fn write_atomically(filename: &str, content: &str) -> BoxResult<()> {
+    let tmp_filename = format!("{}.tmp", filename);
+    fs::write(&tmp_filename, content)?;
+    fs::rename(&tmp_filename, filename)?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{
+        io::{BufRead, BufReader, Write},
+        net::TcpListener,
+    };
+
+    use crate::tf2bd::formats::default_attribute_mapping;
+
+    const FIXTURE: &str = include_str!("playerlist_fixture.json");
+    const VARIANT_FIXTURE: &str = include_str!("playerlist_variant_fixture.json");
+    const MAC_FIXTURE: &str = include_str!("playerlist_mac_fixture.json");
+    const ETAG: &str = "\"fixture-v1\"";
+
+    /// Serves the fixture with an ETag and replies 304 when the client sends the same ETag.
+    /// Handles `requests` requests, then stops.
+    fn start_server(body: &'static str, requests: usize) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        thread::spawn(move || {
+            for stream in listener.incoming().take(requests) {
+                let mut stream = stream.unwrap();
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+
+                let mut if_none_match = None;
+                loop {
+                    let mut line = String::new();
+                    reader.read_line(&mut line).unwrap();
+                    let line = line.trim_end();
+                    if line.is_empty() {
+                        break;
+                    }
+                    if let Some((name, value)) = line.split_once(':')
+                        && name.eq_ignore_ascii_case("if-none-match")
+                    {
+                        if_none_match = Some(value.trim().to_string());
+                    }
+                }
+
+                let response = if if_none_match.as_deref() == Some(ETAG) {
+                    "HTTP/1.1 304 Not Modified\r\nConnection: close\r\n\r\n".to_string()
+                } else {
+                    format!(
+                        "HTTP/1.1 200 OK\r\nETag: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                        ETAG,
+                        body.len(),
+                        body
+                    )
+                };
+                stream.write_all(response.as_bytes()).unwrap();
+            }
+        });
+
+        format!("http://{}/playerlist.json", addr)
+    }
+
+    #[test]
+    fn test_update_list_with_etag() {
+        let url = start_server(FIXTURE, 2);
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("playerlist.remote.json");
+        let filename = path.to_str().unwrap();
+        let mapping = default_attribute_mapping();
+        let mut validators = Validators::default();
+
+        // First request downloads the list
+        assert!(update_list(filename, &url, None, &mapping, &mut validators).unwrap());
+        assert_eq!(validators.etag.as_deref(), Some(ETAG));
+        assert_eq!(std::fs::read_to_string(filename).unwrap(), FIXTURE);
+        assert!(!Path::new(&format!("{}.tmp", filename)).exists());
+
+        // Second request sends the ETag and gets a 304
+        assert!(!update_list(filename, &url, None, &mapping, &mut validators).unwrap());
+        assert_eq!(std::fs::read_to_string(filename).unwrap(), FIXTURE);
+
+        let (playerlist, _) = formats::load(filename, None, &mapping).unwrap();
+        assert_eq!(playerlist.players.len(), 2);
+    }
+
+    #[test]
+    fn test_update_list_rejects_invalid_json() {
+        let url = start_server("{ \"this is\": \"not a playerlist\" }", 1);
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("playerlist.remote.json");
+        let filename = path.to_str().unwrap();
+        std::fs::write(filename, FIXTURE).unwrap();
+
+        let mapping = default_attribute_mapping();
+        let mut validators = Validators::default();
+        assert!(update_list(filename, &url, None, &mapping, &mut validators).is_err());
+        assert!(validators.etag.is_none());
+
+        // The local copy is untouched
+        assert_eq!(std::fs::read_to_string(filename).unwrap(), FIXTURE);
+    }
+
+    #[test]
+    fn test_update_mac_list() {
+        let url = start_server(MAC_FIXTURE, 1);
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("playerlist.remote.json");
+        let filename = path.to_str().unwrap();
+        let mapping = default_attribute_mapping();
+        let mut validators = Validators::default();
+
+        // A MAC list has no file_info, the update_url comes from the settings
+        // and is used before there is a local copy
+        let player_list = PlayerListSettings {
+            filename: filename.to_string(),
+            suggestion: true,
+            auto_update: true,
+            format: None,
+            update_url: Some(url.clone()),
+        };
+        assert_eq!(get_update_url(&player_list, &mapping), Some(url.clone()));
+
+        assert!(update_list(filename, &url, None, &mapping, &mut validators).unwrap());
+        let (playerlist, format) = formats::load(filename, None, &mapping).unwrap();
+        assert_eq!(format, ListFormat::Mac);
+        assert!(!playerlist.players.is_empty());
+    }
+
+    #[test]
+    fn test_validate_playerlist() {
+        let mapping = default_attribute_mapping();
+        assert!(validate_playerlist(FIXTURE, None, &mapping).is_ok());
+        assert!(validate_playerlist(VARIANT_FIXTURE, None, &mapping).is_ok());
+        assert!(validate_playerlist(MAC_FIXTURE, None, &mapping).is_ok());
+
+        // A list that changed to another format than the one set for it
+        assert!(validate_playerlist(MAC_FIXTURE, Some(ListFormat::Tf2bd), &mapping).is_err());
+        assert!(
+            validate_playerlist(r#"{ "$schema": "https://example.com/schemas/v2/playerlist.schema.json", "players": [] }"#, None, &mapping)
+                .is_err()
+        );
+    }
+}
@@ -1,12 +1,41 @@
+pub mod auto_vote;
+pub mod flag_exchange;
+pub mod flag_proof;
+pub mod formats;
+pub mod kick_scheduler;
+pub mod list_updater;
 pub mod models;
+pub mod rule_engine;
 pub mod ruleset_handler;
 pub mod tf2bd_thread;
 
 use crate::models::steamid::SteamID;
-use models::PlayerInfo;
+use kick_scheduler::KickSchedule;
+use models::{PlayerAttribute, PlayerInfo};
+use std::collections::HashMap;
+
+/// Where an attribute of a player's marking came from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MarkingSource {
+    /// The filename of the playerlist or the description of the rule that matched
+    pub source: String,
+
+    /// The attribute only comes from suggestion lists
+    pub suggestion: bool,
+}
+
+/// Where each attribute of a player's marking came from.
+/// Contains both trusted and suggested attributes.
+pub type MarkingSources = HashMap<PlayerAttribute, MarkingSource>;
 
 #[derive(Debug, Clone)]
 pub enum Tf2bdMsg {
-    /// SteamID, PlayerMarking
-    Tf2bdPlayerMarking(SteamID, Option<PlayerInfo>),
+    /// SteamID, PlayerMarking with trusted attributes, source of each attribute
+    Tf2bdPlayerMarking(SteamID, Option<PlayerInfo>, MarkingSources),
+
+    /// The player lists that failed to load, as "filename: error". Empty when all are fine.
+    PlayerListErrors(Vec<String>),
+
+    /// Who the kicker will call votes on and when, sent when it changes
+    KickSchedule(KickSchedule),
 }
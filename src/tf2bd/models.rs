@@ -3,21 +3,88 @@
 // https://raw.githubusercontent.com/PazerOP/tf2_bot_detector/master/schemas/v3/settings.schema.json
 //
 
+use crate::config::TF2BD_PLAYERLIST_BACKUPS;
 use fs_err as fs;
 use serde::{Deserialize, Serialize};
-use std::{io::Read, path::Path};
+use std::{fmt, path::Path};
+
+/// Why a TF2BD playerlist could not be loaded or saved
+#[derive(Debug)]
+pub enum PlayerListError {
+    Io(std::io::Error),
+    Json {
+        line: usize,
+        column: usize,
+        source: serde_json::Error,
+    },
+
+    /// Valid JSON that doesn't have the shape of a known playerlist format
+    Format(String),
+}
+
+impl fmt::Display for PlayerListError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PlayerListError::Io(e) => write!(f, "{}", e),
+            PlayerListError::Json {
+                line,
+                column,
+                source,
+            } => {
+                // serde_json adds the position to the message, it is shown first instead
+                let message = source.to_string();
+                let message = message.split(" at line ").next().unwrap_or_default();
+                write!(
+                    f,
+                    "Invalid JSON at line {}, column {}: {}",
+                    line, column, message
+                )
+            }
+            PlayerListError::Format(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+impl std::error::Error for PlayerListError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            PlayerListError::Io(e) => Some(e),
+            PlayerListError::Json { source, .. } => Some(source),
+            PlayerListError::Format(_) => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for PlayerListError {
+    fn from(e: std::io::Error) -> Self {
+        PlayerListError::Io(e)
+    }
+}
+
+impl From<serde_json::Error> for PlayerListError {
+    fn from(e: serde_json::Error) -> Self {
+        PlayerListError::Json {
+            line: e.line(),
+            column: e.column(),
+            source: e,
+        }
+    }
+}
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct TF2BDPlayerList {
     #[serde(rename = "$schema")]
     pub schema: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub file_info: Option<FileInfo>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub rules: Option<Vec<Rule>>,
     pub players: Vec<PlayerInfo>,
 }
 
 #[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Eq, Hash)]
 pub struct PlayerLastSeen {
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub player_name: Option<String>,
 
     /// Unix timestamp, use Local::now().timestamp() to get the current time
@@ -25,38 +92,83 @@ pub struct PlayerLastSeen {
 }
 
 #[derive(Serialize, Deserialize, Debug, Default, PartialEq, Clone, Eq, Hash)]
+#[serde(from = "RawPlayerInfo", into = "RawPlayerInfo")]
 pub struct PlayerInfo {
     pub attributes: Vec<PlayerAttribute>,
 
-    #[serde(skip_serializing_if = "Option::is_none")]
+    /// Attributes from other tools that TF2Monitor doesn't know about.
+    /// They are kept so they are written back when the list is saved.
+    pub unknown_attributes: Vec<String>,
+
     pub last_seen: Option<PlayerLastSeen>,
 
-    #[serde(rename = "steamid")]
+    /// Free text evidence, e.g. links to demos or chat logs
+    pub proof: Vec<String>,
+
     pub steamid32: String,
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+/// PlayerInfo as it is stored in the file
+#[derive(Serialize, Deserialize)]
+struct RawPlayerInfo {
+    attributes: Vec<String>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    last_seen: Option<PlayerLastSeen>,
+
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    proof: Vec<String>,
+
+    steamid: String,
+}
+
+impl From<RawPlayerInfo> for PlayerInfo {
+    fn from(raw: RawPlayerInfo) -> Self {
+        let (attributes, unknown_attributes) = split_attributes(raw.attributes);
+        Self {
+            attributes,
+            unknown_attributes,
+            last_seen: raw.last_seen,
+            proof: raw.proof,
+            steamid32: raw.steamid,
+        }
+    }
+}
+
+impl From<PlayerInfo> for RawPlayerInfo {
+    fn from(player_info: PlayerInfo) -> Self {
+        Self {
+            attributes: join_attributes(&player_info.attributes, &player_info.unknown_attributes),
+            last_seen: player_info.last_seen,
+            proof: player_info.proof,
+            steamid: player_info.steamid32,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Default, PartialEq, Clone)]
+#[serde(default)]
 pub struct FileInfo {
-    authors: Vec<String>,
-    description: String,
-    title: String,
-    update_url: String,
+    pub authors: Vec<String>,
+    pub description: String,
+    pub title: String,
+    pub update_url: String,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Rule {
-    actions: RuleAction,
-    description: String,
-    triggers: Trigger,
+    pub actions: RuleAction,
+    pub description: String,
+    pub triggers: Trigger,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Trigger {
     #[serde(default)]
-    mode: TriggerMode,
-    username_text_match: Option<TextMatch>,
-    chatmsg_text_match: Option<TextMatch>,
-    avatar_match: Option<Vec<AvatarMatch>>,
+    pub mode: TriggerMode,
+    pub username_text_match: Option<TextMatch>,
+    pub chatmsg_text_match: Option<TextMatch>,
+    pub avatar_match: Option<Vec<AvatarMatch>>,
 }
 
 #[derive(Default, Serialize, Deserialize, Debug, PartialEq, Clone)]
@@ -69,14 +181,14 @@ pub enum TriggerMode {
 
 #[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
 pub struct TextMatch {
-    case_sensitive: bool,
-    mode: TextMatchMode,
-    patterns: Vec<String>,
+    pub case_sensitive: bool,
+    pub mode: TextMatchMode,
+    pub patterns: Vec<String>,
 }
 
 #[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
 pub struct AvatarMatch {
-    avatar_hash: String,
+    pub avatar_hash: String,
 }
 
 #[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
@@ -91,11 +203,45 @@ pub enum TextMatchMode {
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(from = "RawRuleAction", into = "RawRuleAction")]
 pub struct RuleAction {
+    pub mark: Vec<PlayerAttribute>,
+    pub unmark: Vec<PlayerAttribute>,
+
+    /// Attributes TF2Monitor doesn't know about, kept for saving
+    pub unknown_mark: Vec<String>,
+    pub unknown_unmark: Vec<String>,
+}
+
+/// RuleAction as it is stored in the file
+#[derive(Serialize, Deserialize)]
+struct RawRuleAction {
     #[serde(default)]
-    mark: Vec<PlayerAttribute>,
+    mark: Vec<String>,
     #[serde(default)]
-    unmark: Vec<PlayerAttribute>,
+    unmark: Vec<String>,
+}
+
+impl From<RawRuleAction> for RuleAction {
+    fn from(raw: RawRuleAction) -> Self {
+        let (mark, unknown_mark) = split_attributes(raw.mark);
+        let (unmark, unknown_unmark) = split_attributes(raw.unmark);
+        Self {
+            mark,
+            unmark,
+            unknown_mark,
+            unknown_unmark,
+        }
+    }
+}
+
+impl From<RuleAction> for RawRuleAction {
+    fn from(action: RuleAction) -> Self {
+        Self {
+            mark: join_attributes(&action.mark, &action.unknown_mark),
+            unmark: join_attributes(&action.unmark, &action.unknown_unmark),
+        }
+    }
 }
 
 #[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Hash, Clone, Copy)]
@@ -110,8 +256,46 @@ pub enum PlayerAttribute {
     Cool,
 }
 
+impl PlayerAttribute {
+    /// Parses the name used in TF2BD files, e.g. "racist" for Toxic
+    pub fn from_tf2bd_name(name: &str) -> Option<Self> {
+        serde_json::from_value(serde_json::Value::String(name.to_string())).ok()
+    }
+
+    /// The name used in TF2BD files
+    pub fn tf2bd_name(&self) -> String {
+        match serde_json::to_value(self) {
+            Ok(serde_json::Value::String(name)) => name,
+            _ => format!("{:?}", self).to_lowercase(),
+        }
+    }
+}
+
+/// Splits attribute names into known attributes and unknown names
+fn split_attributes(names: Vec<String>) -> (Vec<PlayerAttribute>, Vec<String>) {
+    let mut attributes = Vec::new();
+    let mut unknown = Vec::new();
+
+    for name in names {
+        match PlayerAttribute::from_tf2bd_name(&name) {
+            Some(attribute) => attributes.push(attribute),
+            None => unknown.push(name),
+        }
+    }
+
+    (attributes, unknown)
+}
+
+fn join_attributes(attributes: &[PlayerAttribute], unknown: &[String]) -> Vec<String> {
+    attributes
+        .iter()
+        .map(PlayerAttribute::tf2bd_name)
+        .chain(unknown.iter().cloned())
+        .collect()
+}
+
 #[derive(Debug)]
-struct RulesStats {
+pub struct RulesStats {
     pub rules: usize,
 
     pub players: usize,
@@ -133,38 +317,38 @@ impl TF2BDPlayerList {
         }
     }
 
-    pub fn load(filename: &str) -> TF2BDPlayerList {
-        if Path::new(filename).exists() {
-            log::info!("Loading TF2BD rules file: {}", filename);
-            let mut f = fs::File::open(filename).unwrap();
-            let mut json = String::new();
-            f.read_to_string(&mut json).unwrap();
-            let rules = TF2BDPlayerList::from_json_str(&json);
-            log::info!("Stats: {:?}", rules.get_stats());
-
-            rules
-        } else {
-            log::info!(
-                "File {} does not exist. Creating an empty rules file",
-                filename
-            );
-            TF2BDPlayerList::new()
-        }
-    }
+    /// Loads the file with `load`, or the newest backup that can be loaded if the file is broken.
+    /// The error is returned together with the list so it can be shown to the user.
+    pub fn load_or_backup<F>(filename: &str, load: F) -> (TF2BDPlayerList, Option<PlayerListError>)
+    where
+        F: Fn(&str) -> Result<TF2BDPlayerList, PlayerListError>,
+    {
+        let error = match load(filename) {
+            Ok(rules) => return (rules, None),
+            Err(e) => e,
+        };
+        log::error!("Failed to load TF2BD rules file {}: {}", filename, error);
 
-    pub fn save(&self, filename: &str) {
-        log::info!("Saving TF2BD rules file: {}", filename);
-        let json = serde_json::to_string_pretty(self).unwrap();
-        let mut f = fs::File::create(filename).unwrap();
-        std::io::Write::write_all(&mut f, json.as_bytes()).unwrap();
-        log::info!("Saving done");
-    }
+        for backup in 1..=TF2BD_PLAYERLIST_BACKUPS {
+            let backup_filename = backup_filename(filename, backup);
+            if !Path::new(&backup_filename).exists() {
+                continue;
+            }
 
-    pub fn from_json_str(json: &str) -> TF2BDPlayerList {
-        serde_json::from_str(json).unwrap()
+            match load(&backup_filename) {
+                Ok(rules) => {
+                    log::warn!("Using backup {} instead", backup_filename);
+                    return (rules, Some(error));
+                }
+                Err(e) => log::error!("Failed to load backup {}: {}", backup_filename, e),
+            }
+        }
+
+        log::warn!("No usable backup of {}, using an empty list", filename);
+        (TF2BDPlayerList::new(), Some(error))
     }
 
-    fn get_stats(&self) -> RulesStats {
+    pub fn get_stats(&self) -> RulesStats {
         let mut result = RulesStats {
             rules: 0,
             players: self.players.len(),
@@ -203,10 +387,68 @@ impl TF2BDPlayerList {
     }
 }
 
+/// Writes a playerlist in any format after rotating the previous versions into
+/// filename.1.bak, filename.2.bak etc, with 1 being the newest.
+/// The current file is only rotated if `is_valid` accepts it, so a broken file never
+/// pushes a working backup out.
+pub fn write_with_backups(
+    filename: &str,
+    json: &str,
+    is_valid: impl Fn(&str) -> bool,
+) -> Result<(), PlayerListError> {
+    if let Ok(current) = fs::read_to_string(filename) {
+        if is_valid(&current) {
+            rotate_backups(filename)?;
+        } else {
+            log::warn!("Not keeping a backup of {}, it can't be loaded", filename);
+        }
+    }
+
+    // Write to a temporary file first so a crash never leaves a half written list
+    let tmp_filename = format!("{}.tmp", filename);
+    fs::write(&tmp_filename, json)?;
+    fs::rename(&tmp_filename, filename)?;
+
+    Ok(())
+}
+
+fn backup_filename(filename: &str, backup: usize) -> String {
+    format!("{}.{}.bak", filename, backup)
+}
+
+fn rotate_backups(filename: &str) -> std::io::Result<()> {
+    for backup in (1..TF2BD_PLAYERLIST_BACKUPS).rev() {
+        let from = backup_filename(filename, backup);
+        if Path::new(&from).exists() {
+            fs::rename(&from, backup_filename(filename, backup + 1))?;
+        }
+    }
+
+    if TF2BD_PLAYERLIST_BACKUPS > 0 {
+        fs::copy(filename, backup_filename(filename, 1))?;
+    }
+
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    /// Loads a v3 list without the format adapters, see formats::load for the app
+    fn load(filename: &str) -> Result<TF2BDPlayerList, PlayerListError> {
+        if Path::new(filename).exists() {
+            let json = fs::read_to_string(filename)?;
+            from_json_str(&json)
+        } else {
+            Ok(TF2BDPlayerList::new())
+        }
+    }
+
+    fn from_json_str(json: &str) -> Result<TF2BDPlayerList, PlayerListError> {
+        Ok(serde_json::from_str(json)?)
+    }
+
     #[test]
     fn test_from_json() {
         let json = r#"
@@ -250,7 +492,7 @@ mod tests {
                 }
            ]
         }"#;
-        let rules_file = TF2BDPlayerList::from_json_str(json);
+        let rules_file = from_json_str(json).unwrap();
         let rules = rules_file.rules.unwrap();
 
         assert_eq!(rules.len(), 1);
@@ -278,4 +520,100 @@ mod tests {
         assert!(rules_file.players.len() == 1);
         assert!(rules_file.players[0].steamid32 == "[U:1:1532246671]".to_string());
     }
+
+    #[test]
+    fn test_invalid_json_error() {
+        let json = "{\n  \"$schema\": \"\",\n  \"players\": [ oops ]\n}";
+        match from_json_str(json) {
+            Err(e @ PlayerListError::Json { .. }) => {
+                assert_eq!(
+                    e.to_string(),
+                    "Invalid JSON at line 3, column 16: expected value"
+                );
+            }
+            other => panic!("Expected a JSON error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_unknown_attributes_round_trip() {
+        let json = r#"{
+            "$schema": "",
+            "rules": [
+                {
+                    "actions": { "mark": [ "cheater", "sniper" ], "unmark": [ "kiwi" ] },
+                    "description": "description",
+                    "triggers": { "username_text_match": { "case_sensitive": true, "mode": "equal", "patterns": [ "x" ] } }
+                }
+            ],
+            "players": [ { "attributes": [ "racist", "sniper" ], "steamid": "[U:1:1532246671]" } ]
+        }"#;
+
+        let rules_file = from_json_str(json).unwrap();
+        let player = &rules_file.players[0];
+        assert_eq!(player.attributes, vec![PlayerAttribute::Toxic]);
+        assert_eq!(player.unknown_attributes, vec!["sniper".to_string()]);
+
+        let actions = &rules_file.rules.as_ref().unwrap()[0].actions;
+        assert_eq!(actions.mark, vec![PlayerAttribute::Cheater]);
+        assert_eq!(actions.unknown_mark, vec!["sniper".to_string()]);
+        assert_eq!(actions.unknown_unmark, vec!["kiwi".to_string()]);
+
+        let saved = serde_json::to_value(&rules_file).unwrap();
+        assert_eq!(
+            saved["players"][0]["attributes"],
+            serde_json::json!(["racist", "sniper"])
+        );
+        assert_eq!(
+            saved["rules"][0]["actions"]["mark"],
+            serde_json::json!(["cheater", "sniper"])
+        );
+        assert_eq!(
+            saved["rules"][0]["actions"]["unmark"],
+            serde_json::json!(["kiwi"])
+        );
+    }
+
+    fn is_valid(json: &str) -> bool {
+        from_json_str(json).is_ok()
+    }
+
+    #[test]
+    fn test_backups_and_fallback() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("playerlist.json");
+        let filename = path.to_str().unwrap();
+
+        let mut rules_file = TF2BDPlayerList::new();
+        for i in 0..3 {
+            rules_file.players.push(PlayerInfo {
+                attributes: vec![PlayerAttribute::Cheater],
+                steamid32: format!("[U:1:{}]", i),
+                ..Default::default()
+            });
+            let json = serde_json::to_string_pretty(&rules_file).unwrap();
+            write_with_backups(filename, &json, is_valid).unwrap();
+        }
+
+        // The previous two versions are kept, newest first
+        let backup = load(&backup_filename(filename, 1)).unwrap();
+        assert_eq!(backup.players.len(), 2);
+        let backup = load(&backup_filename(filename, 2)).unwrap();
+        assert_eq!(backup.players.len(), 1);
+        assert!(!Path::new(&backup_filename(filename, 3)).exists());
+
+        // A broken file falls back to the newest backup
+        std::fs::write(filename, "{ broken").unwrap();
+        let (rules_file, error) = TF2BDPlayerList::load_or_backup(filename, load);
+        assert!(matches!(error, Some(PlayerListError::Json { .. })));
+        assert_eq!(rules_file.players.len(), 2);
+
+        // Saving over the broken file keeps the working backups
+        let json = serde_json::to_string_pretty(&rules_file).unwrap();
+        write_with_backups(filename, &json, is_valid).unwrap();
+        let backup = load(&backup_filename(filename, 1)).unwrap();
+        assert_eq!(backup.players.len(), 2);
+        let backup = load(&backup_filename(filename, 2)).unwrap();
+        assert_eq!(backup.players.len(), 1);
+    }
 }
@@ -0,0 +1,355 @@
+//
+// Evaluates the `rules` section of TF2BD rules files against players.
+// A rule has triggers on the player's name, chat messages and avatar
+// and marks or unmarks the player with attributes when it matches.
+//
+
+use super::models::{PlayerAttribute, Rule, TextMatch, TextMatchMode, TriggerMode};
+use regex::{Regex, RegexBuilder};
+
+/// What is known about a player when evaluating the rules
+#[derive(Debug, Default, Clone)]
+pub struct RuleInput<'a> {
+    pub name: &'a str,
+    pub avatar_hash: Option<&'a str>,
+    pub chat_messages: Vec<&'a str>,
+}
+
+/// The result of a rule that matched a player
+#[derive(Debug, Clone, PartialEq)]
+pub struct RuleMatch {
+    /// The description of the rule, used as the source of the marking
+    pub description: String,
+    pub mark: Vec<PlayerAttribute>,
+    pub unmark: Vec<PlayerAttribute>,
+}
+
+pub struct RuleEngine {
+    rules: Vec<CompiledRule>,
+}
+
+struct CompiledRule {
+    rule: Rule,
+    username: Option<CompiledTextMatch>,
+    chatmsg: Option<CompiledTextMatch>,
+}
+
+struct CompiledTextMatch {
+    case_sensitive: bool,
+    mode: TextMatchMode,
+    patterns: Vec<String>,
+    regexes: Vec<Regex>,
+}
+
+impl RuleEngine {
+    pub fn new(rules: &[Rule]) -> Self {
+        let rules = rules
+            .iter()
+            .map(|rule| CompiledRule {
+                rule: rule.clone(),
+                username: rule
+                    .triggers
+                    .username_text_match
+                    .as_ref()
+                    .map(CompiledTextMatch::new),
+                chatmsg: rule
+                    .triggers
+                    .chatmsg_text_match
+                    .as_ref()
+                    .map(CompiledTextMatch::new),
+            })
+            .collect();
+
+        Self { rules }
+    }
+
+    pub fn num_rules(&self) -> usize {
+        self.rules.len()
+    }
+
+    /// Returns all rules that match the player, in the order they appear in the rules file
+    pub fn evaluate(&self, input: &RuleInput) -> Vec<RuleMatch> {
+        self.rules
+            .iter()
+            .filter(|rule| rule.matches(input))
+            .map(|rule| RuleMatch {
+                description: rule.rule.description.clone(),
+                mark: rule.rule.actions.mark.clone(),
+                unmark: rule.rule.actions.unmark.clone(),
+            })
+            .collect()
+    }
+}
+
+impl CompiledRule {
+    fn matches(&self, input: &RuleInput) -> bool {
+        // Each trigger that is present in the rule gives one result.
+        // Triggers that are not present in the rule are ignored.
+        let mut results: Vec<bool> = Vec::with_capacity(3);
+
+        if let Some(username) = &self.username {
+            results.push(username.matches(input.name));
+        }
+
+        if let Some(chatmsg) = &self.chatmsg {
+            results.push(input.chat_messages.iter().any(|msg| chatmsg.matches(msg)));
+        }
+
+        if let Some(avatar_match) = &self.rule.triggers.avatar_match {
+            let matched = match input.avatar_hash {
+                Some(avatar_hash) => avatar_match
+                    .iter()
+                    .any(|x| x.avatar_hash.eq_ignore_ascii_case(avatar_hash)),
+                None => false,
+            };
+            results.push(matched);
+        }
+
+        if results.is_empty() {
+            return false;
+        }
+
+        match self.rule.triggers.mode {
+            TriggerMode::MatchAll => results.iter().all(|x| *x),
+            TriggerMode::MatchAny => results.iter().any(|x| *x),
+        }
+    }
+}
+
+impl CompiledTextMatch {
+    fn new(text_match: &TextMatch) -> Self {
+        let regexes = if text_match.mode == TextMatchMode::Regex {
+            text_match
+                .patterns
+                .iter()
+                .filter_map(|pattern| {
+                    RegexBuilder::new(pattern)
+                        .case_insensitive(!text_match.case_sensitive)
+                        .build()
+                        .map_err(|e| log::warn!("Invalid regex '{}' in rule: {}", pattern, e))
+                        .ok()
+                })
+                .collect()
+        } else {
+            Vec::new()
+        };
+
+        let patterns = text_match
+            .patterns
+            .iter()
+            .map(|pattern| {
+                if text_match.case_sensitive {
+                    pattern.clone()
+                } else {
+                    pattern.to_lowercase()
+                }
+            })
+            .collect();
+
+        Self {
+            case_sensitive: text_match.case_sensitive,
+            mode: text_match.mode.clone(),
+            patterns,
+            regexes,
+        }
+    }
+
+    fn matches(&self, text: &str) -> bool {
+        if self.mode == TextMatchMode::Regex {
+            return self.regexes.iter().any(|rx| rx.is_match(text));
+        }
+
+        let text = if self.case_sensitive {
+            text.to_string()
+        } else {
+            text.to_lowercase()
+        };
+
+        self.patterns.iter().any(|pattern| match self.mode {
+            TextMatchMode::Equal => text == *pattern,
+            TextMatchMode::Contains => text.contains(pattern.as_str()),
+            TextMatchMode::StartsWith => text.starts_with(pattern.as_str()),
+            TextMatchMode::EndsWith => text.ends_with(pattern.as_str()),
+            TextMatchMode::Word => text
+                .split(|c: char| !c.is_alphanumeric())
+                .any(|word| word == pattern),
+            TextMatchMode::Regex => false,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tf2bd::models::{AvatarMatch, RuleAction, Trigger};
+
+    fn text_match(mode: TextMatchMode, case_sensitive: bool, patterns: &[&str]) -> TextMatch {
+        TextMatch {
+            case_sensitive,
+            mode,
+            patterns: patterns.iter().map(|x| x.to_string()).collect(),
+        }
+    }
+
+    fn rule(
+        mode: TriggerMode,
+        username: Option<TextMatch>,
+        chatmsg: Option<TextMatch>,
+        avatar: Option<&str>,
+    ) -> Rule {
+        Rule {
+            actions: RuleAction {
+                mark: vec![PlayerAttribute::Bot],
+                unmark: vec![],
+                unknown_mark: vec![],
+                unknown_unmark: vec![],
+            },
+            description: "test rule".to_string(),
+            triggers: Trigger {
+                mode,
+                username_text_match: username,
+                chatmsg_text_match: chatmsg,
+                avatar_match: avatar.map(|x| {
+                    vec![AvatarMatch {
+                        avatar_hash: x.to_string(),
+                    }]
+                }),
+            },
+        }
+    }
+
+    fn input<'a>(name: &'a str, avatar_hash: Option<&'a str>, chat: &[&'a str]) -> RuleInput<'a> {
+        RuleInput {
+            name,
+            avatar_hash,
+            chat_messages: chat.to_vec(),
+        }
+    }
+
+    #[test]
+    fn test_text_match_modes() {
+        let cases = [
+            (TextMatchMode::Equal, "OMEGATRONIC", "OMEGATRONIC", true),
+            (TextMatchMode::Equal, "OMEGATRONIC", "(1)OMEGATRONIC", false),
+            (TextMatchMode::Contains, "TRONIC", "(1)OMEGATRONIC", true),
+            (TextMatchMode::StartsWith, "(1)", "(1)OMEGATRONIC", true),
+            (TextMatchMode::StartsWith, "OMEGA", "(1)OMEGATRONIC", false),
+            (TextMatchMode::EndsWith, "TRONIC", "(1)OMEGATRONIC", true),
+            (
+                TextMatchMode::Regex,
+                r"^\(\d+\)OMEGA",
+                "(12)OMEGATRONIC",
+                true,
+            ),
+            (TextMatchMode::Regex, r"^\(\d+\)OMEGA", "OMEGATRONIC", false),
+            (TextMatchMode::Word, "bot", "this is a bot, really", true),
+            (TextMatchMode::Word, "bot", "robotics", false),
+        ];
+
+        for (mode, pattern, text, expected) in cases {
+            let rule = rule(
+                TriggerMode::MatchAll,
+                Some(text_match(mode.clone(), true, &[pattern])),
+                None,
+                None,
+            );
+            let engine = RuleEngine::new(&[rule]);
+            let actual = !engine.evaluate(&input(text, None, &[])).is_empty();
+            assert_eq!(actual, expected, "{:?} '{}' on '{}'", mode, pattern, text);
+        }
+    }
+
+    #[test]
+    fn test_case_sensitivity() {
+        let sensitive = rule(
+            TriggerMode::MatchAll,
+            Some(text_match(TextMatchMode::Contains, true, &["Tronic"])),
+            None,
+            None,
+        );
+        let insensitive = rule(
+            TriggerMode::MatchAll,
+            Some(text_match(TextMatchMode::Contains, false, &["Tronic"])),
+            None,
+            None,
+        );
+        let insensitive_regex = rule(
+            TriggerMode::MatchAll,
+            Some(text_match(TextMatchMode::Regex, false, &["tronic$"])),
+            None,
+            None,
+        );
+
+        let player = input("OMEGATRONIC", None, &[]);
+        assert!(RuleEngine::new(&[sensitive]).evaluate(&player).is_empty());
+        assert_eq!(RuleEngine::new(&[insensitive]).evaluate(&player).len(), 1);
+        assert_eq!(
+            RuleEngine::new(&[insensitive_regex])
+                .evaluate(&player)
+                .len(),
+            1
+        );
+    }
+
+    #[test]
+    fn test_trigger_modes() {
+        let username = Some(text_match(TextMatchMode::Equal, true, &["Bot"]));
+        let chatmsg = Some(text_match(TextMatchMode::Contains, false, &["discord.gg"]));
+
+        let match_all = RuleEngine::new(&[rule(
+            TriggerMode::MatchAll,
+            username.clone(),
+            chatmsg.clone(),
+            None,
+        )]);
+        let match_any = RuleEngine::new(&[rule(TriggerMode::MatchAny, username, chatmsg, None)]);
+
+        let name_only = input("Bot", None, &["hello"]);
+        let both = input("Bot", None, &["hello", "join DISCORD.GG/xyz"]);
+        let neither = input("Player", None, &["hello"]);
+
+        assert!(match_all.evaluate(&name_only).is_empty());
+        assert_eq!(match_all.evaluate(&both).len(), 1);
+        assert!(match_all.evaluate(&neither).is_empty());
+
+        assert_eq!(match_any.evaluate(&name_only).len(), 1);
+        assert_eq!(match_any.evaluate(&both).len(), 1);
+        assert!(match_any.evaluate(&neither).is_empty());
+    }
+
+    #[test]
+    fn test_avatar_match() {
+        let hash = "76c03c7865876dd13dbe4b60aad86150b8fc6233";
+        let engine = RuleEngine::new(&[rule(TriggerMode::MatchAll, None, None, Some(hash))]);
+
+        assert_eq!(engine.evaluate(&input("x", Some(hash), &[])).len(), 1);
+        assert_eq!(
+            engine
+                .evaluate(&input("x", Some(&hash.to_uppercase()), &[]))
+                .len(),
+            1
+        );
+        assert!(engine.evaluate(&input("x", Some("abc"), &[])).is_empty());
+        assert!(engine.evaluate(&input("x", None, &[])).is_empty());
+    }
+
+    #[test]
+    fn test_rule_match_result() {
+        let engine = RuleEngine::new(&[rule(
+            TriggerMode::MatchAll,
+            Some(text_match(TextMatchMode::Contains, true, &["TRONIC"])),
+            None,
+            None,
+        )]);
+
+        let result = engine.evaluate(&input("(1)OMEGATRONIC", None, &[]));
+        assert_eq!(
+            result,
+            vec![RuleMatch {
+                description: "test rule".to_string(),
+                mark: vec![PlayerAttribute::Bot],
+                unmark: vec![],
+            }]
+        );
+    }
+}
@@ -1,28 +1,57 @@
 use chrono::Local;
 
-use super::models::{PlayerAttribute, PlayerInfo, PlayerLastSeen, TF2BDPlayerList};
+use super::formats::{self, AttributeMapping, ListFormat};
+use super::models::{
+    PlayerAttribute, PlayerInfo, PlayerLastSeen, PlayerListError, Rule, TF2BDPlayerList,
+};
+use super::rule_engine::{RuleEngine, RuleInput, RuleMatch};
 use crate::models::steamid::SteamID;
 use std::collections::HashMap;
 
 pub struct RulesetHandler {
     pub source: String,
-    // pub suggestion: bool,
+
+    /// Markings from a suggestion list are only shown, never acted upon
+    pub suggestion: bool,
+
+    /// The format of the file, it is saved in the same format
+    pub format: ListFormat,
     pub tf2bd_playerlist: TF2BDPlayerList,
 
     pub player_rules: HashMap<SteamID, PlayerInfo>,
+
+    /// The rules section of the rules file, ready to be evaluated
+    pub rule_engine: RuleEngine,
+
+    /// Set when the file could not be loaded and a backup or an empty list is used instead
+    pub load_error: Option<PlayerListError>,
+
+    /// The list is replaced when it is downloaded again, so local edits to it would be lost
+    pub auto_updated: bool,
 }
 
 impl RulesetHandler {
-    pub fn new(source: &str, _suggestion: bool) -> Self {
-        let rules_file = TF2BDPlayerList::load(source);
+    pub fn new(
+        source: &str,
+        suggestion: bool,
+        format: Option<ListFormat>,
+        mapping: &AttributeMapping,
+    ) -> Self {
+        let (rules_file, format, load_error) = formats::load_or_backup(source, format, mapping);
 
         let player_rules = get_player_rules(&rules_file);
+        let rule_engine = RuleEngine::new(rules_file.rules.as_deref().unwrap_or_default());
+        log::info!("Loaded {} rules from {}", rule_engine.num_rules(), source);
 
         Self {
             source: source.to_string(),
-            //suggestion,
+            suggestion,
+            format,
             tf2bd_playerlist: rules_file,
             player_rules,
+            rule_engine,
+            load_error,
+            auto_updated: false,
         }
     }
 
@@ -30,6 +59,20 @@ impl RulesetHandler {
         self.player_rules.get(steamid)
     }
 
+    /// Returns the rules that match the player's name, avatar or chat messages
+    pub fn get_rule_matches(&self, input: &RuleInput) -> Vec<RuleMatch> {
+        self.rule_engine.evaluate(input)
+    }
+
+    /// Appends a rule to the rules section and saves the file
+    pub fn add_rule(&mut self, rule: Rule) {
+        let rules = self.tf2bd_playerlist.rules.get_or_insert_with(Vec::new);
+        rules.push(rule);
+        self.rule_engine = RuleEngine::new(rules);
+
+        self.save();
+    }
+
     pub fn set_player_flags(
         &mut self,
         steamid: SteamID,
@@ -43,7 +86,7 @@ impl RulesetHandler {
                 player_info.attributes.push(flag);
             }
 
-            if player_info.attributes.is_empty() {
+            if player_info.attributes.is_empty() && player_info.unknown_attributes.is_empty() {
                 self.player_rules.remove(&steamid);
             }
         } else if enable {
@@ -56,6 +99,8 @@ impl RulesetHandler {
                 steamid32: steamid.to_steam_id32(),
                 last_seen: Some(last_seen),
                 attributes: vec![flag],
+                unknown_attributes: Vec::new(),
+                proof: Vec::new(),
             };
 
             self.player_rules.insert(steamid, player_info);
@@ -67,18 +112,30 @@ impl RulesetHandler {
     }
 
     fn save(&mut self) {
+        // Saving would replace the list that failed to load with the fallback
+        if let Some(error) = &self.load_error {
+            log::error!(
+                "Not saving {}, it failed to load ({}). Changes are kept until restart",
+                self.source,
+                error
+            );
+            return;
+        }
+
         let mut rules_file = self.tf2bd_playerlist.clone();
         rules_file.schema = "https://raw.githubusercontent.com/PazerOP/tf2_bot_detector/master/schemas/v3/playerlist.schema.json".to_string();
 
         rules_file.players.clear();
 
         for player_info in self.player_rules.values() {
-            if !player_info.attributes.is_empty() {
+            if !player_info.attributes.is_empty() || !player_info.unknown_attributes.is_empty() {
                 rules_file.players.push(player_info.clone());
             }
         }
 
-        rules_file.save(&self.source);
+        if let Err(e) = formats::save(&rules_file, &self.source, self.format) {
+            log::error!("Failed to save TF2BD rules file {}: {}", self.source, e);
+        }
     }
 }
 
@@ -92,3 +149,26 @@ fn get_player_rules(rules_file: &TF2BDPlayerList) -> HashMap<SteamID, PlayerInfo
 
     result
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_no_save_after_load_error() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("playerlist.json");
+        let filename = path.to_str().unwrap();
+        std::fs::write(filename, "{ broken").unwrap();
+
+        let mut handler = RulesetHandler::new(filename, false, None, &AttributeMapping::new());
+        assert!(handler.load_error.is_some());
+
+        // The edit is kept in memory but the broken file is left alone
+        let steamid = SteamID::from_steam_id32("[U:1:1]");
+        handler.set_player_flags(steamid, None, PlayerAttribute::Cheater, true);
+        assert!(handler.get_player_marking(&steamid).is_some());
+        assert_eq!(std::fs::read_to_string(filename).unwrap(), "{ broken");
+        assert!(!dir.path().join("playerlist.json.1.bak").exists());
+    }
+}
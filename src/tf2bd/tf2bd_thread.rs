@@ -1,4 +1,13 @@
-use super::{Tf2bdMsg, models::PlayerAttribute, ruleset_handler::RulesetHandler};
+use super::rule_engine::{RuleInput, RuleMatch};
+use super::{
+    MarkingSource, MarkingSources, Tf2bdMsg,
+    auto_vote::auto_vote_choice,
+    flag_proof, formats,
+    kick_scheduler::{KickSchedule, KickScheduler},
+    list_updater,
+    models::{PlayerAttribute, PlayerInfo, Rule},
+    ruleset_handler::RulesetHandler,
+};
 use crate::config::TF2BD_LOOP_DELAY;
 use crate::db::db::DbPool;
 use crate::db::entities::NewPlayerFlag;
@@ -6,21 +15,16 @@ use crate::db::queries;
 use crate::{
     appbus::{AppBus, AppEventMsg},
     models::{app_settings::AppSettings, steamid::SteamID},
-    tf2::lobby::{Lobby, Player, Team},
+    tf2::lobby::{Lobby, Player},
 };
 use bus::BusReader;
-use chrono::Utc;
+use chrono::{DateTime, Local, Utc};
 use std::{
-    collections::HashSet,
+    collections::{HashMap, HashSet},
     sync::{Arc, Mutex},
     thread::{self, sleep},
-    time::Instant,
 };
 
-const FILENAME: &str = "playerlist.json";
-
-const VOTE_PERIOD_SECONDS: u64 = 10;
-
 pub fn start(
     settings: &AppSettings,
     bus: &Arc<Mutex<AppBus>>,
@@ -38,14 +42,46 @@ struct Tf2bdThread {
 
     app_settings: AppSettings,
 
-    ruleset_handler: RulesetHandler,
+    /// One handler per playerlist in the settings, in the same order
+    ruleset_handlers: Vec<RulesetHandler>,
+
+    /// Edits that were refused, shown with the player list errors
+    rejected_edits: Vec<String>,
 
     db: DbPool,
 
     last_lobby_id: String,
-    last_vote_time: Instant,
+
+    /// A replayed match is not running, so no votes are called or answered and the party is not told
+    replaying: bool,
+    kick_scheduler: KickScheduler,
+
+    /// The last kick schedule sent to the lobby
+    kick_schedule: KickSchedule,
+
+    /// The lobby and start time of the last vote we answered automatically
+    auto_voted: Option<(String, DateTime<Local>)>,
 
     notifications_send: HashSet<SteamID>,
+
+    /// Rule matches per player for the current lobby, with the suggestion flag of the list.
+    /// Only re-evaluated when the player's name, avatar or number of chat messages changes.
+    rule_matches: HashMap<SteamID, (RuleMatchKey, Vec<(bool, RuleMatch)>)>,
+
+    /// The markings written to player_flags per player in the current lobby,
+    /// so they are only written again when they change
+    persisted_markings: HashMap<SteamID, MarkingSources>,
+
+    /// Set when the app exits
+    exit: bool,
+}
+
+/// What the rule matches for a player were computed from
+#[derive(Debug, Clone, PartialEq)]
+struct RuleMatchKey {
+    name: String,
+    avatar_hash: Option<String>,
+    chat_messages: usize,
 }
 
 impl Tf2bdThread {
@@ -53,7 +89,7 @@ impl Tf2bdThread {
         let shared_lobby = bus.lock().unwrap().shared_lobby.clone();
         let app_event_bus_rx = bus.lock().unwrap().app_event_bus.add_rx();
 
-        let ruleset_handler = RulesetHandler::new(FILENAME, false);
+        let ruleset_handlers = load_player_lists(settings);
 
         Self {
             bus: Arc::clone(bus),
@@ -62,37 +98,70 @@ impl Tf2bdThread {
 
             app_settings: settings.clone(),
 
-            ruleset_handler,
+            ruleset_handlers,
+            rejected_edits: Vec::new(),
 
             db: db.clone(),
 
             last_lobby_id: String::new(),
 
-            last_vote_time: Instant::now(),
+            replaying: bus.lock().unwrap().replaying,
+
+            kick_scheduler: KickScheduler::new(Local::now()),
+            kick_schedule: KickSchedule::default(),
+
+            auto_voted: None,
 
             notifications_send: HashSet::new(),
+
+            rule_matches: HashMap::new(),
+            persisted_markings: HashMap::new(),
+            exit: false,
         }
     }
 
     pub fn run(&mut self) {
         log::info!("TF2BD background thread started");
 
-        loop {
+        self.send_player_list_errors();
+
+        while !self.exit {
             self.process_bus();
 
             self.apply_rules_to_lobby();
-            self.send_notifications();
+            if !self.replaying {
+                self.send_notifications();
 
-            self.do_callvotes();
+                self.do_callvotes();
+                self.do_auto_vote();
+            }
 
             sleep(TF2BD_LOOP_DELAY);
         }
+
+        log::info!("TF2BD background thread stopped");
     }
 
     fn send(&self, msg: Tf2bdMsg) {
         self.bus.lock().unwrap().tf2bd_bus.broadcast(msg);
     }
 
+    fn send_player_list_errors(&self) {
+        let errors = self
+            .ruleset_handlers
+            .iter()
+            .filter_map(|ruleset_handler| {
+                ruleset_handler
+                    .load_error
+                    .as_ref()
+                    .map(|e| format!("{}: {}", ruleset_handler.source, e))
+            })
+            .chain(self.rejected_edits.iter().cloned())
+            .collect();
+
+        self.send(Tf2bdMsg::PlayerListErrors(errors));
+    }
+
     fn process_bus(&mut self) {
         self.get_latest_lobby();
         self.process_app_event_bus();
@@ -103,6 +172,8 @@ impl Tf2bdThread {
         if self.last_lobby_id != lobby.lobby_id {
             log::info!("New lobby detected: {}", lobby.lobby_id);
             self.notifications_send.clear();
+            self.rule_matches.clear();
+            self.persisted_markings.clear();
             self.last_lobby_id = lobby.lobby_id.clone();
         }
     }
@@ -115,37 +186,115 @@ impl Tf2bdThread {
                     name,
                     flag,
                     enable,
-                } => self.set_player_flag(steamid, &name, flag, enable),
-                AppEventMsg::UpdatedSettings(settings) => self.app_settings = settings,
+                    reason,
+                } => self.set_player_flag(steamid, name.as_deref(), flag, enable, reason),
+                AppEventMsg::UpdatedSettings(settings) => self.updated_settings(settings),
+                AppEventMsg::PlayerListUpdated { filename } => self.reload_player_list(&filename),
+                AppEventMsg::AddRule { filename, rule } => self.add_rule(&filename, rule),
+                AppEventMsg::Exit => self.exit = true,
+            }
+        }
+    }
+
+    fn updated_settings(&mut self, settings: AppSettings) {
+        if settings.player_lists != self.app_settings.player_lists
+            || settings.attribute_mapping != self.app_settings.attribute_mapping
+        {
+            log::info!("Player lists changed, reloading them");
+            self.ruleset_handlers = load_player_lists(&settings);
+            self.rejected_edits.clear();
+            self.rule_matches.clear();
+            self.send_player_list_errors();
+        }
+
+        self.app_settings = settings;
+    }
+
+    fn reload_player_list(&mut self, filename: &str) {
+        for ruleset_handler in self.ruleset_handlers.iter_mut() {
+            if ruleset_handler.source == filename {
+                log::info!("Reloading updated player list {}", filename);
+                let auto_updated = ruleset_handler.auto_updated;
+                *ruleset_handler = RulesetHandler::new(
+                    filename,
+                    ruleset_handler.suggestion,
+                    Some(ruleset_handler.format),
+                    &self.app_settings.attribute_mapping,
+                );
+                ruleset_handler.auto_updated = auto_updated;
+                self.rule_matches.clear();
             }
         }
+        self.send_player_list_errors();
+    }
+
+    fn add_rule(&mut self, filename: &str, rule: Rule) {
+        let Some(ruleset_handler) = self
+            .ruleset_handlers
+            .iter_mut()
+            .find(|ruleset_handler| ruleset_handler.source == filename)
+        else {
+            log::error!("Can't add rule, {} is not a loaded player list", filename);
+            return;
+        };
+
+        if ruleset_handler.auto_updated {
+            let error = format!(
+                "{}: Rule '{}' was not added, the list is replaced by auto updates. Add it to another list or turn off Auto update for this one.",
+                filename, rule.description
+            );
+            log::error!("{}", error);
+            self.rejected_edits.push(error);
+            self.send_player_list_errors();
+            return;
+        }
+
+        if !formats::adapter(ruleset_handler.format).supports_rules() {
+            let error = format!(
+                "{}: Rule '{}' was not added, {} lists have no rules. Add it to a TF2BD list.",
+                filename, rule.description, ruleset_handler.format
+            );
+            log::error!("{}", error);
+            self.rejected_edits.push(error);
+            self.send_player_list_errors();
+            return;
+        }
+
+        log::info!("Adding rule '{}' to {}", rule.description, filename);
+        ruleset_handler.add_rule(rule);
+        self.rule_matches.clear();
+    }
+
+    /// The list where flags set in TF2Monitor are saved, the first trusted list
+    fn own_player_list(&mut self) -> Option<&mut RulesetHandler> {
+        self.ruleset_handlers
+            .iter_mut()
+            .find(|ruleset_handler| !ruleset_handler.suggestion)
     }
 
     fn set_player_flag(
         &mut self,
         steamid: SteamID,
-        player_name: &str,
+        player_name: Option<&str>,
         player_attribute: PlayerAttribute,
         enable: bool,
+        reason: Option<String>,
     ) {
         log::info!(
             "Setting player attribute {:?} for {} ({}) to {}",
             player_attribute,
-            player_name,
+            player_name.unwrap_or_default(),
             steamid.to_u64(),
             enable
         );
 
-        // Send out the updated marking
-        let data = self.ruleset_handler.get_player_marking(&steamid);
-        self.send(Tf2bdMsg::Tf2bdPlayerMarking(steamid, data.cloned()));
-
-        self.ruleset_handler.set_player_flags(
-            steamid,
-            Some(player_name),
-            player_attribute,
-            enable,
-        );
+        // The updated marking is sent out by apply_rules_to_lobby()
+        match self.own_player_list() {
+            Some(ruleset_handler) => {
+                ruleset_handler.set_player_flags(steamid, player_name, player_attribute, enable)
+            }
+            None => log::warn!("There is no trusted player list to save the flag to"),
+        }
 
         // Persist to database
         if let Ok(mut conn) = self.db.get() {
@@ -157,11 +306,13 @@ impl Tf2bdThread {
                 // Add or update the flag
                 let new_flag = NewPlayerFlag {
                     steam_id: steamid.to_u64() as i64,
-                    flag_type,
-                    source,
+                    flag_type: flag_type.clone(),
+                    source: source.clone(),
                     first_seen: current_time,
                     last_seen: current_time,
                     notified: false,
+                    suggestion: false,
+                    from_marking: false,
                 };
 
                 if let Err(e) = queries::upsert_player_flag(&mut conn, new_flag) {
@@ -171,6 +322,24 @@ impl Tf2bdThread {
                         e
                     );
                 }
+
+                // Keep a record of why and in what situation the player was flagged
+                let lobby = self.shared_lobby.get();
+                if let Some(proof) = flag_proof::capture_flag_proof(
+                    &lobby,
+                    steamid,
+                    &flag_type,
+                    &source,
+                    reason,
+                    current_time,
+                ) && let Err(e) = queries::insert_player_flag_proof(&mut conn, &proof)
+                {
+                    log::error!(
+                        "Failed to persist player flag proof for {}: {}",
+                        steamid.to_u64(),
+                        e
+                    );
+                }
             } else {
                 // Remove the flag
                 if let Err(e) = queries::remove_player_flag(
@@ -192,121 +361,285 @@ impl Tf2bdThread {
     fn apply_rules_to_lobby(&mut self) {
         let lobby = self.shared_lobby.get();
         for player in &lobby.players {
-            let data = self
-                .ruleset_handler
-                .get_player_marking(&player.steamid)
-                .cloned();
-            self.send(Tf2bdMsg::Tf2bdPlayerMarking(player.steamid, data.clone()));
-
-            // Persist flags from ruleset to database
-            if let Some(player_info) = &data {
-                if let Ok(mut conn) = self.db.get() {
-                    let current_time = Utc::now().timestamp();
-
-                    for attribute in &player_info.attributes {
-                        let flag_type = format!("{:?}", attribute);
-                        // Get the source from file_info if available, otherwise use filename
-                        let source = FILENAME.to_string();
-
-                        let new_flag = NewPlayerFlag {
-                            steam_id: player.steamid.to_u64() as i64,
-                            flag_type,
-                            source,
-                            first_seen: current_time,
-                            last_seen: current_time,
-                            notified: false,
-                        };
-
-                        if let Err(e) = queries::upsert_player_flag(&mut conn, new_flag) {
-                            log::debug!(
-                                "Failed to persist player flag for {}: {}",
-                                player.steamid.to_u64(),
-                                e
-                            );
-                        }
-                    }
-                }
-            }
+            let (data, sources) = self.get_player_marking(&lobby, player);
+            self.send(Tf2bdMsg::Tf2bdPlayerMarking(
+                player.steamid,
+                data.clone(),
+                sources.clone(),
+            ));
+
+            self.persist_markings(player.steamid, sources);
         }
     }
 
-    fn do_callvotes(&mut self) {
-        if !self.app_settings.kick_cheaters && !self.app_settings.kick_bots {
+    /// Persist flags from ruleset to database, with whether they are only suggestions.
+    /// Flags written from an earlier marking that the player no longer has are removed,
+    /// e.g. after a list update or when a rule stops matching.
+    /// Only written when the player's markings changed since they were last written in this lobby.
+    fn persist_markings(&mut self, steamid: SteamID, sources: MarkingSources) {
+        if self.persisted_markings.get(&steamid) == Some(&sources) {
             return;
         }
 
-        let passed_time = self.last_vote_time.elapsed().as_secs();
-        if passed_time < VOTE_PERIOD_SECONDS {
-            // log::info!("Not time yet to call a vote");
+        let Ok(mut conn) = self.db.get() else {
             return;
+        };
+        let current_time = Utc::now().timestamp();
+
+        let stale_flags = match queries::get_player_flags(&mut conn, steamid.to_u64() as i64) {
+            Ok(flags) => flags.into_iter().filter(|flag| {
+                flag.from_marking
+                    && !sources.iter().any(|(attribute, marking_source)| {
+                        flag.flag_type == format!("{:?}", attribute)
+                            && flag.source == marking_source.source
+                    })
+            }),
+            Err(e) => {
+                log::debug!(
+                    "Failed to load player flags for {}: {}",
+                    steamid.to_u64(),
+                    e
+                );
+                return;
+            }
+        };
+
+        for flag in stale_flags {
+            if let Err(e) =
+                queries::remove_player_flag(&mut conn, flag.steam_id, &flag.flag_type, &flag.source)
+            {
+                log::debug!(
+                    "Failed to remove player flag for {}: {}",
+                    steamid.to_u64(),
+                    e
+                );
+                return;
+            }
         }
 
-        let player_to_kick = self.find_player_to_kick();
-        if player_to_kick.is_none() {
-            self.last_vote_time = Instant::now();
-            log::info!("Found no player to kick");
-            return;
+        for (attribute, marking_source) in &sources {
+            let new_flag = NewPlayerFlag {
+                steam_id: steamid.to_u64() as i64,
+                flag_type: format!("{:?}", attribute),
+                // The playerlist filename or the description of the matching rule
+                source: marking_source.source.clone(),
+                first_seen: current_time,
+                last_seen: current_time,
+                notified: false,
+                suggestion: marking_source.suggestion,
+                from_marking: true,
+            };
+
+            if let Err(e) = queries::upsert_player_flag(&mut conn, new_flag) {
+                log::debug!(
+                    "Failed to persist player flag for {}: {}",
+                    steamid.to_u64(),
+                    e
+                );
+                return;
+            }
         }
 
-        let player_to_kick = player_to_kick.unwrap();
+        self.persisted_markings.insert(steamid, sources);
+    }
+
+    /// Merges the player's markings in all playerlists with the rules that match the player.
+    /// Trusted lists are merged first so they win over suggestion lists.
+    /// Rules can both mark and unmark attributes, unmarks are applied last.
+    /// The returned PlayerInfo only contains the trusted attributes.
+    fn get_player_marking(
+        &mut self,
+        lobby: &Lobby,
+        player: &Player,
+    ) -> (Option<PlayerInfo>, MarkingSources) {
+        let rule_matches = self.get_rule_matches(lobby, player);
+
+        let mut attributes: Vec<PlayerAttribute> = Vec::new();
+        let mut sources = MarkingSources::new();
+        let mut last_seen = None;
+
+        for suggestion in [false, true] {
+            for ruleset_handler in &self.ruleset_handlers {
+                if ruleset_handler.suggestion != suggestion {
+                    continue;
+                }
 
-        log::info!("Calling vote to kick player {}", player_to_kick.name);
-        let cmd = format!("callvote kick \"{} cheating\"", player_to_kick.id);
-        self.bus.lock().unwrap().send_rcon_cmd(cmd.as_str());
+                if let Some(player_info) = ruleset_handler.get_player_marking(&player.steamid) {
+                    if last_seen.is_none() {
+                        last_seen.clone_from(&player_info.last_seen);
+                    }
 
-        self.last_vote_time = Instant::now();
-    }
+                    for attribute in &player_info.attributes {
+                        add_marking(
+                            &mut attributes,
+                            &mut sources,
+                            *attribute,
+                            &ruleset_handler.source,
+                            suggestion,
+                        );
+                    }
+                }
+            }
 
-    fn find_player_to_kick(&self) -> Option<Player> {
-        let lobby = self.shared_lobby.get();
-        let me = lobby.get_me()?;
-        let team = me.team;
+            for (rule_suggestion, rule_match) in &rule_matches {
+                if *rule_suggestion != suggestion {
+                    continue;
+                }
 
-        if self.app_settings.kick_bots {
-            if let Some(bot_to_kick) = self.find_player_in_team(&lobby, team, PlayerAttribute::Bot)
-            {
-                return Some(bot_to_kick);
+                for attribute in &rule_match.mark {
+                    add_marking(
+                        &mut attributes,
+                        &mut sources,
+                        *attribute,
+                        &rule_match.description,
+                        suggestion,
+                    );
+                }
             }
         }
 
-        if self.app_settings.kick_cheaters {
-            if let Some(cheater_to_kick) =
-                self.find_player_in_team(&lobby, team, PlayerAttribute::Cheater)
-            {
-                return Some(cheater_to_kick);
+        // Rules in a suggestion list can only unmark suggested attributes
+        for (suggestion, rule_match) in &rule_matches {
+            for attribute in &rule_match.unmark {
+                let removable = match sources.get(attribute) {
+                    Some(source) => !suggestion || source.suggestion,
+                    None => false,
+                };
+                if removable {
+                    sources.remove(attribute);
+                }
             }
         }
 
-        None
+        attributes.retain(|attribute| {
+            sources
+                .get(attribute)
+                .is_some_and(|source| !source.suggestion)
+        });
+
+        if attributes.is_empty() {
+            return (None, sources);
+        }
+
+        let player_info = PlayerInfo {
+            attributes,
+            unknown_attributes: Vec::new(),
+            proof: Vec::new(),
+            last_seen,
+            steamid32: player.steamid.to_steam_id32(),
+        };
+
+        (Some(player_info), sources)
     }
 
-    fn find_player_in_team(
-        &self,
-        lobby: &Lobby,
-        team: Team,
-        player_attribute: PlayerAttribute,
-    ) -> Option<Player> {
-        let candidates: Vec<&Player> = lobby
-            .players
+    fn get_rule_matches(&mut self, lobby: &Lobby, player: &Player) -> Vec<(bool, RuleMatch)> {
+        let chat_messages: Vec<&str> = lobby
+            .chat
             .iter()
-            .filter(|player| Self::ok_to_kick(player, team, player_attribute))
+            .filter(|chat| chat.steamid == player.steamid && !chat.system)
+            .map(|chat| chat.message.as_str())
             .collect();
 
-        candidates.first().map(|p| (*p).clone())
+        let avatar_hash = player
+            .steam_info
+            .as_ref()
+            .and_then(|steam_info| steam_info.avatar_hash.clone());
+
+        let key = RuleMatchKey {
+            name: player.name.clone(),
+            avatar_hash: avatar_hash.clone(),
+            chat_messages: chat_messages.len(),
+        };
+
+        if let Some((cached_key, rule_matches)) = self.rule_matches.get(&player.steamid)
+            && *cached_key == key
+        {
+            return rule_matches.clone();
+        }
+
+        let input = RuleInput {
+            name: &player.name,
+            avatar_hash: avatar_hash.as_deref(),
+            chat_messages,
+        };
+        let mut rule_matches = Vec::new();
+        for ruleset_handler in &self.ruleset_handlers {
+            for rule_match in ruleset_handler.get_rule_matches(&input) {
+                rule_matches.push((ruleset_handler.suggestion, rule_match));
+            }
+        }
+
+        for (_, rule_match) in &rule_matches {
+            log::info!(
+                "Rule '{}' matched player {}",
+                rule_match.description,
+                player.name
+            );
+        }
+
+        self.rule_matches
+            .insert(player.steamid, (key, rule_matches.clone()));
+
+        rule_matches
     }
 
-    fn ok_to_kick(player: &Player, team: Team, player_attribute: PlayerAttribute) -> bool {
-        if player.team != team {
-            return false;
+    fn do_callvotes(&mut self) {
+        let lobby = self.shared_lobby.get();
+        let now = Local::now();
+
+        let mut schedule = if self.app_settings.kick_cheaters || self.app_settings.kick_bots {
+            self.kick_scheduler
+                .schedule(&lobby, &self.app_settings, now)
+        } else {
+            KickSchedule::default()
+        };
+
+        if let Some(target) = schedule.due(now) {
+            log::info!(
+                "Calling vote to kick player {} ({:?})",
+                target.name,
+                target.attribute
+            );
+            let cmd = format!("callvote kick \"{} cheating\"", target.user_id);
+            self.bus.lock().unwrap().send_rcon_cmd(cmd.as_str());
+
+            self.kick_scheduler.called(now);
+            schedule = self
+                .kick_scheduler
+                .schedule(&lobby, &self.app_settings, now);
         }
 
-        if let Some(player_info) = &player.player_info {
-            if player_info.attributes.contains(&player_attribute) {
-                return true;
-            }
+        if schedule != self.kick_schedule {
+            self.kick_schedule = schedule.clone();
+            self.send(Tf2bdMsg::KickSchedule(schedule));
         }
+    }
 
-        false
+    /// Answers the running kick vote according to the auto vote settings
+    fn do_auto_vote(&mut self) {
+        let lobby = self.shared_lobby.get();
+        let Some(vote) = lobby.active_vote() else {
+            return;
+        };
+
+        let key = (lobby.lobby_id.clone(), vote.started);
+        if self.auto_voted.as_ref() == Some(&key) {
+            return;
+        }
+
+        let Some(yes) = auto_vote_choice(&lobby, vote, &self.app_settings) else {
+            return;
+        };
+
+        log::info!(
+            "Voting {} on '{}'",
+            if yes { "yes" } else { "no" },
+            vote.issue
+        );
+        let cmd = if yes { "vote option1" } else { "vote option2" };
+        self.bus.lock().unwrap().send_rcon_cmd(cmd);
+
+        self.auto_voted = Some(key);
     }
 
     fn send_notifications(&mut self) {
@@ -315,10 +648,8 @@ impl Tf2bdThread {
             // Check if we have already informed the party about this player
             // TODO: Maybe store the list of flags we have informed about and inform about new flags
             if !self.notifications_send.contains(&player.steamid) {
-                let player_info = self
-                    .ruleset_handler
-                    .get_player_marking(&player.steamid)
-                    .cloned();
+                // Only trusted attributes, suggestions never notify the party
+                let (player_info, _) = self.get_player_marking(&lobby, player);
                 if let Some(player_info) = player_info {
                     log::info!("Player attributes: {:?}", player_info.attributes);
                     log::info!(
@@ -357,3 +688,233 @@ impl Tf2bdThread {
         }
     }
 }
+
+fn load_player_lists(settings: &AppSettings) -> Vec<RulesetHandler> {
+    let own_list = settings.own_player_list();
+
+    settings
+        .player_lists
+        .iter()
+        .map(|player_list| {
+            let mut ruleset_handler = RulesetHandler::new(
+                &player_list.filename,
+                player_list.suggestion,
+                player_list.format,
+                &settings.attribute_mapping,
+            );
+
+            // The same lists list_updater downloads
+            ruleset_handler.auto_updated = player_list.auto_update
+                && Some(player_list) != own_list
+                && list_updater::update_url(player_list, &ruleset_handler.tf2bd_playerlist)
+                    .is_some();
+
+            ruleset_handler
+        })
+        .collect()
+}
+
+/// Adds an attribute to the merged marking unless an earlier list already has set it
+fn add_marking(
+    attributes: &mut Vec<PlayerAttribute>,
+    sources: &mut MarkingSources,
+    attribute: PlayerAttribute,
+    source: &str,
+    suggestion: bool,
+) {
+    if sources.contains_key(&attribute) {
+        return;
+    }
+
+    attributes.push(attribute);
+    sources.insert(
+        attribute,
+        MarkingSource {
+            source: source.to_string(),
+            suggestion,
+        },
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        db::db,
+        models::app_settings::PlayerListSettings,
+        tf2::{
+            fake_tf2::{FakeTf2, FakeTf2Script},
+            lobby::{LobbyEventKind, lobby_thread},
+            logfile::logfile_watcher,
+            rcon::rcon_thread,
+        },
+    };
+    use std::time::{Duration, Instant};
+
+    const ME: u64 = 76561198012345678;
+
+    const PLAYER_LIST: &str = r#"{
+        "file_info": { "authors": ["TF2Monitor"], "description": "", "title": "Test list", "update_url": "" },
+        "players": [{ "attributes": ["bot"], "steamid": "[U:1:1218982957]" }]
+    }"#;
+
+    fn marking(source: &str) -> MarkingSource {
+        MarkingSource {
+            source: source.to_string(),
+            suggestion: false,
+        }
+    }
+
+    fn flags(db: &DbPool, steamid: SteamID) -> Vec<(String, String)> {
+        let mut conn = db.get().unwrap();
+        let mut flags: Vec<(String, String)> =
+            queries::get_player_flags(&mut conn, steamid.to_u64() as i64)
+                .unwrap()
+                .into_iter()
+                .map(|flag| (flag.flag_type, flag.source))
+                .collect();
+        flags.sort();
+        flags
+    }
+
+    #[test]
+    fn test_persist_markings() {
+        let db = db::connect_memory().unwrap();
+        let settings = AppSettings {
+            player_lists: Vec::new(),
+            ..Default::default()
+        };
+        let bus = Arc::new(Mutex::new(AppBus::default()));
+        let mut tf2bd_thread = Tf2bdThread::new(&settings, &bus, &db);
+        let steamid = SteamID::from_u64(76561198012345678);
+
+        // An imported flag is not from a marking and is kept
+        let imported = NewPlayerFlag {
+            steam_id: steamid.to_u64() as i64,
+            flag_type: "Cheater".to_string(),
+            source: "team".to_string(),
+            first_seen: 100,
+            last_seen: 100,
+            notified: false,
+            suggestion: false,
+            from_marking: false,
+        };
+        queries::upsert_player_flag(&mut db.get().unwrap(), imported).unwrap();
+
+        let sources = MarkingSources::from([
+            (PlayerAttribute::Cheater, marking("playerlist.json")),
+            (PlayerAttribute::Bot, marking("Bot rule")),
+        ]);
+        tf2bd_thread.persist_markings(steamid, sources);
+        assert_eq!(
+            flags(&db, steamid),
+            vec![
+                ("Bot".to_string(), "Bot rule".to_string()),
+                ("Cheater".to_string(), "playerlist.json".to_string()),
+                ("Cheater".to_string(), "team".to_string()),
+            ]
+        );
+
+        // The list was updated without the player
+        let sources = MarkingSources::from([(PlayerAttribute::Bot, marking("Bot rule"))]);
+        tf2bd_thread.persist_markings(steamid, sources);
+        assert_eq!(
+            flags(&db, steamid),
+            vec![
+                ("Bot".to_string(), "Bot rule".to_string()),
+                ("Cheater".to_string(), "team".to_string()),
+            ]
+        );
+
+        // The rule no longer matches
+        tf2bd_thread.persist_markings(steamid, MarkingSources::new());
+        assert_eq!(
+            flags(&db, steamid),
+            vec![("Cheater".to_string(), "team".to_string())]
+        );
+    }
+
+    /// Waits until the check returns something or the time runs out
+    fn wait_for<T>(timeout: Duration, check: impl Fn() -> Option<T>) -> Option<T> {
+        let started = Instant::now();
+        while started.elapsed() < timeout {
+            if let Some(result) = check() {
+                return Some(result);
+            }
+            sleep(Duration::from_millis(100));
+        }
+        None
+    }
+
+    /// The RCON, logfile, lobby and TF2BD threads together kick a bot from the player list
+    #[test]
+    fn test_auto_kick() {
+        let dir = tempfile::tempdir().unwrap();
+        let log_filename = dir.path().join("console.log");
+        let player_list = dir.path().join("playerlist.json");
+        std::fs::write(&player_list, PLAYER_LIST).unwrap();
+
+        let fake_tf2 = FakeTf2::start(
+            FakeTf2Script::load("src/tf2/fake_tf2_fixture.json").unwrap(),
+            0,
+            "rconpwd",
+            &log_filename,
+        )
+        .unwrap();
+
+        let settings = AppSettings {
+            log_filename: log_filename.to_str().unwrap().to_string(),
+            self_steamid64: SteamID::from_u64(ME),
+            rcon_password: "rconpwd".to_string(),
+            rcon_ip: "127.0.0.1".to_string(),
+            rcon_port: fake_tf2.port(),
+            kick_bots: true,
+            kick_cheaters: false,
+            player_lists: vec![PlayerListSettings {
+                filename: player_list.to_str().unwrap().to_string(),
+                suggestion: false,
+                auto_update: false,
+                format: None,
+                update_url: None,
+            }],
+            ..Default::default()
+        };
+        let db = db::connect_file(dir.path().join("appdata.sqlite").to_str().unwrap()).unwrap();
+        let bus = Arc::new(Mutex::new(AppBus::new(settings.self_steamid64)));
+
+        let threads = vec![
+            rcon_thread::start(&settings, &bus),
+            logfile_watcher::start(&settings, &bus),
+            lobby_thread::start(&settings, &bus, &db),
+            start(&settings, &bus, &db),
+        ];
+
+        let callvote = wait_for(Duration::from_secs(30), || {
+            fake_tf2
+                .commands()
+                .into_iter()
+                .find(|cmd| cmd.starts_with("callvote"))
+        });
+        assert_eq!(callvote.as_deref(), Some("callvote kick \"3 cheating\""));
+
+        // The player who joins later and the console line about it
+        let lobby = wait_for(Duration::from_secs(10), || {
+            let lobby = bus.lock().unwrap().shared_lobby.get();
+            let joined = lobby.players.len() == 4
+                && lobby
+                    .events
+                    .iter()
+                    .any(|event| event.kind == LobbyEventKind::Connected);
+            joined.then_some(lobby)
+        });
+        assert!(lobby.is_some());
+
+        bus.lock()
+            .unwrap()
+            .app_event_bus
+            .broadcast(AppEventMsg::Exit);
+        for thread in threads {
+            thread.join().unwrap();
+        }
+    }
+}